@@ -16,6 +16,8 @@ fn benchmark_throughput(c: &mut Criterion) {
             required: true,
             list: false,
             type_hint: None,
+            optional: false,
+            tables: Vec::new(),
         },
     );
     values.insert(
@@ -27,6 +29,8 @@ fn benchmark_throughput(c: &mut Criterion) {
             required: false,
             list: false,
             type_hint: None,
+            optional: false,
+            tables: Vec::new(),
         },
     );
 
@@ -40,6 +44,7 @@ fn benchmark_throughput(c: &mut Criterion) {
                 line_action: Action::Next,
                 record_action: Action::Record,
                 next_state: None,
+                table: None,
             }],
         },
     );
@@ -48,6 +53,10 @@ fn benchmark_throughput(c: &mut Criterion) {
         values,
         states,
         macros: HashMap::new(),
+        coverage_threshold: None,
+        field_order: Vec::new(),
+        columns: Vec::new(),
+        metrics: None,
     };
 
     let template = Template::from_ir(ir).unwrap();
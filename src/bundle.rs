@@ -0,0 +1,141 @@
+//! Collection bundles: per-device command output collected by Nornir, Netmiko scripts or
+//! Ansible, dumped as one JSON or YAML document.
+//!
+//! ```yaml
+//! r1:
+//!   show version: "Cisco IOS Software, ..."
+//!   show ip interface brief: |
+//!     Interface  IP-Address  OK? Method Status  Protocol
+//!     Gi0/0      10.0.0.1    YES manual up      up
+//! r2:
+//!   show version: "..."
+//! ```
+//!
+//! Hosts and commands keep their document order.
+
+use anyhow::{Context, Result, bail};
+use indexmap::IndexMap;
+use serde_json::Value;
+use std::path::Path;
+
+/// One command's output for one host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundleEntry {
+    pub host: String,
+    pub command: String,
+    pub output: String,
+}
+
+type Document = IndexMap<String, IndexMap<String, Value>>;
+
+/// Read a bundle, as YAML for `*.yaml`/`*.yml` files and JSON otherwise.
+///
+/// Outputs that are not strings (e.g. a tool's structured result or a failed task's error
+/// object) are skipped with a warning.
+pub fn load(path: &Path, warnings: &mut Vec<String>) -> Result<Vec<BundleEntry>> {
+    let raw = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read bundle {}", path.display()))?;
+    let yaml = path
+        .extension()
+        .is_some_and(|ext| ext == "yaml" || ext == "yml");
+    let document: Document = if yaml {
+        serde_yaml_ng::from_str(&raw).map_err(anyhow::Error::from)
+    } else {
+        serde_json::from_str(&raw).map_err(anyhow::Error::from)
+    }
+    .with_context(|| {
+        format!(
+            "Invalid bundle {}: expected a mapping of host -> command -> output",
+            path.display()
+        )
+    })?;
+    entries(document, warnings)
+}
+
+fn entries(document: Document, warnings: &mut Vec<String>) -> Result<Vec<BundleEntry>> {
+    let mut entries = Vec::new();
+    for (host, commands) in document {
+        for (command, output) in commands {
+            match output {
+                Value::String(output) => entries.push(BundleEntry {
+                    host: host.clone(),
+                    command,
+                    output,
+                }),
+                other => warnings.push(format!(
+                    "Bundle output of '{}' on {} is {}, not text - skipping",
+                    command,
+                    host,
+                    kind(&other)
+                )),
+            }
+        }
+    }
+    if entries.is_empty() {
+        bail!("Bundle holds no command output");
+    }
+    Ok(entries)
+}
+
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "a list",
+        Value::Object(_) => "a mapping",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(host: &str, command: &str, output: &str) -> BundleEntry {
+        BundleEntry {
+            host: host.to_string(),
+            command: command.to_string(),
+            output: output.to_string(),
+        }
+    }
+
+    #[test]
+    fn keeps_document_order_and_skips_non_text_outputs() {
+        let document: Document = serde_yaml_ng::from_str(concat!(
+            "r2:\n",
+            "  show version: \"Version 2\"\n",
+            "  show clock: null\n",
+            "r1:\n",
+            "  show version: |\n",
+            "    Version 1\n",
+            "  show ip route: { failed: true }\n",
+        ))
+        .unwrap();
+        let mut warnings = Vec::new();
+
+        let entries = entries(document, &mut warnings).unwrap();
+
+        assert_eq!(
+            entries,
+            [
+                entry("r2", "show version", "Version 2"),
+                entry("r1", "show version", "Version 1\n"),
+            ]
+        );
+        assert_eq!(
+            warnings,
+            [
+                "Bundle output of 'show clock' on r2 is null, not text - skipping",
+                "Bundle output of 'show ip route' on r1 is a mapping, not text - skipping",
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_bundles_without_output() {
+        let document: Document = serde_json::from_str(r#"{"r1": {"show clock": 1}}"#).unwrap();
+        let err = entries(document, &mut Vec::new()).unwrap_err();
+        assert_eq!(err.to_string(), "Bundle holds no command output");
+    }
+}
@@ -1,5 +1,7 @@
 use crate::logging::LogFormat;
-use clap::{Parser, Subcommand, ValueEnum};
+use crate::query;
+use crate::transcript::{CleanupOptions, PromptDialect, SessionFormat, TranscriptOptions};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
 #[derive(Parser)]
@@ -27,9 +29,32 @@ pub struct Cli {
 pub enum Commands {
     /// Parse a raw text file using a template
     Parse {
-        /// Template spec (path or identifier)
-        #[arg(short, long, value_name = "TEMPLATE")]
-        template: String,
+        /// Template spec (path or identifier), or `auto` to pick the best-scoring template
+        /// for the input (see `identify`)
+        #[arg(
+            short,
+            long,
+            value_name = "TEMPLATE",
+            required_unless_present_any = ["platform", "auto_template", "bundle"],
+            conflicts_with_all = ["platform", "auto_template", "bundle"]
+        )]
+        template: Option<String>,
+
+        /// Select the template from the command index by platform (e.g. cisco_ios)
+        #[arg(long, value_name = "PLATFORM")]
+        platform: Option<String>,
+
+        /// Command whose output is parsed, matched against the command index (e.g. "sh ver")
+        #[arg(long, value_name = "COMMAND", requires = "platform")]
+        command: Option<String>,
+
+        /// Parse each transcript block with the template the command index gives for its
+        /// echoed command (narrowed by --platform, if given); output is keyed by template name
+        #[arg(
+            long,
+            conflicts_with_all = ["command", "output_dir", "group_by_source", "path_capture"]
+        )]
+        auto_template: bool,
 
         /// Override template format selection (default: auto from extension)
         #[arg(long, value_enum, default_value_t = TemplateFormat::Auto)]
@@ -55,6 +80,29 @@ pub enum Commands {
         #[arg(short, long, value_enum, default_value_t = OutputFormat::Auto)]
         format: OutputFormat,
 
+        /// Write output to PATH instead of stdout (required for --format sqlite)
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<PathBuf>,
+
+        /// Write one output file per input source into DIR instead of stdout
+        #[arg(long, value_name = "DIR", conflicts_with_all = ["output", "group_by_source"])]
+        output_dir: Option<PathBuf>,
+
+        /// File name for each --output-dir file; expands {stem}, {name}, {parent}, {index} and
+        /// {format}
+        #[arg(
+            long,
+            value_name = "PATTERN",
+            default_value = "{stem}.{format}",
+            requires = "output_dir"
+        )]
+        output_name: String,
+
+        /// Emit one object keyed by input source (source#N per transcript block when a source
+        /// holds several) instead of a flat record list (json and yaml)
+        #[arg(long)]
+        group_by_source: bool,
+
         /// Suppress the success status line (warnings still print)
         #[arg(long)]
         quiet: bool,
@@ -63,13 +111,143 @@ pub enum Commands {
         #[arg(long)]
         strict: bool,
 
-        /// Minimum required field capture percentage (default: 80.0)
-        #[arg(long, default_value_t = 80.0)]
-        threshold: f64,
+        /// Minimum aggregate field capture percentage (default: template's coverage_threshold, else 80.0)
+        #[arg(long)]
+        threshold: Option<f64>,
 
         /// Maximum parsing time in milliseconds
         #[arg(long)]
         timeout: Option<u64>,
+
+        /// Set a template variable referenced as ${var:NAME} in rule regexes (repeatable)
+        #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_key_val)]
+        vars: Vec<(String, String)>,
+
+        /// Add a constant column to every emitted record (repeatable); template fields of the
+        /// same name keep their parsed value
+        #[arg(long = "set", value_name = "FIELD=VALUE", value_parser = parse_key_val)]
+        constants: Vec<(String, String)>,
+
+        /// With --format ndjson, also write warnings as {"warning": {...}} lines
+        #[arg(long)]
+        ndjson_warnings: bool,
+
+        #[command(flatten)]
+        transcript: TranscriptArgs,
+
+        /// Regex with named groups matched against each input file path; groups become columns
+        /// unless the template already has a field of that name
+        #[arg(long, value_name = "REGEX", value_parser = parse_path_capture)]
+        path_capture: Option<Box<regex::Regex>>,
+
+        #[command(flatten)]
+        routes: Box<RouteArgs>,
+
+        #[command(flatten)]
+        query: Box<QueryArgs>,
+
+        #[command(flatten)]
+        render: Box<RenderArgs>,
+    },
+    /// Parse a multi-command transcript with several templates and join the results
+    Pipeline {
+        /// Pipeline spec (YAML) routing commands to templates and describing joins
+        #[arg(short, long, value_name = "SPEC")]
+        spec: PathBuf,
+
+        /// Input paths (0+), one device capture each; with several, records gain a `source`
+        /// column
+        #[arg(value_name = "INPUTS", num_args = 0..)]
+        inputs: Vec<PathBuf>,
+
+        /// Add an input path (repeatable)
+        #[arg(long, value_name = "PATH")]
+        input: Vec<PathBuf>,
+
+        /// Add an input glob pattern (repeatable; expanded by app code)
+        #[arg(long, value_name = "PATTERN")]
+        input_glob: Vec<String>,
+
+        /// Include stdin as an input source (in addition to file inputs)
+        #[arg(long)]
+        stdin: bool,
+
+        #[command(flatten)]
+        transcript: TranscriptArgs,
+
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Auto)]
+        format: OutputFormat,
+
+        /// Suppress the success status line (warnings still print)
+        #[arg(long)]
+        quiet: bool,
+
+        #[command(flatten)]
+        render: RenderArgs,
+    },
+    /// Compare two captures of the same command parsed with one template
+    Diff {
+        /// Template spec (path or identifier)
+        #[arg(short, long, value_name = "TEMPLATE")]
+        template: String,
+
+        /// Override template format selection (default: auto from extension)
+        #[arg(long, value_enum, default_value_t = TemplateFormat::Auto)]
+        template_format: TemplateFormat,
+
+        /// Capture taken before the change
+        #[arg(value_name = "BEFORE")]
+        before: PathBuf,
+
+        /// Capture taken after the change
+        #[arg(value_name = "AFTER")]
+        after: PathBuf,
+
+        /// Columns identifying a record (comma-separated or repeatable; default: the template's
+        /// Required fields, else whole records)
+        #[arg(long, value_name = "FIELDS", value_delimiter = ',')]
+        key: Vec<String>,
+
+        #[command(flatten)]
+        transcript: TranscriptArgs,
+
+        /// Output format (table, json or yaml)
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Auto)]
+        format: OutputFormat,
+
+        /// Exit with status 1 when the captures differ (errors always exit with status 2)
+        #[arg(long)]
+        exit_code: bool,
+
+        /// Suppress the success status line (warnings still print)
+        #[arg(long)]
+        quiet: bool,
+    },
+    /// Rank embedded and user templates by how well they parse a sample capture
+    Identify {
+        /// Capture to identify (stdin when omitted)
+        #[arg(value_name = "INPUT")]
+        input: Option<PathBuf>,
+
+        #[command(flatten)]
+        transcript: TranscriptArgs,
+
+        /// Show at most N candidates
+        #[arg(long, value_name = "N", default_value_t = 5)]
+        limit: usize,
+
+        /// Also list templates that emit no records
+        #[arg(long)]
+        all: bool,
+
+        /// Output format (table, json or yaml)
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Auto)]
+        format: OutputFormat,
+
+        /// Suppress the success status line (warnings still print)
+        #[arg(long)]
+        quiet: bool,
     },
     /// Launch the TUI debugger
     Debug {
@@ -125,6 +303,76 @@ pub enum Commands {
     },
 }
 
+/// Post-parse filtering and projection options (see `query.rs`).
+#[derive(Args)]
+pub struct QueryArgs {
+    /// Keep only records matching EXPR, e.g. "STATUS == down and MTU > 1500" (repeatable; all must match)
+    #[arg(long = "where", value_name = "EXPR", value_parser = query::parse_expr)]
+    pub filters: Vec<query::Expr>,
+
+    /// Select and rename columns: NAME or NAME:ALIAS (comma-separated or repeatable)
+    #[arg(long, value_name = "FIELDS", value_delimiter = ',', value_parser = query::parse_field)]
+    pub fields: Vec<query::FieldSpec>,
+
+    /// Sort records by NAME[:asc|desc] (comma-separated or repeatable; earlier keys win)
+    #[arg(long, value_name = "KEYS", value_delimiter = ',', value_parser = query::parse_sort_key)]
+    pub sort: Vec<query::SortKey>,
+
+    /// Emit at most N records (after filtering and sorting)
+    #[arg(long, value_name = "N")]
+    pub limit: Option<usize>,
+
+    /// jq-like path applied to the record array before filtering, e.g. '.[].ADDRESSES[]'
+    #[arg(long, value_name = "PATH", value_parser = query::parse_path)]
+    pub query: Option<query::PathQuery>,
+}
+
+/// CSV and table rendering options (see `output.rs`).
+#[derive(Args, Clone, Default)]
+pub struct RenderArgs {
+    /// Join list values in CSV/table cells with DELIM (default: newline)
+    #[arg(long, value_name = "DELIM", conflicts_with = "explode_lists")]
+    pub list_delimiter: Option<String>,
+
+    /// Emit one CSV/table row per list item instead of joining list values
+    #[arg(long)]
+    pub explode_lists: bool,
+
+    /// Text shown in CSV/table cells for null, missing or empty values
+    #[arg(long, value_name = "TEXT", default_value = "")]
+    pub null_text: String,
+
+    /// Table width in columns (default: terminal width when stdout is a terminal)
+    #[arg(long, value_name = "COLS")]
+    pub table_width: Option<u16>,
+
+    /// How table cells wider than their column are fitted
+    #[arg(long, value_enum, default_value_t = TableOverflow::Wrap)]
+    pub table_overflow: TableOverflow,
+
+    /// Table layout: one row per record, or one block of FIELD | VALUE lines per record
+    #[arg(long, value_enum, default_value_t = TableLayout::Horizontal)]
+    pub table_layout: TableLayout,
+}
+
+#[derive(Copy, Clone, Default, PartialEq, Eq, ValueEnum)]
+pub enum TableOverflow {
+    /// Wrap long cells onto several lines
+    #[default]
+    Wrap,
+    /// Cut long cells to a single line ending in an ellipsis
+    Truncate,
+}
+
+#[derive(Copy, Clone, Default, PartialEq, Eq, ValueEnum)]
+pub enum TableLayout {
+    /// One row per record
+    #[default]
+    Horizontal,
+    /// One block per record, for templates with many columns
+    Vertical,
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 pub enum TemplateFormat {
     /// Infer template format from file extension
@@ -137,16 +385,136 @@ pub enum TemplateFormat {
     Toml,
 }
 
+/// How session captures are cleaned and split into command blocks.
+#[derive(Args, Clone)]
+pub struct TranscriptArgs {
+    /// Prompt style used to split transcripts into command blocks (default: detected)
+    #[arg(long, value_enum, value_name = "DIALECT", default_value_t = TranscriptDialect::Auto)]
+    pub transcript_dialect: TranscriptDialect,
+
+    /// Remove syslog lines (e.g. %LINK-3-UPDOWN) interleaved with command output
+    #[arg(long)]
+    pub strip_syslog: bool,
+
+    /// Keep pager prompts, backspace/carriage-return redraws and bells as captured
+    #[arg(long, conflicts_with = "strip_syslog")]
+    pub keep_terminal_noise: bool,
+
+    /// Session log format of the inputs (default: detected from file name and content)
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t = InputFormat::Auto)]
+    pub input_format: InputFormat,
+}
+
+impl TranscriptArgs {
+    pub fn options(&self) -> TranscriptOptions {
+        let cleanup = if self.keep_terminal_noise {
+            CleanupOptions::none()
+        } else {
+            CleanupOptions {
+                syslog: self.strip_syslog,
+                ..CleanupOptions::default()
+            }
+        };
+        TranscriptOptions {
+            dialect: self.transcript_dialect.dialect(),
+            cleanup,
+            input_format: self.input_format.session_format(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum InputFormat {
+    /// Detect from the file name and content
+    Auto,
+    /// Plain capture
+    Plain,
+    /// SecureCRT/PuTTY log with per-line timestamps (detected only from the PuTTY banner or
+    /// bracketed stamps before prompts)
+    Timestamped,
+    /// script(1) typescript
+    Typescript,
+    /// asciinema .cast recording
+    Asciicast,
+}
+
+impl InputFormat {
+    pub fn session_format(self) -> SessionFormat {
+        match self {
+            InputFormat::Auto => SessionFormat::Auto,
+            InputFormat::Plain => SessionFormat::Plain,
+            InputFormat::Timestamped => SessionFormat::Timestamped,
+            InputFormat::Typescript => SessionFormat::Typescript,
+            InputFormat::Asciicast => SessionFormat::Asciicast,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum TranscriptDialect {
+    /// Detect from the prompts in each input
+    Auto,
+    /// Cisco IOS and lookalikes (`host#`, `host(config)#`)
+    Ios,
+    /// Juniper Junos (`user@host>`, `[edit]`)
+    Junos,
+    /// Huawei VRP and H3C Comware (`<host>`, `[host]`)
+    Huawei,
+    /// MikroTik RouterOS (`[admin@host] >`)
+    Mikrotik,
+    /// Nokia SR OS (`A:host#`, MD-CLI `[/]`)
+    Sros,
+    /// POSIX shells (`user@host:~$`)
+    Linux,
+    /// Fortinet FortiOS (`host # `)
+    Fortios,
+}
+
+impl TranscriptDialect {
+    /// The forced prompt dialect, or `None` to detect one per input.
+    pub fn dialect(self) -> Option<&'static dyn PromptDialect> {
+        use crate::transcript::dialect;
+        match self {
+            TranscriptDialect::Auto => None,
+            TranscriptDialect::Ios => Some(&dialect::Ios),
+            TranscriptDialect::Junos => Some(&dialect::Junos),
+            TranscriptDialect::Huawei => Some(&dialect::Huawei),
+            TranscriptDialect::Mikrotik => Some(&dialect::Mikrotik),
+            TranscriptDialect::Sros => Some(&dialect::Sros),
+            TranscriptDialect::Linux => Some(&dialect::Linux),
+            TranscriptDialect::Fortios => Some(&dialect::Fortios),
+        }
+    }
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 pub enum OutputFormat {
     /// Auto-select output format
     Auto,
     /// JSON output
     Json,
+    /// JSON document with records, warnings, stats and template identity (`ok: false` and the
+    /// error instead of records when parsing fails)
+    JsonEnvelope,
+    /// Newline-delimited JSON, one record per line (streamed as input blocks are parsed)
+    Ndjson,
     /// CSV output (placeholder)
     Csv,
     /// Table output (placeholder)
     Table,
+    /// YAML sequence of records
+    Yaml,
+    /// TOML array of tables (`[[records]]`); null fields are omitted
+    Toml,
+    /// GitHub-flavoured Markdown table, one section per input when several are parsed
+    Markdown,
+    /// Standalone HTML document with escaped tables, one section per input when several are parsed
+    Html,
+    /// Append records to a SQLite database (requires --output)
+    Sqlite,
+    /// OpenMetrics text exposition of the fields mapped in the template's `metadata.metrics`
+    #[value(name = "openmetrics")]
+    OpenMetrics,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
@@ -164,3 +532,52 @@ pub enum ConvertFormat {
     /// Modern TOML template
     Toml,
 }
+
+/// Per-command template routing (`parse --auto-template` and `parse --bundle`) and command
+/// tagging.
+#[derive(Args, Clone, Default)]
+pub struct RouteArgs {
+    /// Add a column holding the transcript command each record was parsed from
+    #[arg(long, value_name = "NAME", conflicts_with = "auto_template")]
+    pub command_column: Option<String>,
+
+    /// Parse a collection bundle (JSON, or YAML for *.yaml/*.yml) mapping host -> command ->
+    /// output; each output is parsed like an --auto-template block and records gain `host`
+    /// and `command` columns
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = [
+            "command", "inputs", "input", "input_glob", "stdin", "output_dir",
+            "group_by_source", "path_capture", "command_column"
+        ]
+    )]
+    pub bundle: Option<PathBuf>,
+
+    /// Route COMMAND to TEMPLATE ahead of the command index (repeatable)
+    #[arg(long, value_name = "COMMAND=TEMPLATE", value_parser = parse_key_val)]
+    pub template_map: Vec<(String, String)>,
+}
+
+/// Parse a `KEY=VALUE` argument (value may be empty or contain further `=`).
+fn parse_key_val(s: &str) -> Result<(String, String), String> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, got '{s}'"))?;
+    let key = key.trim();
+    if key.is_empty() {
+        return Err(format!("missing name before '=' in '{s}'"));
+    }
+    Ok((key.to_string(), value.to_string()))
+}
+
+/// Compile a `--path-capture` regex, requiring at least one named group.
+fn parse_path_capture(s: &str) -> Result<Box<regex::Regex>, String> {
+    let re = regex::Regex::new(s).map_err(|e| e.to_string())?;
+    if re.capture_names().flatten().next().is_none() {
+        return Err(
+            "pattern must contain at least one named group, e.g. (?P<hostname>[^/]+)".to_string(),
+        );
+    }
+    Ok(Box::new(re))
+}
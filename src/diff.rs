@@ -0,0 +1,268 @@
+//! Snapshot diff: align the records of two captures of the same command and report what
+//! changed between them.
+//!
+//! Records pair up by key columns (`--key`, else the template's `Required` fields); records
+//! sharing a key pair up in capture order. Without key columns records only pair up when
+//! identical, so every difference shows as a removed and an added record.
+
+use comfy_table::{Table, presets};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+pub type Record = BTreeMap<String, Value>;
+
+/// Differences between the records of one table in two captures.
+#[derive(Debug, Default, Serialize)]
+pub struct TableDiff {
+    /// Columns the records were aligned by.
+    pub key: Vec<String>,
+    pub added: Vec<Record>,
+    pub removed: Vec<Record>,
+    pub changed: Vec<ChangedRecord>,
+    /// Number of paired records with no differences.
+    pub unchanged: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChangedRecord {
+    /// Key column values identifying the record.
+    pub key: Record,
+    pub changes: Vec<FieldChange>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    /// Null when the field is absent from the record.
+    pub old: Value,
+    pub new: Value,
+}
+
+impl TableDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Diff `before` against `after`. Changed fields follow `columns`, then other fields by name;
+/// added and changed records keep `after` order, removed records keep `before` order.
+pub fn diff_records(
+    before: Vec<Record>,
+    after: Vec<Record>,
+    key: &[String],
+    columns: &[String],
+) -> TableDiff {
+    let identity = |record: &Record| -> String {
+        if key.is_empty() {
+            return serde_json::to_string(record).unwrap_or_default();
+        }
+        let values: Vec<&Value> = key
+            .iter()
+            .map(|k| record.get(k).unwrap_or(&Value::Null))
+            .collect();
+        serde_json::to_string(&values).unwrap_or_default()
+    };
+
+    let mut pending: HashMap<String, VecDeque<usize>> = HashMap::new();
+    for (idx, record) in before.iter().enumerate() {
+        pending.entry(identity(record)).or_default().push_back(idx);
+    }
+
+    let mut diff = TableDiff {
+        key: key.to_vec(),
+        ..TableDiff::default()
+    };
+    let mut paired = vec![false; before.len()];
+    for record in after {
+        let Some(idx) = pending
+            .get_mut(&identity(&record))
+            .and_then(VecDeque::pop_front)
+        else {
+            diff.added.push(record);
+            continue;
+        };
+        paired[idx] = true;
+
+        let changes = field_changes(&before[idx], &record, columns);
+        if changes.is_empty() {
+            diff.unchanged += 1;
+        } else {
+            let key = key
+                .iter()
+                .filter_map(|k| Some((k.clone(), record.get(k)?.clone())))
+                .collect();
+            diff.changed.push(ChangedRecord { key, changes });
+        }
+    }
+    diff.removed = before
+        .into_iter()
+        .zip(paired)
+        .filter(|(_, paired)| !paired)
+        .map(|(record, _)| record)
+        .collect();
+    diff
+}
+
+fn field_changes(old: &Record, new: &Record, columns: &[String]) -> Vec<FieldChange> {
+    let mut fields: Vec<&String> = old.keys().chain(new.keys()).collect();
+    fields.sort_by_key(|f| {
+        (
+            columns.iter().position(|c| c == *f).unwrap_or(usize::MAX),
+            *f,
+        )
+    });
+    fields.dedup();
+    fields
+        .into_iter()
+        .filter_map(|field| {
+            let (old, new) = (old.get(field), new.get(field));
+            (old != new).then(|| FieldChange {
+                field: field.clone(),
+                old: old.cloned().unwrap_or(Value::Null),
+                new: new.cloned().unwrap_or(Value::Null),
+            })
+        })
+        .collect()
+}
+
+/// Human-readable diff: one row per removed or added record and per changed field, then a
+/// summary line.
+pub fn render_table(diff: &TableDiff) -> String {
+    let mut out = String::new();
+    if !diff.is_empty() {
+        let mut table = Table::new();
+        table.load_preset(presets::UTF8_FULL);
+        table.set_header(vec!["Change", "Key", "Field", "Old", "New"]);
+        for record in &diff.removed {
+            let (key, rest) = split_key(record, &diff.key);
+            table.add_row(vec!["removed", &key, "", &rest, ""]);
+        }
+        for record in &diff.added {
+            let (key, rest) = split_key(record, &diff.key);
+            table.add_row(vec!["added", &key, "", "", &rest]);
+        }
+        for record in &diff.changed {
+            let (key, _) = split_key(&record.key, &diff.key);
+            for change in &record.changes {
+                table.add_row(vec![
+                    "changed",
+                    &key,
+                    &change.field,
+                    &value_text(&change.old),
+                    &value_text(&change.new),
+                ]);
+            }
+        }
+        out.push_str(&table.to_string());
+        out.push('\n');
+    }
+    out.push_str(&format!(
+        "{} added, {} removed, {} changed, {} unchanged",
+        diff.added.len(),
+        diff.removed.len(),
+        diff.changed.len(),
+        diff.unchanged
+    ));
+    out
+}
+
+/// `name=value` text of the key columns and of the remaining fields.
+fn split_key(record: &Record, key: &[String]) -> (String, String) {
+    let pairs = |keep: bool| {
+        record
+            .iter()
+            .filter(|(name, _)| key.contains(name) == keep)
+            .map(|(name, value)| format!("{}={}", name, value_text(value)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    (pairs(true), pairs(false))
+}
+
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn record(value: Value) -> Record {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn key(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn keyed_diff_reports_added_removed_and_changed_fields() {
+        let before = vec![
+            record(json!({ "port": "Gi0/1", "mtu": 1500, "status": "up" })),
+            record(json!({ "port": "Gi0/2", "mtu": 1500, "status": "up" })),
+            record(json!({ "port": "Gi0/3", "mtu": 1500, "status": "up" })),
+        ];
+        let after = vec![
+            record(json!({ "port": "Gi0/1", "mtu": 1500, "status": "up" })),
+            record(json!({ "port": "Gi0/2", "mtu": 9000, "status": "down" })),
+            record(json!({ "port": "Gi0/4", "mtu": 1500, "status": "up" })),
+        ];
+
+        let diff = diff_records(before, after, &key(&["port"]), &key(&["port", "status"]));
+
+        assert_eq!(diff.unchanged, 1);
+        assert_eq!(diff.added[0]["port"], "Gi0/4");
+        assert_eq!(diff.removed[0]["port"], "Gi0/3");
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].key, record(json!({ "port": "Gi0/2" })));
+        let fields: Vec<&str> = diff.changed[0]
+            .changes
+            .iter()
+            .map(|c| c.field.as_str())
+            .collect();
+        assert_eq!(
+            fields,
+            ["status", "mtu"],
+            "columns order first, then by name"
+        );
+        assert_eq!(diff.changed[0].changes[1].old, json!(1500));
+        assert_eq!(diff.changed[0].changes[1].new, json!(9000));
+    }
+
+    #[test]
+    fn duplicate_keys_pair_in_capture_order() {
+        let before = vec![
+            record(json!({ "vlan": "10", "port": "Gi0/1" })),
+            record(json!({ "vlan": "10", "port": "Gi0/2" })),
+        ];
+        let after = vec![record(json!({ "vlan": "10", "port": "Gi0/1" }))];
+
+        let diff = diff_records(before, after, &key(&["vlan"]), &[]);
+
+        assert_eq!(diff.unchanged, 1);
+        assert_eq!(
+            diff.removed,
+            vec![record(json!({ "vlan": "10", "port": "Gi0/2" }))]
+        );
+        assert!(diff.added.is_empty() && diff.changed.is_empty());
+    }
+
+    #[test]
+    fn unkeyed_diff_only_pairs_identical_records() {
+        let before = vec![record(json!({ "port": "Gi0/1", "mtu": 1500 }))];
+        let after = vec![record(json!({ "port": "Gi0/1", "mtu": 9000 }))];
+
+        let diff = diff_records(before, after, &[], &[]);
+
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.added.len(), 1);
+        assert!(diff.changed.is_empty());
+        assert!(render_table(&diff).ends_with("1 added, 1 removed, 0 changed, 0 unchanged"));
+    }
+}
@@ -1,6 +1,9 @@
 use serde_json::Value;
 use std::collections::BTreeMap;
 
+/// Coverage threshold used when neither the caller nor the template declares one.
+pub const DEFAULT_COVERAGE_THRESHOLD: f64 = 80.0;
+
 #[derive(Debug, Clone)]
 pub struct CoverageReport {
     pub percentage: f64,
@@ -9,17 +12,34 @@ pub struct CoverageReport {
     pub total_expected: usize,
 }
 
+/// Coverage across a whole result set rather than a single record.
+#[derive(Debug, Clone)]
+pub struct CoverageSummary {
+    /// Captured (record, field) pairs as a percentage of all expected pairs.
+    pub percentage: f64,
+    pub record_count: usize,
+    pub total_expected: usize,
+    /// Per-field percentage of records in which the field was captured.
+    pub field_rates: BTreeMap<String, f64>,
+    /// Expected fields never captured in any record.
+    pub missing_fields: Vec<String>,
+}
+
+fn is_captured(v: &Value) -> bool {
+    match v {
+        Value::String(s) => !s.is_empty(),
+        Value::Array(a) => !a.is_empty(),
+        _ => true, // numbers etc are considered captured
+    }
+}
+
 pub fn calculate_coverage(
     parsed_record: &BTreeMap<String, Value>,
     template_fields: &[String],
 ) -> CoverageReport {
     let captured: Vec<String> = parsed_record
         .iter()
-        .filter(|(_, v)| match v {
-            Value::String(s) => !s.is_empty(),
-            Value::Array(a) => !a.is_empty(),
-            _ => true, // numbers etc are considered captured
-        })
+        .filter(|(_, v)| is_captured(v))
         .map(|(k, _)| k.clone())
         .collect();
 
@@ -32,7 +52,8 @@ pub fn calculate_coverage(
     let percentage = if template_fields.is_empty() {
         100.0 // Edge case: no expected fields
     } else {
-        (captured.len() as f64 / template_fields.len() as f64) * 100.0
+        // Only expected fields count; optional or injected columns must not inflate the ratio.
+        ((template_fields.len() - missing.len()) as f64 / template_fields.len() as f64) * 100.0
     };
 
     CoverageReport {
@@ -42,3 +63,90 @@ pub fn calculate_coverage(
         total_expected: template_fields.len(),
     }
 }
+
+pub fn calculate_aggregate_coverage(
+    records: &[BTreeMap<String, Value>],
+    template_fields: &[String],
+) -> CoverageSummary {
+    let mut field_rates = BTreeMap::new();
+    let mut missing_fields = Vec::new();
+    let mut captured_pairs = 0usize;
+
+    for field in template_fields {
+        let hits = records
+            .iter()
+            .filter(|r| r.get(field).is_some_and(is_captured))
+            .count();
+        captured_pairs += hits;
+        if hits == 0 {
+            missing_fields.push(field.clone());
+        }
+        let rate = if records.is_empty() {
+            0.0
+        } else {
+            (hits as f64 / records.len() as f64) * 100.0
+        };
+        field_rates.insert(field.clone(), rate);
+    }
+
+    let expected_pairs = records.len() * template_fields.len();
+    let percentage = if expected_pairs == 0 {
+        100.0
+    } else {
+        (captured_pairs as f64 / expected_pairs as f64) * 100.0
+    };
+
+    CoverageSummary {
+        percentage,
+        record_count: records.len(),
+        total_expected: template_fields.len(),
+        field_rates,
+        missing_fields,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(pairs: &[(&str, &str)]) -> BTreeMap<String, Value> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), Value::String(v.to_string())))
+            .collect()
+    }
+
+    #[test]
+    fn aggregate_coverage_averages_over_records() {
+        let records = vec![
+            record(&[("iface", "Gi0/1"), ("desc", "uplink")]),
+            record(&[("iface", "Gi0/2"), ("desc", "")]),
+        ];
+        let fields = vec!["iface".to_string(), "desc".to_string()];
+        let summary = calculate_aggregate_coverage(&records, &fields);
+
+        assert_eq!(summary.record_count, 2);
+        assert_eq!(summary.percentage, 75.0);
+        assert_eq!(summary.field_rates["iface"], 100.0);
+        assert_eq!(summary.field_rates["desc"], 50.0);
+        assert!(summary.missing_fields.is_empty());
+    }
+
+    #[test]
+    fn aggregate_coverage_lists_fields_never_captured() {
+        let records = vec![record(&[("iface", "Gi0/1")])];
+        let fields = vec!["iface".to_string(), "serial".to_string()];
+        let summary = calculate_aggregate_coverage(&records, &fields);
+
+        assert_eq!(summary.missing_fields, vec!["serial".to_string()]);
+    }
+
+    #[test]
+    fn record_coverage_ignores_keys_outside_expected_fields() {
+        let rec = record(&[("iface", "Gi0/1"), ("extra", "x")]);
+        let fields = vec!["iface".to_string(), "serial".to_string()];
+        let report = calculate_coverage(&rec, &fields);
+
+        assert_eq!(report.percentage, 50.0);
+    }
+}
@@ -1,14 +1,31 @@
-use crate::engine::macros::expand_macros;
+use crate::engine::coverage::{DEFAULT_COVERAGE_THRESHOLD, calculate_aggregate_coverage};
+use crate::engine::macros::{expand_macros, expand_vars};
 use crate::engine::records::RecordBuffer;
 use crate::engine::types::*;
 use crate::engine::{convert::convert_scalar, debug::*};
-use crate::{DetailedParseError, ParseOptions, ScraperError, TemplateWarning};
+use crate::{DetailedParseError, ParseOptions, RecordTables, ScraperError, TemplateWarning};
 use regex::Regex;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Arc, PoisonError};
+
+/// Emitted records, each tagged with the record table it belongs to.
+type TaggedRecords = Vec<(String, BTreeMap<String, serde_json::Value>)>;
 
 impl Template {
     pub fn from_ir(ir: TemplateIR) -> Result<Self, ScraperError> {
+        Self::from_ir_with_vars(ir, &BTreeMap::new())
+    }
+
+    /// Compile a template, substituting `${var:name}` variables from `vars`.
+    ///
+    /// Variables without a value are left unbound; parsing fails until they are supplied
+    /// through `ParseOptions::vars`.
+    pub fn from_ir_with_vars(
+        ir: TemplateIR,
+        vars: &BTreeMap<String, String>,
+    ) -> Result<Self, ScraperError> {
         let mut compiled_states = HashMap::new();
+        let mut referenced_vars = BTreeSet::new();
 
         for (state_name, state) in &ir.states {
             let mut compiled_rules = Vec::new();
@@ -33,6 +50,9 @@ impl Template {
                     final_regex_str = final_regex_str.replace(&placeholder, &replacement);
                 }
 
+                // 2a. Expand parse-time variables ${var:name}
+                final_regex_str = expand_vars(&final_regex_str, vars, &mut referenced_vars);
+
                 // 2b. Validate no undefined tokens remain
                 static LEFTOVER_TOKEN_RE: std::sync::OnceLock<regex::Regex> =
                     std::sync::OnceLock::new();
@@ -69,6 +89,7 @@ impl Template {
                     line_action: rule.line_action.clone(),
                     record_action: rule.record_action.clone(),
                     next_state: rule.next_state.clone(),
+                    table: rule.table.clone(),
                 });
             }
             compiled_states.insert(state_name.clone(), compiled_rules);
@@ -81,16 +102,64 @@ impl Template {
             ));
         }
 
+        let tables = build_tables(&ir)?;
+        let ColumnLayout {
+            field_order,
+            columns,
+            hidden,
+        } = build_columns(&ir)?;
+
+        // Keep the source around only when variables may need (re)binding at parse time.
+        let unbound_vars: BTreeSet<String> = referenced_vars
+            .iter()
+            .filter(|name| !vars.contains_key(*name))
+            .cloned()
+            .collect();
+        let source = if referenced_vars.is_empty() {
+            None
+        } else {
+            Some(ir.clone())
+        };
+
         Ok(Template {
             states: compiled_states,
             values: ir.values,
+            tables,
+            coverage_threshold: ir.coverage_threshold,
+            field_order,
+            columns,
+            hidden,
+            metrics: ir.metrics,
+            unbound_vars,
+            source,
+            bound: BoundTemplate::default(),
         })
     }
 
+    /// This template compiled with `vars` bound, or `None` when no rule references variables.
+    /// The last binding is reused while `vars` stay the same.
+    fn bind_vars(
+        &self,
+        vars: &BTreeMap<String, String>,
+    ) -> Result<Option<Arc<Template>>, ScraperError> {
+        let Some(source) = &self.source else {
+            return Ok(None);
+        };
+        let mut cache = self.bound.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some((cached_vars, template)) = cache.as_ref()
+            && cached_vars == vars
+        {
+            return Ok(Some(Arc::clone(template)));
+        }
+        let template = Arc::new(Template::from_ir_with_vars(source.clone(), vars)?);
+        *cache = Some((vars.clone(), Arc::clone(&template)));
+        Ok(Some(template))
+    }
+
     pub fn parse_internal(
         &self,
         input: &str,
-        mut debug: Option<&mut DebugReport>,
+        debug: Option<&mut DebugReport>,
         options: ParseOptions,
     ) -> Result<
         (
@@ -99,28 +168,132 @@ impl Template {
         ),
         ScraperError,
     > {
+        let (rows, warnings) = self.parse_rows(input, debug, options)?;
+        Ok((
+            rows.into_iter().map(|(_, record)| record).collect(),
+            warnings,
+        ))
+    }
+
+    /// Parse into named record tables. Every table the template declares is present,
+    /// even when no rows were recorded into it.
+    pub fn parse_tables_internal(
+        &self,
+        input: &str,
+        debug: Option<&mut DebugReport>,
+        options: ParseOptions,
+    ) -> Result<(RecordTables, Vec<TemplateWarning>), ScraperError> {
+        let (rows, warnings) = self.parse_rows(input, debug, options)?;
+        let mut tables: BTreeMap<String, Vec<_>> = self
+            .record_tables()
+            .into_iter()
+            .map(|(name, _)| (name.to_string(), Vec::new()))
+            .collect();
+        for (table, record) in rows {
+            tables.entry(table).or_default().push(record);
+        }
+        Ok((tables, warnings))
+    }
+
+    /// Names of the record tables, or just the default table for single-table templates.
+    pub fn table_names(&self) -> Vec<String> {
+        self.record_tables()
+            .into_iter()
+            .map(|(name, _)| name.to_string())
+            .collect()
+    }
+
+    /// Visible values recorded into `table`, in output column order.
+    pub fn table_columns(&self, table: &str) -> Vec<&Value> {
+        let values = self.table_values(Some(table));
+        self.columns
+            .iter()
+            .filter_map(|name| values.get(name))
+            .collect()
+    }
+
+    /// Whether any rule records into a named table.
+    pub fn has_named_tables(&self) -> bool {
+        self.tables.keys().any(|name| name != DEFAULT_TABLE)
+    }
+
+    fn record_tables(&self) -> Vec<(&str, &HashMap<String, Value>)> {
+        if self.tables.is_empty() {
+            vec![(DEFAULT_TABLE, &self.values)]
+        } else {
+            self.tables
+                .iter()
+                .map(|(name, values)| (name.as_str(), values))
+                .collect()
+        }
+    }
+
+    fn table_values(&self, table: Option<&str>) -> &HashMap<String, Value> {
+        self.tables
+            .get(table.unwrap_or(DEFAULT_TABLE))
+            .unwrap_or(&self.values)
+    }
+
+    /// Run the state machine, returning every emitted record tagged with its table.
+    fn parse_rows(
+        &self,
+        input: &str,
+        mut debug: Option<&mut DebugReport>,
+        mut options: ParseOptions,
+    ) -> Result<(TaggedRecords, Vec<TemplateWarning>), ScraperError> {
+        // Bind parse-time variables by compiling (once per vars map) from the retained source IR.
+        if !options.vars.is_empty() {
+            if let Some(bound) = self.bind_vars(&options.vars)? {
+                let options = ParseOptions {
+                    vars: BTreeMap::new(),
+                    ..options
+                };
+                return bound.parse_rows(input, debug, options);
+            }
+        }
+
+        if let Some(name) = self.unbound_vars.iter().next() {
+            return Err(ScraperError::Template(format!(
+                "Template variable '${{var:{}}}' has no value (supply it with --var {}=VALUE)",
+                name, name
+            )));
+        }
+
+        let mut warnings = Vec::new();
+        options.constants.retain(|name, _| {
+            let shadowed = self.values.contains_key(name);
+            if shadowed {
+                warnings.push(TemplateWarning {
+                    kind: "constant_shadowed".to_string(),
+                    message: format!(
+                        "Constant field '{}' is also a template field - keeping the parsed value",
+                        name
+                    ),
+                    line_idx: None,
+                });
+            }
+            !shadowed
+        });
+
         let mut current_state = "Start".to_string();
         let mut results = Vec::new();
         let mut record_buffer = RecordBuffer::new();
-        let mut warnings = Vec::new();
 
         let start_time = std::time::Instant::now();
         let want_debug = debug.is_some();
 
         let lines: Vec<&str> = input.lines().collect();
-        let emit_parse_summary =
-            |results: &Vec<BTreeMap<String, serde_json::Value>>,
-             warnings: &Vec<TemplateWarning>| {
-                let elapsed = start_time.elapsed();
-                tracing::debug!(
-                    target: "cliscrape::engine",
-                    event = "parse_summary",
-                    line_count = lines.len(),
-                    record_count = results.len(),
-                    warning_count = warnings.len(),
-                    elapsed_ms = elapsed.as_millis() as u64
-                );
-            };
+        let emit_parse_summary = |results: &TaggedRecords, warnings: &Vec<TemplateWarning>| {
+            let elapsed = start_time.elapsed();
+            tracing::debug!(
+                target: "cliscrape::engine",
+                event = "parse_summary",
+                line_count = lines.len(),
+                record_count = results.len(),
+                warning_count = warnings.len(),
+                elapsed_ms = elapsed.as_millis() as u64
+            );
+        };
         let mut line_idx = 0;
 
         while line_idx < lines.len() {
@@ -183,36 +356,8 @@ impl Template {
                     // Handle record action
                     match rule.record_action {
                         Action::Record => {
-                            if let Some(record) = record_buffer.emit(&self.values) {
-                                // Validate threshold
-                                let template_fields: Vec<String> =
-                                    self.values.keys().cloned().collect();
-                                let coverage = crate::engine::coverage::calculate_coverage(
-                                    &record,
-                                    &template_fields,
-                                );
-                                if coverage.percentage < options.threshold {
-                                    let msg = format!(
-                                        "Field coverage threshold not met: {:.1}% < {:.1}% (Missing: {})",
-                                        coverage.percentage,
-                                        options.threshold,
-                                        coverage.missing_fields.join(", ")
-                                    );
-                                    if options.strict {
-                                        return Err(ScraperError::Parse(DetailedParseError {
-                                            line_idx,
-                                            line_content: line.to_string(),
-                                            message: msg,
-                                        }));
-                                    } else {
-                                        warnings.push(TemplateWarning {
-                                            kind: "low_coverage".to_string(),
-                                            message: msg,
-                                            line_idx: Some(line_idx),
-                                        });
-                                    }
-                                }
-
+                            let table_values = self.table_values(rule.table.as_deref());
+                            if let Some(record) = record_buffer.emit(table_values) {
                                 if want_debug {
                                     if let Some(d) = debug.as_mut() {
                                         d.records.push(EmittedRecord {
@@ -221,7 +366,8 @@ impl Template {
                                         });
                                     }
                                 }
-                                results.push(record);
+                                let table = rule.table.as_deref().unwrap_or(DEFAULT_TABLE);
+                                results.push((table.to_string(), record));
                             }
                         }
                         Action::Clear => {
@@ -270,6 +416,8 @@ impl Template {
                     }
 
                     if rule.next_state.as_deref() == Some("End") {
+                        self.finish_records(&mut results, &options);
+                        self.check_coverage(&results, &options, lines.len(), &mut warnings)?;
                         emit_parse_summary(&results, &warnings);
                         return Ok((results, warnings));
                     }
@@ -348,36 +496,8 @@ impl Template {
                         // Handle record action
                         match rule.record_action {
                             Action::Record => {
-                                if let Some(record) = record_buffer.emit(&self.values) {
-                                    // Validate threshold
-                                    let template_fields: Vec<String> =
-                                        self.values.keys().cloned().collect();
-                                    let coverage = crate::engine::coverage::calculate_coverage(
-                                        &record,
-                                        &template_fields,
-                                    );
-                                    if coverage.percentage < options.threshold {
-                                        let msg = format!(
-                                            "Field coverage threshold not met: {:.1}% < {:.1}% (Missing: {})",
-                                            coverage.percentage,
-                                            options.threshold,
-                                            coverage.missing_fields.join(", ")
-                                        );
-                                        if options.strict {
-                                            return Err(ScraperError::Parse(DetailedParseError {
-                                                line_idx: lines.len(),
-                                                line_content: "<EOF>".to_string(),
-                                                message: msg,
-                                            }));
-                                        } else {
-                                            warnings.push(TemplateWarning {
-                                                kind: "low_coverage".to_string(),
-                                                message: msg,
-                                                line_idx: Some(lines.len()),
-                                            });
-                                        }
-                                    }
-
+                                let table_values = self.table_values(rule.table.as_deref());
+                                if let Some(record) = record_buffer.emit(table_values) {
                                     if want_debug {
                                         if let Some(d) = debug.as_mut() {
                                             d.records.push(EmittedRecord {
@@ -386,7 +506,8 @@ impl Template {
                                             });
                                         }
                                     }
-                                    results.push(record);
+                                    let table = rule.table.as_deref().unwrap_or(DEFAULT_TABLE);
+                                    results.push((table.to_string(), record));
                                 }
                             }
                             Action::Clear => {
@@ -411,34 +532,11 @@ impl Template {
                 }
             }
         } else {
-            // No explicit EOF state: use implicit EOF record emission
-            if let Some(record) = record_buffer.emit(&self.values) {
-                // Validate threshold
-                let template_fields: Vec<String> = self.values.keys().cloned().collect();
-                let coverage =
-                    crate::engine::coverage::calculate_coverage(&record, &template_fields);
-                if coverage.percentage < options.threshold {
-                    let msg = format!(
-                        "Field coverage threshold not met: {:.1}% < {:.1}% (Missing: {})",
-                        coverage.percentage,
-                        options.threshold,
-                        coverage.missing_fields.join(", ")
-                    );
-                    if options.strict {
-                        return Err(ScraperError::Parse(DetailedParseError {
-                            line_idx: lines.len(),
-                            line_content: "<EOF>".to_string(),
-                            message: msg,
-                        }));
-                    } else {
-                        warnings.push(TemplateWarning {
-                            kind: "low_coverage".to_string(),
-                            message: msg,
-                            line_idx: Some(lines.len()),
-                        });
-                    }
-                }
-
+            // No explicit EOF state: use implicit EOF record emission, once per table
+            for (table, table_values) in self.record_tables() {
+                let Some(record) = record_buffer.emit(table_values) else {
+                    continue;
+                };
                 if want_debug {
                     if let Some(d) = debug.as_mut() {
                         d.records.push(EmittedRecord {
@@ -457,14 +555,161 @@ impl Template {
                         d.trace.push(trace_event);
                     }
                 }
-                results.push(record);
+                results.push((table.to_string(), record));
             }
         }
 
+        self.finish_records(&mut results, &options);
+        self.check_coverage(&results, &options, lines.len(), &mut warnings)?;
         emit_parse_summary(&results, &warnings);
         Ok((results, warnings))
     }
 
+    /// Shape emitted records for output: stamp constants and drop hidden values. Every return
+    /// path of `parse_rows` goes through here.
+    fn finish_records(&self, results: &mut TaggedRecords, options: &ParseOptions) {
+        apply_constants(results, options);
+        if !self.hidden.is_empty() {
+            for (_, record) in results.iter_mut() {
+                record.retain(|name, _| !self.hidden.contains(name));
+            }
+        }
+    }
+
+    /// Fields that count towards coverage: every value not marked optional or hidden, sorted by
+    /// name.
+    pub fn coverage_fields(&self) -> Vec<String> {
+        self.required_fields(&self.values)
+    }
+
+    /// Aggregate coverage of a parsed result set across every record table, weighting each
+    /// table by its expected (record, field) pairs. `None` when no records were produced.
+    pub fn result_coverage(&self, tables: &RecordTables) -> Option<f64> {
+        let mut captured = 0.0;
+        let mut expected = 0usize;
+        for (table, table_values) in self.record_tables() {
+            let Some(records) = tables.get(table) else {
+                continue;
+            };
+            let summary =
+                calculate_aggregate_coverage(records, &self.required_fields(table_values));
+            let pairs = summary.record_count * summary.total_expected;
+            captured += summary.percentage * pairs as f64 / 100.0;
+            expected += pairs;
+        }
+        (expected > 0).then(|| captured / expected as f64 * 100.0)
+    }
+
+    /// Output value names, sorted; the fields coverage is measured against.
+    fn required_fields(&self, values: &HashMap<String, Value>) -> Vec<String> {
+        let mut fields: Vec<String> = values
+            .values()
+            .filter(|v| !v.optional && !self.hidden.contains(&v.name))
+            .map(|v| v.name.clone())
+            .collect();
+        fields.sort();
+        fields
+    }
+
+    /// Threshold in effect for a parse: caller override, then template default, then 80%.
+    pub fn effective_threshold(&self, options: &ParseOptions) -> f64 {
+        options
+            .threshold
+            .or(self.coverage_threshold)
+            .unwrap_or(DEFAULT_COVERAGE_THRESHOLD)
+    }
+
+    /// Validate aggregate field coverage across all emitted records.
+    ///
+    /// Emits at most one `low_coverage` warning per parse and record table (or an error in
+    /// strict mode).
+    fn check_coverage(
+        &self,
+        results: &[(String, BTreeMap<String, serde_json::Value>)],
+        options: &ParseOptions,
+        eof_line_idx: usize,
+        warnings: &mut Vec<TemplateWarning>,
+    ) -> Result<(), ScraperError> {
+        let named = self.has_named_tables();
+        for (table, table_values) in self.record_tables() {
+            let records: Vec<BTreeMap<String, serde_json::Value>> = results
+                .iter()
+                .filter(|(t, _)| t == table)
+                .map(|(_, record)| record.clone())
+                .collect();
+            let label = named.then_some(table);
+            self.check_table_coverage(
+                label,
+                &records,
+                table_values,
+                options,
+                eof_line_idx,
+                warnings,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn check_table_coverage(
+        &self,
+        table: Option<&str>,
+        results: &[BTreeMap<String, serde_json::Value>],
+        table_values: &HashMap<String, Value>,
+        options: &ParseOptions,
+        eof_line_idx: usize,
+        warnings: &mut Vec<TemplateWarning>,
+    ) -> Result<(), ScraperError> {
+        if results.is_empty() {
+            return Ok(());
+        }
+
+        let fields = self.required_fields(table_values);
+        let threshold = self.effective_threshold(options);
+        let summary = calculate_aggregate_coverage(results, &fields);
+        if summary.percentage >= threshold {
+            return Ok(());
+        }
+
+        let partial: Vec<String> = summary
+            .field_rates
+            .iter()
+            .filter(|(_, rate)| **rate > 0.0 && **rate < 100.0)
+            .map(|(name, rate)| format!("{} {:.1}%", name, rate))
+            .collect();
+        let scope = table
+            .map(|t| format!(" in table '{}'", t))
+            .unwrap_or_default();
+        let mut details = Vec::new();
+        if !summary.missing_fields.is_empty() {
+            details.push(format!("Missing: {}", summary.missing_fields.join(", ")));
+        }
+        if !partial.is_empty() {
+            details.push(format!("Partial: {}", partial.join(", ")));
+        }
+        let mut msg = format!(
+            "Field coverage threshold not met{}: {:.1}% < {:.1}% across {} records",
+            scope, summary.percentage, threshold, summary.record_count
+        );
+        if !details.is_empty() {
+            msg.push_str(&format!(" ({})", details.join("; ")));
+        }
+
+        if options.strict {
+            return Err(ScraperError::Parse(DetailedParseError {
+                line_idx: eof_line_idx,
+                line_content: "<EOF>".to_string(),
+                message: msg,
+            }));
+        }
+
+        warnings.push(TemplateWarning {
+            kind: "low_coverage".to_string(),
+            message: msg,
+            line_idx: None,
+        });
+        Ok(())
+    }
+
     pub fn parse(
         &self,
         input: &str,
@@ -481,6 +726,119 @@ impl Template {
     }
 }
 
+/// Output layout of a template's values (see the matching `Template` fields).
+struct ColumnLayout {
+    field_order: Vec<String>,
+    columns: Vec<String>,
+    hidden: BTreeSet<String>,
+}
+
+/// Resolve declaration order, visible output columns and hidden values.
+fn build_columns(ir: &TemplateIR) -> Result<ColumnLayout, ScraperError> {
+    let field_order = ir.ordered_field_names();
+
+    let mut columns = Vec::new();
+    let mut hidden = BTreeSet::new();
+    for column in &ir.columns {
+        if !ir.values.contains_key(&column.name) {
+            return Err(ScraperError::Template(format!(
+                "Column '{}' is not a declared field",
+                column.name
+            )));
+        }
+        if columns.contains(&column.name) || hidden.contains(&column.name) {
+            return Err(ScraperError::Template(format!(
+                "Column '{}' is listed more than once",
+                column.name
+            )));
+        }
+        if column.hidden {
+            hidden.insert(column.name.clone());
+        } else {
+            columns.push(column.name.clone());
+        }
+    }
+    for name in &field_order {
+        if !columns.contains(name) && !hidden.contains(name) {
+            columns.push(name.clone());
+        }
+    }
+
+    Ok(ColumnLayout {
+        field_order,
+        columns,
+        hidden,
+    })
+}
+
+/// Group values into record tables.
+///
+/// Templates without named tables get a single default table holding every value. Otherwise
+/// values without a table assignment form the default table, and every table a rule records
+/// into must own at least one value.
+fn build_tables(ir: &TemplateIR) -> Result<BTreeMap<String, HashMap<String, Value>>, ScraperError> {
+    let mut recorded: BTreeMap<String, String> = BTreeMap::new();
+    for (state_name, state) in &ir.states {
+        for rule in &state.rules {
+            if rule.record_action == Action::Record {
+                let table = rule.table.as_deref().unwrap_or(DEFAULT_TABLE);
+                recorded
+                    .entry(table.to_string())
+                    .or_insert_with(|| state_name.clone());
+            }
+        }
+    }
+
+    let named = recorded.keys().any(|t| t != DEFAULT_TABLE)
+        || ir.values.values().any(|v| !v.tables.is_empty());
+    let mut tables = BTreeMap::new();
+    if !named {
+        tables.insert(DEFAULT_TABLE.to_string(), ir.values.clone());
+        return Ok(tables);
+    }
+
+    for value in ir.values.values() {
+        if value.tables.is_empty() {
+            tables
+                .entry(DEFAULT_TABLE.to_string())
+                .or_insert_with(HashMap::new)
+                .insert(value.name.clone(), value.clone());
+        }
+        for table in &value.tables {
+            tables
+                .entry(table.clone())
+                .or_insert_with(HashMap::new)
+                .insert(value.name.clone(), value.clone());
+        }
+    }
+
+    for (table, state_name) in &recorded {
+        if !tables.contains_key(table) {
+            return Err(ScraperError::Template(format!(
+                "State '{}' records into table '{}' but no fields belong to it",
+                state_name, table
+            )));
+        }
+    }
+
+    Ok(tables)
+}
+
+/// Stamp `ParseOptions::constants` onto every emitted record.
+fn apply_constants(
+    results: &mut [(String, BTreeMap<String, serde_json::Value>)],
+    options: &ParseOptions,
+) {
+    if options.constants.is_empty() {
+        return;
+    }
+    for (_, record) in results.iter_mut() {
+        for (name, value) in &options.constants {
+            record.insert(name.clone(), serde_json::Value::String(value.clone()));
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -497,6 +855,8 @@ mod tests {
                 required: false,
                 list: false,
                 type_hint: None,
+                optional: false,
+                tables: Vec::new(),
             },
         );
 
@@ -510,6 +870,7 @@ mod tests {
                     line_action: Action::Next,
                     record_action: Action::Record,
                     next_state: None,
+                    table: None,
                 }],
             },
         );
@@ -518,6 +879,10 @@ mod tests {
             values,
             states,
             macros: HashMap::new(),
+            coverage_threshold: None,
+            field_order: Vec::new(),
+            columns: Vec::new(),
+            metrics: None,
         };
 
         let template = Template::from_ir(ir).unwrap();
@@ -544,6 +909,8 @@ mod tests {
                 required: false,
                 list: false,
                 type_hint: None,
+                optional: false,
+                tables: Vec::new(),
             },
         );
         values.insert(
@@ -555,6 +922,8 @@ mod tests {
                 required: false,
                 list: false,
                 type_hint: None,
+                optional: false,
+                tables: Vec::new(),
             },
         );
 
@@ -569,12 +938,14 @@ mod tests {
                         line_action: Action::Continue,
                         record_action: Action::Next, // Acts as NoRecord
                         next_state: None,
+                        table: None,
                     },
                     Rule {
                         regex: r#"is ${Status}"#.to_string(),
                         line_action: Action::Next,
                         record_action: Action::Record,
                         next_state: None,
+                        table: None,
                     },
                 ],
             },
@@ -584,6 +955,10 @@ mod tests {
             values,
             states,
             macros: HashMap::new(),
+            coverage_threshold: None,
+            field_order: Vec::new(),
+            columns: Vec::new(),
+            metrics: None,
         };
 
         let template = Template::from_ir(ir).unwrap();
@@ -609,6 +984,8 @@ mod tests {
                 required: false,
                 list: false,
                 type_hint: None,
+                optional: false,
+                tables: Vec::new(),
             },
         );
         values.insert(
@@ -620,6 +997,8 @@ mod tests {
                 required: false,
                 list: false,
                 type_hint: None,
+                optional: false,
+                tables: Vec::new(),
             },
         );
 
@@ -634,12 +1013,14 @@ mod tests {
                         line_action: Action::Continue,
                         record_action: Action::Next,
                         next_state: None,
+                        table: None,
                     },
                     Rule {
                         regex: r#"is ${Status}"#.to_string(),
                         line_action: Action::Next,
                         record_action: Action::Record,
                         next_state: None,
+                        table: None,
                     },
                 ],
             },
@@ -649,6 +1030,10 @@ mod tests {
             values,
             states,
             macros: HashMap::new(),
+            coverage_threshold: None,
+            field_order: Vec::new(),
+            columns: Vec::new(),
+            metrics: None,
         };
 
         Template::from_ir(ir).unwrap()
@@ -708,6 +1093,7 @@ mod tests {
                     line_action: Action::Next,
                     record_action: Action::Record,
                     next_state: Some("Invalid".to_string()),
+                    table: None,
                 }],
             },
         );
@@ -716,6 +1102,10 @@ mod tests {
             values: HashMap::new(),
             states,
             macros: HashMap::new(),
+            coverage_threshold: None,
+            field_order: Vec::new(),
+            columns: Vec::new(),
+            metrics: None,
         };
 
         let result = Template::from_ir(ir);
@@ -734,6 +1124,8 @@ mod tests {
                 required: false,
                 list: false,
                 type_hint: None,
+                optional: false,
+                tables: Vec::new(),
             },
         );
         values.insert(
@@ -745,6 +1137,8 @@ mod tests {
                 required: false,
                 list: false,
                 type_hint: None,
+                optional: false,
+                tables: Vec::new(),
             },
         );
 
@@ -758,6 +1152,7 @@ mod tests {
                     line_action: Action::Next,
                     record_action: Action::Next,
                     next_state: Some("STATE2".to_string()),
+                    table: None,
                 }],
             },
         );
@@ -770,6 +1165,7 @@ mod tests {
                     line_action: Action::Next,
                     record_action: Action::Record,
                     next_state: None,
+                    table: None,
                 }],
             },
         );
@@ -778,6 +1174,10 @@ mod tests {
             values,
             states,
             macros: HashMap::new(),
+            coverage_threshold: None,
+            field_order: Vec::new(),
+            columns: Vec::new(),
+            metrics: None,
         };
 
         let template = Template::from_ir(ir).unwrap();
@@ -801,6 +1201,8 @@ mod tests {
                 required: false,
                 list: false,
                 type_hint: None,
+                optional: false,
+                tables: Vec::new(),
             },
         );
 
@@ -814,6 +1216,7 @@ mod tests {
                     line_action: Action::Next,
                     record_action: Action::Record,
                     next_state: Some("End".to_string()),
+                    table: None,
                 }],
             },
         );
@@ -822,6 +1225,10 @@ mod tests {
             values,
             states,
             macros: HashMap::new(),
+            coverage_threshold: None,
+            field_order: Vec::new(),
+            columns: Vec::new(),
+            metrics: None,
         };
 
         let template = Template::from_ir(ir).unwrap();
@@ -844,6 +1251,8 @@ mod tests {
                 required: false,
                 list: false,
                 type_hint: None,
+                optional: false,
+                tables: Vec::new(),
             },
         );
         values.insert(
@@ -855,6 +1264,8 @@ mod tests {
                 required: false,
                 list: false,
                 type_hint: None,
+                optional: false,
+                tables: Vec::new(),
             },
         );
 
@@ -869,12 +1280,14 @@ mod tests {
                         line_action: Action::Next,
                         record_action: Action::Next,
                         next_state: None,
+                        table: None,
                     },
                     Rule {
                         regex: r#"Slot ${Slot}"#.to_string(),
                         line_action: Action::Next,
                         record_action: Action::Record,
                         next_state: None,
+                        table: None,
                     },
                 ],
             },
@@ -884,6 +1297,10 @@ mod tests {
             values,
             states,
             macros: HashMap::new(),
+            coverage_threshold: None,
+            field_order: Vec::new(),
+            columns: Vec::new(),
+            metrics: None,
         };
 
         let template = Template::from_ir(ir).unwrap();
@@ -915,6 +1332,8 @@ mod tests {
                 required: true,
                 list: false,
                 type_hint: None,
+                optional: false,
+                tables: Vec::new(),
             },
         );
         values.insert(
@@ -926,6 +1345,8 @@ mod tests {
                 required: false,
                 list: false,
                 type_hint: None,
+                optional: false,
+                tables: Vec::new(),
             },
         );
 
@@ -940,18 +1361,21 @@ mod tests {
                         line_action: Action::Continue,
                         record_action: Action::Next,
                         next_state: None,
+                        table: None,
                     },
                     Rule {
                         regex: r#"IP ${IP}"#.to_string(),
                         line_action: Action::Next,
                         record_action: Action::Record,
                         next_state: None,
+                        table: None,
                     },
                     Rule {
                         regex: r#"NO_INTERFACE"#.to_string(),
                         line_action: Action::Next,
                         record_action: Action::Record,
                         next_state: None,
+                        table: None,
                     },
                 ],
             },
@@ -961,6 +1385,10 @@ mod tests {
             values,
             states,
             macros: HashMap::new(),
+            coverage_threshold: None,
+            field_order: Vec::new(),
+            columns: Vec::new(),
+            metrics: None,
         };
 
         let template = Template::from_ir(ir).unwrap();
@@ -984,6 +1412,8 @@ mod tests {
                 required: false,
                 list: false,
                 type_hint: None,
+                optional: false,
+                tables: Vec::new(),
             },
         );
 
@@ -997,6 +1427,7 @@ mod tests {
                     line_action: Action::Next,
                     record_action: Action::Next, // NoRecord
                     next_state: None,
+                    table: None,
                 }],
             },
         );
@@ -1005,6 +1436,10 @@ mod tests {
             values,
             states,
             macros: HashMap::new(),
+            coverage_threshold: None,
+            field_order: Vec::new(),
+            columns: Vec::new(),
+            metrics: None,
         };
 
         let template = Template::from_ir(ir).unwrap();
@@ -1027,6 +1462,8 @@ mod tests {
                 required: false,
                 list: true,
                 type_hint: None,
+                optional: false,
+                tables: Vec::new(),
             },
         );
 
@@ -1040,6 +1477,7 @@ mod tests {
                     line_action: Action::Next,
                     record_action: Action::Next,
                     next_state: None,
+                    table: None,
                 }],
             },
         );
@@ -1048,6 +1486,10 @@ mod tests {
             values,
             states,
             macros: HashMap::new(),
+            coverage_threshold: None,
+            field_order: Vec::new(),
+            columns: Vec::new(),
+            metrics: None,
         };
 
         let template = Template::from_ir(ir).unwrap();
@@ -1074,6 +1516,8 @@ mod tests {
                 required: false,
                 list: false,
                 type_hint: None,
+                optional: false,
+                tables: Vec::new(),
             },
         );
 
@@ -1087,6 +1531,7 @@ mod tests {
                     line_action: Action::Next,
                     record_action: Action::Next,
                     next_state: None,
+                    table: None,
                 }],
             },
         );
@@ -1095,6 +1540,10 @@ mod tests {
             values,
             states,
             macros: HashMap::new(),
+            coverage_threshold: None,
+            field_order: Vec::new(),
+            columns: Vec::new(),
+            metrics: None,
         };
 
         let template = Template::from_ir(ir).unwrap();
@@ -1122,6 +1571,8 @@ mod tests {
                 required: false,
                 list: false,
                 type_hint: None,
+                optional: false,
+                tables: Vec::new(),
             },
         );
 
@@ -1135,6 +1586,7 @@ mod tests {
                     line_action: Action::Next,
                     record_action: Action::Next,
                     next_state: Some("State2".to_string()),
+                    table: None,
                 }],
             },
         );
@@ -1147,6 +1599,7 @@ mod tests {
                     line_action: Action::Next,
                     record_action: Action::Next,
                     next_state: None,
+                    table: None,
                 }],
             },
         );
@@ -1155,6 +1608,10 @@ mod tests {
             values,
             states,
             macros: HashMap::new(),
+            coverage_threshold: None,
+            field_order: Vec::new(),
+            columns: Vec::new(),
+            metrics: None,
         };
 
         let template = Template::from_ir(ir).unwrap();
@@ -1180,6 +1637,8 @@ mod tests {
                 required: false,
                 list: false,
                 type_hint: None,
+                optional: false,
+                tables: Vec::new(),
             },
         );
 
@@ -1193,6 +1652,7 @@ mod tests {
                     line_action: Action::Next,
                     record_action: Action::Next,
                     next_state: None,
+                    table: None,
                 }],
             },
         );
@@ -1201,6 +1661,10 @@ mod tests {
             values,
             states,
             macros: HashMap::new(),
+            coverage_threshold: None,
+            field_order: Vec::new(),
+            columns: Vec::new(),
+            metrics: None,
         };
 
         let template = Template::from_ir(ir).unwrap();
@@ -1225,6 +1689,8 @@ mod tests {
                 required: false,
                 list: false,
                 type_hint: None,
+                optional: false,
+                tables: Vec::new(),
             },
         );
 
@@ -1238,6 +1704,7 @@ mod tests {
                     line_action: Action::Next,
                     record_action: Action::Record,
                     next_state: None,
+                    table: None,
                 }],
             },
         );
@@ -1246,6 +1713,10 @@ mod tests {
             values,
             states,
             macros: HashMap::new(),
+            coverage_threshold: None,
+            field_order: Vec::new(),
+            columns: Vec::new(),
+            metrics: None,
         };
 
         let template = Template::from_ir(ir).unwrap();
@@ -1274,6 +1745,8 @@ mod tests {
                 required: false,
                 list: false,
                 type_hint: None,
+                optional: false,
+                tables: Vec::new(),
             },
         );
 
@@ -1287,6 +1760,7 @@ mod tests {
                     line_action: Action::Next,
                     record_action: Action::Next,
                     next_state: None,
+                    table: None,
                 }],
             },
         );
@@ -1295,6 +1769,10 @@ mod tests {
             values,
             states,
             macros: HashMap::new(),
+            coverage_threshold: None,
+            field_order: Vec::new(),
+            columns: Vec::new(),
+            metrics: None,
         };
 
         let template = Template::from_ir(ir).unwrap();
@@ -1312,4 +1790,333 @@ mod tests {
             "should have RecordEmitted event at EOF"
         );
     }
+
+    fn build_optional_description_template(optional: bool) -> Template {
+        let mut values = HashMap::new();
+        values.insert(
+            "Iface".to_string(),
+            Value {
+                name: "Iface".to_string(),
+                regex: r#"\S+"#.to_string(),
+                filldown: false,
+                required: false,
+                list: false,
+                type_hint: None,
+                optional: false,
+                tables: Vec::new(),
+            },
+        );
+        values.insert(
+            "Desc".to_string(),
+            Value {
+                name: "Desc".to_string(),
+                regex: r#"\S+"#.to_string(),
+                filldown: false,
+                required: false,
+                list: false,
+                type_hint: None,
+                optional,
+                tables: Vec::new(),
+            },
+        );
+
+        let mut states = HashMap::new();
+        states.insert(
+            "Start".to_string(),
+            State {
+                name: "Start".to_string(),
+                rules: vec![
+                    Rule {
+                        regex: r#"^Iface ${Iface} desc ${Desc}$"#.to_string(),
+                        line_action: Action::Next,
+                        record_action: Action::Record,
+                        next_state: None,
+                        table: None,
+                    },
+                    Rule {
+                        regex: r#"^Iface ${Iface}$"#.to_string(),
+                        line_action: Action::Next,
+                        record_action: Action::Record,
+                        next_state: None,
+                        table: None,
+                    },
+                ],
+            },
+        );
+
+        let ir = TemplateIR {
+            values,
+            states,
+            macros: HashMap::new(),
+            coverage_threshold: None,
+            field_order: Vec::new(),
+            columns: Vec::new(),
+            metrics: None,
+        };
+
+        Template::from_ir(ir).unwrap()
+    }
+
+    #[test]
+    fn low_coverage_is_reported_once_across_records() {
+        let template = build_optional_description_template(false);
+        let input = "Iface Gi0/1\nIface Gi0/2\nIface Gi0/3 desc uplink";
+        let (results, warnings) = template
+            .parse_internal(input, None, ParseOptions::default())
+            .unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind, "low_coverage");
+        assert!(warnings[0].message.contains("across 3 records"));
+        assert!(
+            warnings[0]
+                .message
+                .ends_with("across 3 records (Partial: Desc 33.3%)"),
+            "{}",
+            warnings[0].message
+        );
+    }
+
+    #[test]
+    fn optional_fields_do_not_lower_coverage() {
+        let template = build_optional_description_template(true);
+        let input = "Iface Gi0/1\nIface Gi0/2\nIface Gi0/3 desc uplink";
+        let options = ParseOptions {
+            strict: true,
+            ..ParseOptions::default()
+        };
+        let (results, warnings) = template.parse_internal(input, None, options).unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn caller_threshold_overrides_template_default() {
+        let mut template = build_optional_description_template(false);
+        template.coverage_threshold = Some(50.0);
+        let input = "Iface Gi0/1\nIface Gi0/2 desc uplink";
+
+        let (_, warnings) = template
+            .parse_internal(input, None, ParseOptions::default())
+            .unwrap();
+        assert!(warnings.is_empty(), "75% meets template default of 50%");
+
+        let options = ParseOptions {
+            strict: true,
+            threshold: Some(90.0),
+            ..ParseOptions::default()
+        };
+        let err = template.parse_internal(input, None, options).unwrap_err();
+        assert!(err.to_string().contains("75.0% < 90.0%"), "{err}");
+    }
+
+    #[test]
+    fn vars_from_parse_options_rebind_rule_regexes() {
+        let mut values = HashMap::new();
+        values.insert(
+            "Uptime".to_string(),
+            Value {
+                name: "Uptime".to_string(),
+                regex: r#".+"#.to_string(),
+                filldown: false,
+                required: false,
+                list: false,
+                type_hint: None,
+                optional: false,
+                tables: Vec::new(),
+            },
+        );
+
+        let mut states = HashMap::new();
+        states.insert(
+            "Start".to_string(),
+            State {
+                name: "Start".to_string(),
+                rules: vec![Rule {
+                    regex: r#"^${var:host} uptime is ${Uptime}$"#.to_string(),
+                    line_action: Action::Next,
+                    record_action: Action::Record,
+                    next_state: None,
+                    table: None,
+                }],
+            },
+        );
+
+        let ir = TemplateIR {
+            values,
+            states,
+            macros: HashMap::new(),
+            coverage_threshold: None,
+            field_order: Vec::new(),
+            columns: Vec::new(),
+            metrics: None,
+        };
+
+        let template = Template::from_ir(ir).unwrap();
+        assert!(template.unbound_vars.contains("host"));
+        assert!(template.parse("r1.lab uptime is 2 days").is_err());
+
+        let mut options = ParseOptions::default();
+        options
+            .vars
+            .insert("host".to_string(), "r1.lab".to_string());
+        options
+            .constants
+            .insert("site".to_string(), "lab".to_string());
+        let input = "r1.lab uptime is 2 days\nr1xlab uptime is 9 days";
+        let (results, _) = template
+            .parse_internal(input, None, options.clone())
+            .unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0]["Uptime"], "2 days");
+        assert_eq!(results[0]["site"], "lab");
+
+        // The bound template is compiled once and reused for the same vars.
+        let first = template.bind_vars(&options.vars).unwrap().unwrap();
+        template
+            .parse_internal(input, None, options.clone())
+            .unwrap();
+        let second = template.bind_vars(&options.vars).unwrap().unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+
+        options
+            .vars
+            .insert("host".to_string(), "r1xlab".to_string());
+        let (results, _) = template.parse_internal(input, None, options).unwrap();
+        assert_eq!(results[0]["Uptime"], "9 days");
+    }
+
+    #[test]
+    fn constants_named_like_template_fields_are_dropped() {
+        let template = build_continue_template();
+        let mut options = ParseOptions::default();
+        options
+            .constants
+            .insert("Vlan".to_string(), "1".to_string());
+        options
+            .constants
+            .insert("site".to_string(), "lab".to_string());
+
+        let (results, warnings) = template
+            .parse_internal("VLAN 10 is up", None, options)
+            .unwrap();
+        assert_eq!(results[0]["Vlan"], 10);
+        assert_eq!(results[0]["site"], "lab");
+        assert_eq!(warnings[0].kind, "constant_shadowed");
+        assert!(
+            warnings[0].message.contains("'Vlan'"),
+            "{}",
+            warnings[0].message
+        );
+    }
+
+    fn build_table_template(body: &str) -> Result<Template, ScraperError> {
+        let ir = crate::template::loader::TextFsmLoader::parse_str(body).unwrap();
+        Template::from_ir(ir)
+    }
+
+    #[test]
+    fn named_tables_split_header_and_rows() {
+        let template = build_table_template(
+            r#"# Table chassis: CHASSIS, SERIAL
+# Table ports: CHASSIS, PORT
+Value Filldown CHASSIS (\S+)
+Value SERIAL (\S+)
+Value PORT (\S+)
+
+Start
+  ^Chassis ${CHASSIS} serial ${SERIAL} -> Record(chassis)
+  ^Port ${PORT} -> Record(ports)
+"#,
+        )
+        .unwrap();
+        assert!(template.has_named_tables());
+
+        let input = "Chassis sw1 serial FOC1\nPort Gi0/1\nPort Gi0/2";
+        let (tables, _) = template
+            .parse_tables_internal(input, None, ParseOptions::default())
+            .unwrap();
+
+        assert_eq!(tables.keys().collect::<Vec<_>>(), vec!["chassis", "ports"]);
+        assert_eq!(tables["chassis"].len(), 1);
+        assert_eq!(tables["chassis"][0]["SERIAL"], "FOC1");
+        assert!(!tables["chassis"][0].contains_key("PORT"));
+
+        let ports = &tables["ports"];
+        assert_eq!(ports.len(), 2);
+        assert_eq!(ports[1]["PORT"], "Gi0/2");
+        assert_eq!(ports[1]["CHASSIS"], "sw1");
+        assert!(!ports[0].contains_key("SERIAL"));
+    }
+
+    #[test]
+    fn recording_a_table_keeps_other_tables_pending() {
+        // The header is recorded last, so port rows must not clear its values.
+        let template = build_table_template(
+            r#"# Table ports: PORT
+Value SERIAL (\S+)
+Value PORT (\S+)
+
+Start
+  ^Serial ${SERIAL}
+  ^Port ${PORT} -> Record(ports)
+"#,
+        )
+        .unwrap();
+
+        let input = "Serial FOC1\nPort Gi0/1\nPort Gi0/2";
+        let (tables, _) = template
+            .parse_tables_internal(input, None, ParseOptions::default())
+            .unwrap();
+
+        assert_eq!(tables["ports"].len(), 2);
+        assert_eq!(tables[DEFAULT_TABLE].len(), 1, "implicit EOF record");
+        assert_eq!(tables[DEFAULT_TABLE][0]["SERIAL"], "FOC1");
+    }
+
+    #[test]
+    fn recording_into_a_table_without_fields_is_rejected() {
+        let err = build_table_template(
+            r#"Value PORT (\S+)
+
+Start
+  ^Port ${PORT} -> Record(ports)
+"#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("table 'ports'"), "{err}");
+    }
+
+    #[test]
+    fn result_coverage_weights_tables_by_expected_pairs() {
+        let template = build_table_template(
+            r#"# Table chassis: CHASSIS, SERIAL
+# Table ports: CHASSIS, PORT
+Value Filldown CHASSIS (\S+)
+Value SERIAL (\S+)
+Value PORT (\S+)
+
+Start
+  ^Chassis ${CHASSIS} -> Record(chassis)
+  ^Port ${PORT} -> Record(ports)
+"#,
+        )
+        .unwrap();
+
+        let (tables, _) = template
+            .parse_tables_internal(
+                "Chassis sw1\nPort Gi0/1\nPort Gi0/2",
+                None,
+                ParseOptions::default(),
+            )
+            .unwrap();
+
+        // chassis: 1 of 2 pairs captured; ports: 4 of 4.
+        let coverage = template.result_coverage(&tables).unwrap();
+        assert!((coverage - 500.0 / 6.0).abs() < 1e-9, "{coverage}");
+        assert_eq!(template.result_coverage(&BTreeMap::new()), None);
+    }
 }
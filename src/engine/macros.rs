@@ -1,5 +1,5 @@
 use crate::ScraperError;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 pub fn get_builtin_macros() -> HashMap<String, String> {
     let mut macros = HashMap::new();
@@ -111,6 +111,34 @@ fn resolve_macro(
     Ok(expanded)
 }
 
+/// Regex fragment that can never match; stands in for variables that have no value yet.
+const UNBOUND_VAR_REGEX: &str = r"[^\s\S]";
+
+/// Expand `${var:name}` parse-time variables into literal (regex-escaped) text.
+///
+/// Every referenced name is recorded in `referenced`. Variables missing from `vars` are
+/// replaced with a never-matching fragment, so the template still compiles and the caller
+/// can decide whether the missing value is an error.
+pub fn expand_vars(
+    regex: &str,
+    vars: &BTreeMap<String, String>,
+    referenced: &mut BTreeSet<String>,
+) -> String {
+    static VAR_RE: std::sync::OnceLock<regex::Regex> = std::sync::OnceLock::new();
+    let re =
+        VAR_RE.get_or_init(|| regex::Regex::new(r"\$\{var:([A-Za-z_][A-Za-z0-9_]*)\}").unwrap());
+
+    re.replace_all(regex, |cap: &regex::Captures| {
+        let name = &cap[1];
+        referenced.insert(name.to_string());
+        match vars.get(name) {
+            Some(value) => regex::escape(value),
+            None => UNBOUND_VAR_REGEX.to_string(),
+        }
+    })
+    .into_owned()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -173,4 +201,32 @@ mod tests {
         assert!(msg.contains("a"), "{msg}");
         assert!(msg.contains("b"), "{msg}");
     }
+
+    #[test]
+    fn test_var_expansion_escapes_value() {
+        let mut vars = BTreeMap::new();
+        vars.insert("hostname".to_string(), "core-01.nyc".to_string());
+        let mut referenced = BTreeSet::new();
+
+        let expanded = expand_vars(r"^${var:hostname}#", &vars, &mut referenced);
+        assert_eq!(expanded, r"^core\-01\.nyc#");
+        assert!(referenced.contains("hostname"));
+    }
+
+    #[test]
+    fn test_var_expansion_keeps_unbound_vars_compilable() {
+        let mut referenced = BTreeSet::new();
+        let expanded = expand_vars(
+            r"^${var:site} ${var:hostname}",
+            &BTreeMap::new(),
+            &mut referenced,
+        );
+
+        let re = regex::Regex::new(&expanded).unwrap();
+        assert!(!re.is_match("nyc core-01"));
+        assert_eq!(
+            referenced.into_iter().collect::<Vec<_>>(),
+            vec!["hostname".to_string(), "site".to_string()]
+        );
+    }
 }
@@ -1,29 +1,30 @@
 use crate::engine::convert::convert_scalar;
 use crate::engine::types::Value;
 use serde_json;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 #[derive(Debug, Default)]
 pub struct RecordBuffer {
     buffer: HashMap<String, Vec<String>>,
-    dirty: bool,
+    /// Names captured since they were last emitted or cleared.
+    dirty: HashSet<String>,
 }
 
 impl RecordBuffer {
     pub fn new() -> Self {
         Self {
             buffer: HashMap::new(),
-            dirty: false,
+            dirty: HashSet::new(),
         }
     }
 
     pub fn insert(&mut self, name: String, value: String, is_list: bool) {
+        self.dirty.insert(name.clone());
         if is_list {
             self.buffer.entry(name).or_default().push(value);
         } else {
             self.buffer.insert(name, vec![value]);
         }
-        self.dirty = true;
     }
 
     pub fn clear(&mut self) {
@@ -32,7 +33,7 @@ impl RecordBuffer {
 
     pub fn clear_all(&mut self) {
         self.buffer.clear();
-        self.dirty = false;
+        self.dirty.clear();
     }
 
     /// TextFSM `Clear` semantics: clear only non-Filldown values.
@@ -42,11 +43,14 @@ impl RecordBuffer {
 
     /// Validates and extracts the record.
     /// If valid, returns the record and updates the buffer based on filldown rules.
+    ///
+    /// `values` may be a subset of the template's values (a named record table); only those
+    /// fields are emitted and reset, leaving the rest of the buffer untouched.
     pub fn emit(
         &mut self,
         values: &HashMap<String, Value>,
     ) -> Option<BTreeMap<String, serde_json::Value>> {
-        if !self.dirty {
+        if !values.keys().any(|name| self.dirty.contains(name)) {
             return None;
         }
 
@@ -95,16 +99,9 @@ impl RecordBuffer {
     }
 
     fn reset_after_emit(&mut self, values: &HashMap<String, Value>) {
-        let mut next_buffer = HashMap::new();
-        for (name, val) in values {
-            if val.filldown {
-                if let Some(v) = self.buffer.get(name) {
-                    next_buffer.insert(name.clone(), v.clone());
-                }
-            }
-        }
-        self.buffer = next_buffer;
-        self.dirty = false;
+        self.buffer
+            .retain(|name, _| values.get(name).is_none_or(|val| val.filldown));
+        self.dirty.retain(|name| !values.contains_key(name));
     }
 
     pub fn get_buffer(&self) -> &HashMap<String, Vec<String>> {
@@ -156,6 +153,8 @@ mod tests {
                 required: false,
                 list: true,
                 type_hint: None,
+                optional: false,
+                tables: Vec::new(),
             },
         );
 
@@ -185,6 +184,8 @@ mod tests {
                 required: false,
                 list: false,
                 type_hint: Some(FieldType::Int),
+                optional: false,
+                tables: Vec::new(),
             },
         );
 
@@ -211,6 +212,8 @@ mod tests {
                 required: false,
                 list: false,
                 type_hint: Some(FieldType::Int),
+                optional: false,
+                tables: Vec::new(),
             },
         );
 
@@ -237,6 +240,8 @@ mod tests {
                 required: false,
                 list: false,
                 type_hint: Some(FieldType::String),
+                optional: false,
+                tables: Vec::new(),
             },
         );
 
@@ -263,6 +268,8 @@ mod tests {
                 required: false,
                 list: true,
                 type_hint: Some(FieldType::Int),
+                optional: false,
+                tables: Vec::new(),
             },
         );
 
@@ -296,6 +303,8 @@ mod tests {
                 required: false,
                 list: false,
                 type_hint: None,
+                optional: false,
+                tables: Vec::new(),
             },
         );
 
@@ -1,5 +1,6 @@
 use regex::Regex;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Action {
@@ -25,6 +26,10 @@ pub struct Value {
     pub required: bool,
     pub list: bool,
     pub type_hint: Option<FieldType>,
+    /// Field may legitimately be absent; excluded from coverage accounting.
+    pub optional: bool,
+    /// Named record tables this field belongs to; empty means the default table.
+    pub tables: Vec<String>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -33,6 +38,8 @@ pub struct Rule {
     pub line_action: Action,
     pub record_action: Action,
     pub next_state: Option<String>,
+    /// Named table targeted by `Record`; `None` records into the default table.
+    pub table: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -41,11 +48,81 @@ pub struct State {
     pub rules: Vec<Rule>,
 }
 
+/// Output column declared by a template.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    pub name: String,
+    /// Captured (and usable by filldown) but left out of emitted records.
+    pub hidden: bool,
+}
+
+/// Metric exposition mapping declared in modern template metadata (`metadata.metrics`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricsMapping {
+    /// Columns emitted as labels on every sample, in order.
+    pub labels: Vec<String>,
+    /// One metric family per numeric field, in declaration order.
+    pub metrics: Vec<MetricDef>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricDef {
+    pub field: String,
+    /// Metric family name; counters expose their samples as `<name>_total`.
+    pub name: String,
+    pub kind: MetricKind,
+    pub help: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricKind {
+    Gauge,
+    Counter,
+}
+
+impl MetricKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MetricKind::Gauge => "gauge",
+            MetricKind::Counter => "counter",
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct TemplateIR {
     pub values: HashMap<String, Value>,
     pub states: HashMap<String, State>,
     pub macros: HashMap<String, String>,
+    /// Template-declared default for `ParseOptions::threshold`.
+    pub coverage_threshold: Option<f64>,
+    /// Value names in declaration order; values missing here sort after it by name.
+    pub field_order: Vec<String>,
+    /// Explicit output columns; empty means declaration order with nothing hidden.
+    pub columns: Vec<Column>,
+    /// Metric exposition mapping, for `--format openmetrics`.
+    pub metrics: Option<MetricsMapping>,
+}
+
+impl TemplateIR {
+    /// Value names in declaration order, then any values missing from `field_order` by name.
+    pub fn ordered_field_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .field_order
+            .iter()
+            .filter(|name| self.values.contains_key(*name))
+            .cloned()
+            .collect();
+        let mut undeclared: Vec<String> = self
+            .values
+            .keys()
+            .filter(|name| !names.contains(name))
+            .cloned()
+            .collect();
+        undeclared.sort();
+        names.extend(undeclared);
+        names
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -54,10 +131,44 @@ pub struct CompiledRule {
     pub line_action: Action,
     pub record_action: Action,
     pub next_state: Option<String>,
+    pub table: Option<String>,
 }
 
+/// Table that receives records from a bare `Record` action.
+pub const DEFAULT_TABLE: &str = "records";
+
 #[derive(Debug, Clone)]
 pub struct Template {
     pub states: HashMap<String, Vec<CompiledRule>>,
     pub values: HashMap<String, Value>,
+    /// Value subsets per record table. Without named tables this holds only the default table.
+    pub tables: BTreeMap<String, HashMap<String, Value>>,
+    pub coverage_threshold: Option<f64>,
+    /// Value names in declaration order.
+    pub field_order: Vec<String>,
+    /// Visible output columns: declared `columns` first, then remaining values in declaration order.
+    pub columns: Vec<String>,
+    /// Values dropped from emitted records.
+    pub hidden: BTreeSet<String>,
+    pub metrics: Option<MetricsMapping>,
+    /// `${var:name}` variables referenced by rules but not bound at compile time.
+    pub unbound_vars: BTreeSet<String>,
+    /// Source IR, retained only when rules reference variables so they can be bound at parse time.
+    pub source: Option<TemplateIR>,
+    /// The template last compiled from `source` for parse-time variables.
+    pub bound: BoundTemplate,
+}
+
+/// Cache of the template compiled for one set of parse-time variables, so repeated parses with
+/// the same `ParseOptions::vars` compile it once. Clones start empty.
+#[derive(Debug, Default)]
+pub struct BoundTemplate(pub(crate) Mutex<Option<VarBinding>>);
+
+/// Parse-time variables and the template compiled with them.
+pub(crate) type VarBinding = (BTreeMap<String, String>, Arc<Template>);
+
+impl Clone for BoundTemplate {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
 }
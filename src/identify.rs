@@ -0,0 +1,161 @@
+//! Template identification: score how well candidate templates parse a sample capture.
+//!
+//! Each candidate is scored by the records it emits, its field coverage (required fields
+//! captured, as in `result_coverage`) and its line coverage (non-blank input lines explained
+//! by a rule that captures a value, records or changes state, so catch-all rules don't count).
+//! Confidence is the field coverage, discounted by up to half for unexplained lines (most
+//! `show` output has lines no template captures); templates that emit nothing score zero.
+//! Coverages are rounded to 0.1% and confidence to three decimals.
+
+use crate::FsmParser;
+
+/// Score of one template against a sample.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Candidate {
+    pub name: String,
+    pub records: usize,
+    /// Percentage of required fields captured across all records, to one decimal.
+    pub field_coverage: f64,
+    /// Percentage of non-blank input lines explained by a rule, to one decimal.
+    pub line_coverage: f64,
+    /// Combined score in `0.0..=1.0`.
+    pub confidence: f64,
+}
+
+/// Score `parser` against `input`.
+///
+/// Returns `None` when the template fails on the input (e.g. an `Error` action fired).
+pub fn score(name: &str, parser: &FsmParser, input: &str) -> Option<Candidate> {
+    // One parse yields both the records (field coverage) and the rule matches (line coverage).
+    let (tables, report) = parser.debug_tables(input).ok()?;
+    let records: usize = tables.values().map(Vec::len).sum();
+    if records == 0 {
+        return Some(Candidate {
+            name: name.to_string(),
+            records,
+            field_coverage: 0.0,
+            line_coverage: 0.0,
+            confidence: 0.0,
+        });
+    }
+    let field_coverage = parser.result_coverage(&tables).unwrap_or(100.0);
+
+    let mut total = 0usize;
+    let mut explained = 0usize;
+    for (line, matches) in report.lines.iter().zip(&report.matches_by_line) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        total += 1;
+        let useful = matches.iter().any(|m| {
+            !m.captures.is_empty() || m.record_action != "Next" || m.state_before != m.state_after
+        });
+        if useful {
+            explained += 1;
+        }
+    }
+    let line_coverage = if total == 0 {
+        0.0
+    } else {
+        explained as f64 / total as f64 * 100.0
+    };
+
+    Some(Candidate {
+        name: name.to_string(),
+        records,
+        field_coverage: round(field_coverage, 1),
+        line_coverage: round(line_coverage, 1),
+        confidence: round(field_coverage / 100.0 * (0.5 + line_coverage / 200.0), 3),
+    })
+}
+
+fn round(value: f64, decimals: i32) -> f64 {
+    let scale = 10f64.powi(decimals);
+    (value * scale).round() / scale
+}
+
+/// Score every template against `input` in parallel, best first.
+///
+/// Ties rank by record count, then name. Templates that fail on the input are left out.
+pub fn rank(templates: &[(String, FsmParser)], input: &str) -> Vec<Candidate> {
+    let workers = std::thread::available_parallelism()
+        .map_or(1, usize::from)
+        .min(templates.len().max(1));
+    let chunk = templates.len().div_ceil(workers).max(1);
+
+    let mut candidates: Vec<Candidate> = std::thread::scope(|scope| {
+        let handles: Vec<_> = templates
+            .chunks(chunk)
+            .map(|batch| {
+                scope.spawn(move || {
+                    batch
+                        .iter()
+                        .filter_map(|(name, parser)| score(name, parser, input))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("scoring thread panicked"))
+            .collect()
+    });
+
+    candidates.sort_by(|a, b| {
+        b.confidence
+            .total_cmp(&a.confidence)
+            .then(b.records.cmp(&a.records))
+            .then(a.name.cmp(&b.name))
+    });
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template::modern;
+
+    fn parser(yaml: &str) -> FsmParser {
+        let ir = modern::load_yaml_str(yaml).unwrap();
+        FsmParser::new(crate::engine::Template::from_ir(ir).unwrap())
+    }
+
+    const PORTS: &str = r#"
+version: 1
+fields:
+  port: { type: string }
+  mtu: { type: int }
+patterns:
+  - regex: '^Port (?P<port>\S+) mtu (?P<mtu>\d+)$'
+    record: true
+"#;
+
+    const VERSION: &str = r#"
+version: 1
+fields:
+  version: { type: string }
+patterns:
+  - regex: '^Version (?P<version>\S+)$'
+    record: true
+"#;
+
+    #[test]
+    fn ranks_the_template_explaining_the_sample_first() {
+        let templates = vec![
+            ("version.yaml".to_string(), parser(VERSION)),
+            ("ports.yaml".to_string(), parser(PORTS)),
+        ];
+        let input = "Port Gi0/1 mtu 1500\nPort Gi0/2 mtu 9000\n\nuptime 3 days\n";
+
+        let ranked = rank(&templates, input);
+
+        assert_eq!(ranked[0].name, "ports.yaml");
+        assert_eq!(ranked[0].records, 2);
+        assert_eq!(ranked[0].field_coverage, 100.0);
+        assert_eq!(ranked[0].line_coverage, 66.7);
+        assert_eq!(ranked[0].confidence, 0.833);
+        assert_eq!(round(0.1 + 0.05, 3).to_string(), "0.15");
+        assert_eq!(ranked[1].name, "version.yaml");
+        assert_eq!(ranked[1].confidence, 0.0);
+    }
+}
@@ -1,8 +1,10 @@
+use regex::Regex;
 use std::collections::{BTreeMap, HashMap};
 use std::path::Path;
 use thiserror::Error;
 
 pub mod engine;
+pub mod identify;
 pub mod template;
 
 pub use engine::debug::{CaptureSpan, DebugReport, EmittedRecord, LineMatch};
@@ -11,6 +13,9 @@ use crate::engine::Template;
 use crate::template::loader::TextFsmLoader;
 use crate::template::modern;
 
+/// Records per table name, as returned by `FsmParser::tables_with_warnings`.
+pub type RecordTables = BTreeMap<String, Vec<BTreeMap<String, serde_json::Value>>>;
+
 #[derive(Debug, Clone)]
 pub struct TemplateWarning {
     pub kind: String,
@@ -39,23 +44,52 @@ pub enum ScraperError {
     Timeout(String),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct ParseOptions {
     pub strict: bool,
-    pub threshold: f64,
+    /// Minimum aggregate field coverage percentage. `None` defers to the template's
+    /// declared threshold, falling back to 80%.
+    pub threshold: Option<f64>,
     pub timeout_ms: Option<u64>,
+    /// Values for `${var:name}` references in rule regexes (inserted as literal text).
+    pub vars: BTreeMap<String, String>,
+    /// Constant columns added to every emitted record. A constant named like a template field is
+    /// dropped with a `constant_shadowed` warning; the parsed value wins.
+    pub constants: BTreeMap<String, String>,
 }
 
-impl Default for ParseOptions {
-    fn default() -> Self {
-        Self {
-            strict: false,
-            threshold: 80.0,
-            timeout_ms: None,
+impl ParseOptions {
+    /// Add the named groups of `pattern` matched against `path` as constant columns.
+    ///
+    /// Returns `None` when the pattern does not match. Explicitly set constants take
+    /// precedence over path captures of the same name, and template fields over both.
+    pub fn with_path_captures(mut self, pattern: &Regex, path: &Path) -> Option<Self> {
+        for (name, value) in path_captures(pattern, path)? {
+            self.constants.entry(name).or_insert(value);
         }
+        Some(self)
     }
 }
 
+/// Match `pattern` against `path` (with `/` separators) and collect its named groups.
+///
+/// Returns `None` when the pattern does not match. Groups that did not participate in the
+/// match are omitted.
+pub fn path_captures(pattern: &Regex, path: &Path) -> Option<BTreeMap<String, String>> {
+    let path_str = path.to_string_lossy().replace('\\', "/");
+    let caps = pattern.captures(&path_str)?;
+    Some(
+        pattern
+            .capture_names()
+            .flatten()
+            .filter_map(|name| {
+                caps.name(name)
+                    .map(|m| (name.to_string(), m.as_str().to_string()))
+            })
+            .collect(),
+    )
+}
+
 pub struct FsmParser {
     template: Template,
 }
@@ -151,6 +185,47 @@ impl FsmParser {
         self.template.parse_internal(input, None, options)
     }
 
+    /// Parse into record tables keyed by table name.
+    ///
+    /// Templates without `Record(name)` rules yield a single table named `records`.
+    pub fn tables_with_warnings(
+        &self,
+        input: &str,
+        options: ParseOptions,
+    ) -> Result<(RecordTables, Vec<TemplateWarning>), ScraperError> {
+        self.template.parse_tables_internal(input, None, options)
+    }
+
+    /// Whether the template records into named tables
+    pub fn has_named_tables(&self) -> bool {
+        self.template.has_named_tables()
+    }
+
+    /// Get the record table names declared by the template
+    pub fn table_names(&self) -> Vec<String> {
+        self.template.table_names()
+    }
+
+    /// Get the visible value definitions of a record table, in output column order
+    pub fn table_columns(&self, table: &str) -> Vec<&engine::Value> {
+        self.template.table_columns(table)
+    }
+
+    /// Parse into record tables, with the debug report of that same run
+    pub fn debug_tables(
+        &self,
+        input: &str,
+    ) -> Result<(RecordTables, engine::debug::DebugReport), ScraperError> {
+        let lines: Vec<String> = input.lines().map(|s| s.to_string()).collect();
+        let mut report = engine::debug::DebugReport::new(lines);
+        let (tables, _) = self.template.parse_tables_internal(
+            input,
+            Some(&mut report),
+            ParseOptions::default(),
+        )?;
+        Ok((tables, report))
+    }
+
     pub fn debug_parse(&self, input: &str) -> Result<engine::debug::DebugReport, ScraperError> {
         let lines: Vec<String> = input.lines().map(|s| s.to_string()).collect();
         let mut report = engine::debug::DebugReport::new(lines);
@@ -160,9 +235,34 @@ impl FsmParser {
         Ok(report)
     }
 
-    /// Get the list of field names defined in the template
+    /// Get the list of field names defined in the template, in declaration order
     pub fn field_names(&self) -> Vec<String> {
-        self.template.values.keys().cloned().collect()
+        self.template.field_order.clone()
+    }
+
+    /// Get the visible output columns in display order (hidden columns excluded)
+    pub fn column_order(&self) -> Vec<String> {
+        self.template.columns.clone()
+    }
+
+    /// Get the field names that count towards coverage (excludes optional fields)
+    pub fn coverage_fields(&self) -> Vec<String> {
+        self.template.coverage_fields()
+    }
+
+    /// Aggregate field coverage of a parsed result set (percentage), if any records were produced
+    pub fn result_coverage(&self, tables: &RecordTables) -> Option<f64> {
+        self.template.result_coverage(tables)
+    }
+
+    /// Get the coverage threshold declared by the template, if any
+    pub fn coverage_threshold(&self) -> Option<f64> {
+        self.template.coverage_threshold
+    }
+
+    /// Metric exposition mapping declared by the template, if any
+    pub fn metrics(&self) -> Option<&engine::MetricsMapping> {
+        self.template.metrics.as_ref()
     }
 }
 
@@ -272,6 +372,8 @@ patterns:
                 required: false,
                 list: false,
                 type_hint: None,
+                optional: false,
+                tables: Vec::new(),
             },
         );
 
@@ -285,6 +387,7 @@ patterns:
                     line_action: engine::Action::Next,
                     record_action: engine::Action::Record,
                     next_state: None,
+                    table: None,
                 }],
             },
         );
@@ -293,6 +396,10 @@ patterns:
             values,
             states,
             macros: HashMap::new(),
+            coverage_threshold: None,
+            field_order: Vec::new(),
+            columns: Vec::new(),
+            metrics: None,
         };
         let template = engine::Template::from_ir(ir).unwrap();
         let parser = FsmParser::new(template);
@@ -302,4 +409,30 @@ patterns:
         assert_eq!(report.matches_by_line[0].len(), 1);
         assert_eq!(report.records.len(), 1);
     }
+
+    #[test]
+    fn path_captures_collects_named_groups() {
+        let pattern =
+            Regex::new(r"captures/(?P<hostname>[^/]+)/(?P<command>[^/.]+)\.txt$").unwrap();
+        let caps =
+            path_captures(&pattern, Path::new("captures/nyc-core-01/show_version.txt")).unwrap();
+        assert_eq!(caps["hostname"], "nyc-core-01");
+        assert_eq!(caps["command"], "show_version");
+
+        assert!(path_captures(&pattern, Path::new("other/file.log")).is_none());
+    }
+
+    #[test]
+    fn with_path_captures_keeps_explicit_constants() {
+        let pattern = Regex::new(r"(?P<site>[a-z]+)-").unwrap();
+        let mut options = ParseOptions::default();
+        options
+            .constants
+            .insert("site".to_string(), "override".to_string());
+
+        let options = options
+            .with_path_captures(&pattern, Path::new("nyc-core-01.txt"))
+            .unwrap();
+        assert_eq!(options.constants["site"], "override");
+    }
 }
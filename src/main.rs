@@ -1,20 +1,30 @@
+mod bundle;
 mod cli;
+mod diff;
 mod logging;
 mod output;
+mod pipeline;
+mod query;
+#[cfg(feature = "sqlite")]
+mod sqlite;
 mod transcript;
 mod tui;
 
-use crate::cli::{Cli, Commands, ErrorFormat, OutputFormat, TemplateFormat as CliTemplateFormat};
+use crate::cli::{
+    Cli, Commands, ErrorFormat, OutputFormat, QueryArgs, RenderArgs, RouteArgs,
+    TemplateFormat as CliTemplateFormat,
+};
 use anyhow::Context;
 use clap::Parser;
-use cliscrape::FsmParser;
 use cliscrape::template::{
+    index::normalize_command,
     library, metadata,
     resolver::{TemplateResolver, TemplateSource},
 };
+use cliscrape::{FsmParser, identify};
 use comfy_table::{Table, presets};
 use dialoguer::{Confirm, Input, Select, theme::ColorfulTheme};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::io::{self, IsTerminal, Read, Write};
 use std::path::{Path, PathBuf};
 use std::time::Instant;
@@ -32,8 +42,16 @@ fn output_format_label(f: OutputFormat) -> &'static str {
     match f {
         OutputFormat::Auto => "auto",
         OutputFormat::Json => "json",
+        OutputFormat::JsonEnvelope => "json-envelope",
+        OutputFormat::Ndjson => "ndjson",
         OutputFormat::Csv => "csv",
         OutputFormat::Table => "table",
+        OutputFormat::Yaml => "yaml",
+        OutputFormat::Toml => "toml",
+        OutputFormat::Markdown => "markdown",
+        OutputFormat::Html => "html",
+        OutputFormat::Sqlite => "sqlite",
+        OutputFormat::OpenMetrics => "openmetrics",
     }
 }
 
@@ -112,7 +130,7 @@ fn handle_list_templates(filter: Option<&str>, format: OutputFormat) -> anyhow::
 
             println!("{}", table);
         }
-        OutputFormat::Json => {
+        OutputFormat::Json | OutputFormat::Ndjson | OutputFormat::Yaml | OutputFormat::Toml => {
             let json_output: Vec<_> = templates
                 .iter()
                 .map(|(name, meta, source)| {
@@ -127,11 +145,33 @@ fn handle_list_templates(filter: Option<&str>, format: OutputFormat) -> anyhow::
                     })
                 })
                 .collect();
-            println!("{}", serde_json::to_string_pretty(&json_output)?);
+            match format {
+                OutputFormat::Ndjson => {
+                    for entry in &json_output {
+                        println!("{}", serde_json::to_string(entry)?);
+                    }
+                }
+                OutputFormat::Yaml => print!("{}", serde_yaml_ng::to_string(&json_output)?),
+                OutputFormat::Toml => {
+                    let document = serde_json::json!({ "templates": json_output });
+                    print!("{}", output::to_toml(&document)?);
+                }
+                _ => println!("{}", serde_json::to_string_pretty(&json_output)?),
+            }
         }
         OutputFormat::Csv => {
             anyhow::bail!("CSV format not supported for template listing");
         }
+        OutputFormat::JsonEnvelope
+        | OutputFormat::Markdown
+        | OutputFormat::Html
+        | OutputFormat::Sqlite
+        | OutputFormat::OpenMetrics => {
+            anyhow::bail!(
+                "{} format not supported for template listing",
+                output_format_label(format)
+            );
+        }
     }
 
     Ok(())
@@ -203,15 +243,15 @@ fn handle_show_template(name: &str, show_source: bool) -> anyhow::Result<()> {
         }
     };
 
-    // Get field names from template
-    let mut sorted_fields = parser.field_names();
-    sorted_fields.sort();
+    // Get field names from template, in declaration order
+    let fields = parser.field_names();
+    let coverage_fields = parser.coverage_fields();
 
     tracing::info!(
         target: "cliscrape::cli",
         event = "show_template_resolved",
         source_kind,
-        field_count = sorted_fields.len()
+        field_count = fields.len()
     );
 
     // Print formatted output
@@ -223,10 +263,17 @@ fn handle_show_template(name: &str, show_source: bool) -> anyhow::Result<()> {
     if let Some(maintainer) = &meta.maintainer {
         println!("Maintainer: {}", maintainer);
     }
+    if let Some(threshold) = parser.coverage_threshold() {
+        println!("Coverage Threshold: {:.1}%", threshold);
+    }
     println!("Source: {}", actual_source);
     println!("\nFields Extracted:");
-    for field in sorted_fields {
-        println!("  - {}", field);
+    for field in fields {
+        if coverage_fields.contains(&field) {
+            println!("  - {}", field);
+        } else {
+            println!("  - {} (optional)", field);
+        }
     }
 
     // Show source if requested
@@ -282,9 +329,15 @@ fn main() {
     logging::init_logging(cli.verbose, cli.log_format);
 
     let error_format = cli.error_format;
+    // Like diff(1), `diff` keeps status 1 for "captures differ" and reports errors with 2.
+    let error_status = if matches!(cli.command, Commands::Diff { .. }) {
+        2
+    } else {
+        1
+    };
     if let Err(e) = run_command(cli) {
         print_error(&format!("{:#}", e), error_format);
-        std::process::exit(1);
+        std::process::exit(error_status);
     }
 }
 
@@ -334,184 +387,162 @@ fn run_command(cli: Cli) -> anyhow::Result<()> {
     match cli.command {
         Commands::Parse {
             template,
+            platform,
+            command,
+            auto_template,
             template_format,
             inputs,
             input,
             input_glob,
             stdin,
             format,
+            output: output_path,
+            output_dir,
+            output_name,
+            group_by_source,
             quiet,
             strict,
             threshold,
             timeout,
+            vars,
+            constants,
+            ndjson_warnings,
+            transcript: transcript_args,
+            path_capture,
+            routes,
+            query,
+            render,
         } => {
-            let start_time = Instant::now();
-            // Template resolution: path vs identifier
-            let template_path = resolve_template_spec(&template, template_format)?;
-
-            let (parser, loader_warnings) = match template_format {
-                CliTemplateFormat::Auto => FsmParser::from_file_with_warnings(&template_path)
-                    .with_context(|| {
-                        format!("Failed to load template from {}", template_path.display())
-                    })?,
-                CliTemplateFormat::Textfsm => {
-                    let p = FsmParser::from_file_with_format(
-                        &template_path,
-                        cliscrape::TemplateFormat::Textfsm,
-                    )
-                    .with_context(|| {
-                        format!("Failed to load template from {}", template_path.display())
-                    })?;
-                    (p, Vec::new())
-                }
-                CliTemplateFormat::Yaml => {
-                    let p = FsmParser::from_file_with_format(
-                        &template_path,
-                        cliscrape::TemplateFormat::Yaml,
-                    )
-                    .with_context(|| {
-                        format!("Failed to load template from {}", template_path.display())
-                    })?;
-                    (p, Vec::new())
-                }
-                CliTemplateFormat::Toml => {
-                    let p = FsmParser::from_file_with_format(
-                        &template_path,
-                        cliscrape::TemplateFormat::Toml,
-                    )
-                    .with_context(|| {
-                        format!("Failed to load template from {}", template_path.display())
-                    })?;
-                    (p, Vec::new())
-                }
-            };
-
-            // Resolve multi-input: files + globs + stdin
-            let input_sources = resolve_input_sources(&inputs, &input, &input_glob, stdin)?;
-
-            let span = tracing::info_span!(
-                target: "cliscrape",
-                "cmd.parse",
-                template_spec = %template,
-                template_format = template_format_label(template_format),
-                input_source_count = input_sources.len(),
-                strict,
-                threshold,
-                timeout_ms = ?timeout,
+            let QueryArgs {
+                filters,
+                fields,
+                sort,
+                limit,
+                query: path,
+            } = *query;
+            let settings = ParseSettings {
+                parse_options: cliscrape::ParseOptions {
+                    strict,
+                    threshold,
+                    timeout_ms: timeout,
+                    vars: vars.into_iter().collect(),
+                    constants: constants.into_iter().collect(),
+                },
+                transcript_options: transcript_args.options(),
+                result_query: query::ResultQuery {
+                    path,
+                    filters,
+                    sort,
+                    limit,
+                    fields,
+                },
+                render: *render,
+                format,
+                output_path,
+                output_dir,
+                output_name,
+                group_by_source,
+                ndjson_warnings,
+                path_capture,
+                command_column: routes.command_column.clone(),
                 quiet,
-                output_format = output_format_label(format)
-            );
-            let _guard = span.enter();
-
-            tracing::info!(
-                target: "cliscrape::cli",
-                event = "parse_start",
-                template_spec = %template,
-                template_format = template_format_label(template_format),
-                input_source_count = input_sources.len(),
-                strict,
-                threshold,
-                timeout_ms = ?timeout
-            );
-
-            // Convert template loader warnings into structured log events
-            for warning in &loader_warnings {
-                tracing::warn!(
-                    target: "cliscrape::cli",
-                    event = "template_loader_warning",
-                    kind = %warning.kind,
-                    message = %warning.message
-                );
-            }
-
-            // Fail-fast parsing: collect all records before writing to stdout
-            let mut all_results = Vec::new();
-            let mut all_warnings = Vec::new();
-
-            let parse_options = cliscrape::ParseOptions {
-                strict,
-                threshold,
-                timeout_ms: timeout,
+                start_time: Instant::now(),
             };
 
-            for source in &input_sources {
-                let content = match source {
-                    InputSource::Stdin => {
-                        let mut buffer = String::new();
-                        io::stdin()
-                            .read_to_string(&mut buffer)
-                            .context("Failed to read input from stdin")?;
-                        buffer
-                    }
-                    InputSource::File(path) => std::fs::read_to_string(path)
-                        .with_context(|| format!("Failed to read input from {}", path.display()))?,
-                };
-
-                let (blocks, transcript_warnings) =
-                    transcript::preprocess_ios_transcript_with_warnings(&content);
-                for w in transcript_warnings {
-                    all_warnings.push(cliscrape::TemplateWarning {
-                        kind: "transcript".to_string(),
-                        message: w,
-                        line_idx: None,
-                    });
-                }
-
-                for (idx, block) in blocks.iter().enumerate() {
-                    let (mut parsed, warnings) = parser
-                        .results_with_warnings(block, parse_options.clone())
-                        .with_context(|| {
-                            format!(
-                                "Failed to parse block {} from {}",
-                                idx + 1,
-                                source.display()
-                            )
-                        })?;
-                    all_results.append(&mut parsed);
-                    all_warnings.extend(warnings);
-                }
+            if !routes.template_map.is_empty() && !auto_template && routes.bundle.is_none() {
+                anyhow::bail!("--template-map needs --auto-template or --bundle");
             }
-
-            // Convert warnings into structured log events
-            for warning in &all_warnings {
-                let one_based_line = warning.line_idx.map(|idx| idx + 1);
-                tracing::warn!(
-                    target: "cliscrape::cli",
-                    event = "parse_warning",
-                    kind = %warning.kind,
-                    line_idx = ?one_based_line,
-                    message = %warning.message
+            if auto_template || routes.bundle.is_some() {
+                let input_sources = match routes.bundle {
+                    Some(_) => Vec::new(),
+                    None => resolve_input_sources(&inputs, &input, &input_glob, stdin)?,
+                };
+                return handle_routed_parse(
+                    &settings,
+                    platform.as_deref(),
+                    auto_template,
+                    &routes,
+                    &input_sources,
                 );
             }
-            let warning_count = all_warnings.len();
 
-            // Resolve format=auto based on TTY
-            let final_format = if format == OutputFormat::Auto {
-                if io::stdout().is_terminal() {
-                    OutputFormat::Table
-                } else {
-                    OutputFormat::Json
+            let template = match (template, platform, command) {
+                (Some(template), _, _) => template,
+                (None, Some(platform), Some(command)) => TemplateResolver::new()
+                    .map_err(|e| anyhow::anyhow!("Failed to initialize template resolver: {}", e))?
+                    .resolve_command(&platform, &command)
+                    .map_err(|e| anyhow::anyhow!(e))?,
+                (None, Some(_), None) => {
+                    anyhow::bail!("--platform needs --command (or --auto-template)")
                 }
-            } else {
-                format
+                _ => anyhow::bail!("--template or --platform with --command is required"),
             };
 
-            let output = output::serialize(&all_results, final_format)?;
-            println!("{}", output);
-
-            // Convert success status to a structured info event (unless --quiet)
-            if !quiet {
-                let elapsed = start_time.elapsed();
-                tracing::info!(
-                    target: "cliscrape::cli",
-                    event = "parse_finish",
-                    records = all_results.len(),
-                    warnings = warning_count,
-                    sources = input_sources.len(),
-                    elapsed_ms = elapsed.as_millis() as u64
-                );
+            // Resolve multi-input: files + globs + stdin
+            let input_sources = resolve_input_sources(&inputs, &input, &input_glob, stdin)?;
+            handle_parse(&settings, template, template_format, &input_sources)?;
+        }
+        Commands::Pipeline {
+            spec,
+            inputs,
+            input,
+            input_glob,
+            stdin,
+            transcript: transcript_args,
+            format,
+            quiet,
+            render,
+        } => {
+            let input_sources = resolve_input_sources(&inputs, &input, &input_glob, stdin)?;
+            handle_pipeline(
+                &spec,
+                &input_sources,
+                &transcript_args.options(),
+                format,
+                quiet,
+                &render,
+            )?;
+        }
+        Commands::Diff {
+            template,
+            template_format,
+            before,
+            after,
+            key,
+            transcript: transcript_args,
+            format,
+            exit_code,
+            quiet,
+        } => {
+            let differs = handle_diff(
+                &template,
+                template_format,
+                [&before, &after],
+                &key,
+                &transcript_args.options(),
+                format,
+                quiet,
+            )?;
+            if differs && exit_code {
+                io::stdout().flush()?;
+                std::process::exit(1);
             }
         }
+        Commands::Identify {
+            input,
+            transcript: transcript_args,
+            limit,
+            all,
+            format,
+            quiet,
+        } => handle_identify(
+            input.as_deref(),
+            &transcript_args.options(),
+            limit,
+            all,
+            format,
+            quiet,
+        )?,
         Commands::Debug { template, input } => tui::run_debugger(template, input)?,
 
         Commands::Convert {
@@ -638,6 +669,1428 @@ fn run_command(cli: Cli) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Parse two captures with one template and print their differences; returns whether they
+/// differ.
+/// `parse` options from the command line, shared by the single-template and routed
+/// (`--auto-template`, `--bundle`) modes.
+struct ParseSettings {
+    parse_options: cliscrape::ParseOptions,
+    transcript_options: transcript::TranscriptOptions,
+    result_query: query::ResultQuery,
+    render: RenderArgs,
+    format: OutputFormat,
+    output_path: Option<PathBuf>,
+    output_dir: Option<PathBuf>,
+    output_name: String,
+    group_by_source: bool,
+    ndjson_warnings: bool,
+    path_capture: Option<Box<regex::Regex>>,
+    command_column: Option<String>,
+    quiet: bool,
+    start_time: Instant,
+}
+
+impl ParseSettings {
+    /// `--format` with `auto` resolved by TTY; files and keyed documents default to JSON.
+    fn final_format(&self) -> OutputFormat {
+        if self.format != OutputFormat::Auto {
+            return self.format;
+        }
+        if io::stdout().is_terminal() && self.output_dir.is_none() && !self.group_by_source {
+            OutputFormat::Table
+        } else {
+            OutputFormat::Json
+        }
+    }
+
+    /// Whether records stay grouped by input source: report formats keep each input's records
+    /// apart, as do SQLite for provenance, per-source files and keyed output; queries then run
+    /// per source.
+    fn per_source(&self, format: OutputFormat, source_count: usize) -> bool {
+        (matches!(
+            format,
+            OutputFormat::Markdown | OutputFormat::Html | OutputFormat::OpenMetrics
+        ) && source_count > 1)
+            || format == OutputFormat::Sqlite
+            || self.output_dir.is_some()
+            || self.group_by_source
+    }
+}
+
+/// The template a single-template `parse` runs with.
+struct ParseTemplate {
+    parser: FsmParser,
+    loader_warnings: Vec<cliscrape::TemplateWarning>,
+    resolved: ResolvedTemplate,
+}
+
+/// Records and diagnostics gathered from the inputs of a single-template `parse`.
+struct ParsedSources {
+    /// Records of every source, merged per table (unless kept per source).
+    all_tables: output::RecordTables,
+    /// Records per source, or per transcript block for keyed multi-command captures.
+    source_groups: Vec<(String, output::RecordTables)>,
+    source_stats: Vec<output::SourceStats>,
+    warnings: Vec<(String, cliscrape::TemplateWarning)>,
+}
+
+/// `parse` with one template (or `--template auto`): parse every input source, then write the
+/// records to stdout, a file, per-source files or a SQLite database.
+fn handle_parse(
+    settings: &ParseSettings,
+    template: String,
+    template_format: CliTemplateFormat,
+    input_sources: &[InputSource],
+) -> anyhow::Result<()> {
+    let (template, prefetched) = if template == "auto" {
+        identify_input_template(input_sources, &settings.transcript_options)?
+    } else {
+        (template, std::collections::VecDeque::new())
+    };
+
+    // Template resolution: path vs identifier
+    let resolved = resolve_template(&template, template_format)?;
+    let (parser, loader_warnings) = load_parser(&resolved.path, template_format)?;
+    let options = &settings.parse_options;
+
+    let span = tracing::info_span!(
+        target: "cliscrape",
+        "cmd.parse",
+        template_spec = %template,
+        template_format = template_format_label(template_format),
+        input_source_count = input_sources.len(),
+        strict = options.strict,
+        threshold = ?options.threshold,
+        timeout_ms = ?options.timeout_ms,
+        quiet = settings.quiet,
+        output_format = output_format_label(settings.format)
+    );
+    let _guard = span.enter();
+
+    tracing::info!(
+        target: "cliscrape::cli",
+        event = "parse_start",
+        template_spec = %template,
+        template_format = template_format_label(template_format),
+        input_source_count = input_sources.len(),
+        strict = options.strict,
+        threshold = ?options.threshold,
+        timeout_ms = ?options.timeout_ms
+    );
+
+    // Convert template loader warnings into structured log events
+    for warning in &loader_warnings {
+        tracing::warn!(
+            target: "cliscrape::cli",
+            event = "template_loader_warning",
+            kind = %warning.kind,
+            message = %warning.message
+        );
+    }
+
+    let final_format = settings.final_format();
+    check_parse_format(settings, &parser, final_format)?;
+    let template = ParseTemplate {
+        parser,
+        loader_warnings,
+        resolved,
+    };
+
+    // NDJSON streams each block's records as soon as it is parsed, unless a query stage needs
+    // the whole result set. Otherwise parsing is fail-fast: collect all records before writing
+    // to stdout.
+    let mut stream = if final_format == OutputFormat::Ndjson
+        && settings.result_query.is_streamable()
+        && settings.output_dir.is_none()
+    {
+        let out: Box<dyn Write> = match &settings.output_path {
+            Some(path) => Box::new(io::BufWriter::new(
+                std::fs::File::create(path)
+                    .with_context(|| format!("Failed to create output file {}", path.display()))?,
+            )),
+            None => Box::new(io::stdout().lock()),
+        };
+        Some(output::NdjsonWriter::new(
+            out,
+            template.parser.has_named_tables(),
+            settings
+                .result_query
+                .columns(template.parser.column_order()),
+        ))
+    } else {
+        None
+    };
+
+    let parsed = parse_sources(
+        &template,
+        settings,
+        input_sources,
+        prefetched,
+        final_format,
+        stream.as_mut(),
+    )?;
+
+    // Convert warnings into structured log events
+    for (_, warning) in &parsed.warnings {
+        let one_based_line = warning.line_idx.map(|idx| idx + 1);
+        tracing::warn!(
+            target: "cliscrape::cli",
+            event = "parse_warning",
+            kind = %warning.kind,
+            line_idx = ?one_based_line,
+            message = %warning.message
+        );
+    }
+    let warning_count = parsed.warnings.len();
+
+    let record_count = match stream {
+        Some(writer) => writer.records(),
+        None => emit_parsed(&template, settings, input_sources, parsed, final_format)?,
+    };
+
+    // Convert success status to a structured info event (unless --quiet)
+    if !settings.quiet {
+        tracing::info!(
+            target: "cliscrape::cli",
+            event = "parse_finish",
+            records = record_count,
+            warnings = warning_count,
+            sources = input_sources.len(),
+            elapsed_ms = settings.start_time.elapsed().as_millis() as u64
+        );
+    }
+    Ok(())
+}
+
+/// `--template auto`: read every input up front and pick the best-scoring template for it.
+///
+/// Returns the template name and the inputs' contents, in input order.
+fn identify_input_template(
+    input_sources: &[InputSource],
+    transcript_options: &transcript::TranscriptOptions,
+) -> anyhow::Result<(String, std::collections::VecDeque<String>)> {
+    let prefetched = input_sources
+        .iter()
+        .map(InputSource::read)
+        .collect::<anyhow::Result<std::collections::VecDeque<_>>>()?;
+    let sample: Vec<String> = input_sources
+        .iter()
+        .zip(&prefetched)
+        .map(|(source, content)| transcript_text(content, source, transcript_options))
+        .collect();
+    let best = identify_templates(&sample.join("\n"))?
+        .into_iter()
+        .next()
+        .filter(|candidate| candidate.records > 0)
+        .context("--template auto: no template emits records for this input")?;
+    tracing::info!(
+        target: "cliscrape::cli",
+        event = "template_identified",
+        name = %best.name,
+        confidence = best.confidence,
+        records = best.records
+    );
+    Ok((best.name, prefetched))
+}
+
+/// Reject output settings the resolved format (or the template) cannot honour.
+fn check_parse_format(
+    settings: &ParseSettings,
+    parser: &FsmParser,
+    format: OutputFormat,
+) -> anyhow::Result<()> {
+    if format == OutputFormat::Sqlite && !cfg!(feature = "sqlite") {
+        anyhow::bail!("--format sqlite needs cliscrape built with the `sqlite` feature");
+    }
+    if format == OutputFormat::Sqlite && settings.output_path.is_none() {
+        anyhow::bail!("--format sqlite requires --output <PATH>");
+    }
+    if format == OutputFormat::Sqlite && settings.output_dir.is_some() {
+        anyhow::bail!("--format sqlite writes a single database; use --output, not --output-dir");
+    }
+    if format == OutputFormat::OpenMetrics && parser.metrics().is_none() {
+        anyhow::bail!("--format openmetrics requires a 'metadata.metrics' mapping in the template");
+    }
+    if settings.group_by_source && !matches!(format, OutputFormat::Json | OutputFormat::Yaml) {
+        anyhow::bail!("--group-by-source requires --format json or yaml");
+    }
+    Ok(())
+}
+
+/// Parse options for one input: the run's options plus its `--path-capture` columns.
+fn source_parse_options(
+    settings: &ParseSettings,
+    source: &InputSource,
+    warnings: &mut Vec<cliscrape::TemplateWarning>,
+) -> cliscrape::ParseOptions {
+    let (Some(pattern), InputSource::File(path)) = (&settings.path_capture, source) else {
+        return settings.parse_options.clone();
+    };
+    match settings
+        .parse_options
+        .clone()
+        .with_path_captures(pattern, path)
+    {
+        Some(options) => options,
+        None => {
+            warnings.push(cliscrape::TemplateWarning {
+                kind: "path_capture".to_string(),
+                message: format!("--path-capture pattern did not match {}", path.display()),
+                line_idx: None,
+            });
+            settings.parse_options.clone()
+        }
+    }
+}
+
+/// Parse every transcript block of every input source. With `stream`, each block's records
+/// (and, with --ndjson-warnings, its warnings) are written as soon as it is parsed; otherwise
+/// they are collected, per source when the output keeps sources apart.
+fn parse_sources(
+    template: &ParseTemplate,
+    settings: &ParseSettings,
+    input_sources: &[InputSource],
+    mut prefetched: std::collections::VecDeque<String>,
+    final_format: OutputFormat,
+    mut stream: Option<&mut output::NdjsonWriter<Box<dyn Write>>>,
+) -> anyhow::Result<ParsedSources> {
+    let parser = &template.parser;
+    let per_source = settings.per_source(final_format, input_sources.len());
+    let mut parsed_sources = ParsedSources {
+        all_tables: BTreeMap::new(),
+        source_groups: Vec::new(),
+        source_stats: Vec::new(),
+        warnings: Vec::new(),
+    };
+
+    for source in input_sources {
+        let content = match prefetched.pop_front() {
+            Some(content) => content,
+            None => source.read()?,
+        };
+
+        let mut source_warnings = Vec::new();
+        let source_options = source_parse_options(settings, source, &mut source_warnings);
+
+        let mut source_tables: BTreeMap<String, Vec<_>> = BTreeMap::new();
+        let mut stats = output::SourceStats {
+            name: source.display(),
+            lines: content.lines().count(),
+            records: 0,
+        };
+        let (blocks, transcript_warnings) = transcript::preprocess_transcript_with_warnings(
+            &content,
+            &settings.transcript_options.for_source(source.path()),
+        );
+        for w in transcript_warnings {
+            source_warnings.push(cliscrape::TemplateWarning {
+                kind: "transcript".to_string(),
+                message: w,
+                line_idx: None,
+            });
+        }
+
+        // Keyed output gives each transcript block of a multi-command capture its own key.
+        let split_blocks = settings.group_by_source && blocks.len() > 1;
+        for (idx, block) in blocks.iter().enumerate() {
+            let mut block_options = source_options.clone();
+            if let Some(column) = &settings.command_column {
+                block_options
+                    .constants
+                    .insert(column.clone(), block.command.clone().unwrap_or_default());
+            }
+            let parsed = transcript_lines(
+                block,
+                parser.tables_with_warnings(&block.text, block_options),
+            )
+            .with_context(|| {
+                format!(
+                    "Failed to parse block {} ({}) from {}",
+                    idx + 1,
+                    block.describe(),
+                    source.display()
+                )
+            });
+            let (mut parsed, warnings) = match parsed {
+                Ok(parsed) => parsed,
+                // The envelope reports the failure too, so consumers always get a document.
+                Err(err) if final_format == OutputFormat::JsonEnvelope => {
+                    let warnings: Vec<_> = template
+                        .loader_warnings
+                        .iter()
+                        .map(|w| (template.resolved.name.clone(), w.clone()))
+                        .chain(parsed_sources.warnings.iter().cloned())
+                        .chain(
+                            source_warnings
+                                .iter()
+                                .map(|w| (source.display(), w.clone())),
+                        )
+                        .collect();
+                    let done = &parsed_sources.source_stats;
+                    let stats = output::EnvelopeStats {
+                        lines: done.iter().map(|s| s.lines).sum::<usize>() + stats.lines,
+                        records: done.iter().map(|s| s.records).sum::<usize>() + stats.records,
+                        elapsed_ms: settings.start_time.elapsed().as_millis() as u64,
+                        coverage: None,
+                    };
+                    let envelope = output::serialize_failed_envelope(
+                        &format!("{:#}", err),
+                        &warnings,
+                        &stats,
+                    )?;
+                    write_output(&envelope, settings.output_path.as_deref())?;
+                    return Err(err);
+                }
+                Err(err) => return Err(err),
+            };
+            stats.records += parsed.values().map(Vec::len).sum::<usize>();
+            match stream.as_mut() {
+                Some(writer) => {
+                    for rows in parsed.values_mut() {
+                        *rows = settings.result_query.apply(std::mem::take(rows));
+                    }
+                    // Source-level warnings precede the block's records; parse warnings follow
+                    // them.
+                    if settings.ndjson_warnings {
+                        for warning in &source_warnings {
+                            writer.write_warning(&source.display(), warning)?;
+                        }
+                    }
+                    writer.write_tables(&parsed)?;
+                    if settings.ndjson_warnings {
+                        for warning in &warnings {
+                            writer.write_warning(&source.display(), warning)?;
+                        }
+                    }
+                    parsed_sources.warnings.extend(
+                        source_warnings
+                            .drain(..)
+                            .chain(warnings)
+                            .map(|w| (source.display(), w)),
+                    );
+                }
+                None => {
+                    source_warnings.extend(warnings);
+                    if split_blocks {
+                        let key = format!("{}#{}", source.display(), idx + 1);
+                        parsed_sources.source_groups.push((key, parsed));
+                        continue;
+                    }
+                    let tables = if per_source {
+                        &mut source_tables
+                    } else {
+                        &mut parsed_sources.all_tables
+                    };
+                    for (table, mut rows) in parsed {
+                        tables.entry(table).or_default().append(&mut rows);
+                    }
+                }
+            }
+        }
+        parsed_sources
+            .warnings
+            .extend(source_warnings.into_iter().map(|w| (source.display(), w)));
+        parsed_sources.source_stats.push(stats);
+        if per_source && !split_blocks {
+            parsed_sources
+                .source_groups
+                .push((source.display(), source_tables));
+        }
+    }
+    Ok(parsed_sources)
+}
+
+/// Renders one output document from record tables: the whole run, or one source's file for
+/// --output-dir.
+struct RecordRenderer<'a> {
+    template: &'a ParseTemplate,
+    settings: &'a ParseSettings,
+    format: OutputFormat,
+    columns: Vec<String>,
+    /// Set for the JSON envelope.
+    identity: Option<output::TemplateIdentity>,
+    warnings: &'a [(String, cliscrape::TemplateWarning)],
+}
+
+impl RecordRenderer<'_> {
+    /// Render the records of `sources`, with their warnings where the format carries them.
+    fn render(
+        &self,
+        tables: &output::RecordTables,
+        sources: &[output::SourceStats],
+        coverage: Option<f64>,
+    ) -> anyhow::Result<String> {
+        let parser = &self.template.parser;
+        let source_warnings: Vec<_> = self
+            .warnings
+            .iter()
+            .filter(|(source, _)| sources.iter().any(|s| &s.name == source))
+            .collect();
+        let mut output = if let Some(identity) = &self.identity {
+            let warnings: Vec<_> = self
+                .template
+                .loader_warnings
+                .iter()
+                .map(|w| (identity.name.clone(), w.clone()))
+                .chain(source_warnings.iter().map(|&w| w.clone()))
+                .collect();
+            let stats = output::EnvelopeStats {
+                lines: sources.iter().map(|s| s.lines).sum(),
+                records: tables.values().map(Vec::len).sum(),
+                elapsed_ms: self.settings.start_time.elapsed().as_millis() as u64,
+                coverage,
+            };
+            output::serialize_envelope(
+                identity,
+                sources,
+                tables,
+                parser.has_named_tables(),
+                &self.columns,
+                &warnings,
+                &stats,
+            )?
+        } else if self.format == OutputFormat::OpenMetrics {
+            let source = sources.first().map_or("", |s| s.name.as_str());
+            let records: Vec<_> = tables.values().flatten().map(|r| (source, r)).collect();
+            openmetrics_text(&records, parser, false)
+        } else if parser.has_named_tables() {
+            output::serialize_tables(tables, &self.columns, self.format, &self.settings.render)?
+        } else {
+            let records = tables
+                .get(cliscrape::engine::DEFAULT_TABLE)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            output::serialize(records, &self.columns, self.format, &self.settings.render)?
+        };
+        if self.format == OutputFormat::Ndjson && self.settings.ndjson_warnings {
+            for (source, warning) in source_warnings {
+                if !output.is_empty() {
+                    output.push('\n');
+                }
+                output.push_str(&output::warning_line(source, warning)?);
+            }
+        }
+        Ok(output)
+    }
+}
+
+/// Run the query over the collected records and write them in the layout the settings ask
+/// for. Returns the number of records written.
+fn emit_parsed(
+    template: &ParseTemplate,
+    settings: &ParseSettings,
+    input_sources: &[InputSource],
+    parsed: ParsedSources,
+    final_format: OutputFormat,
+) -> anyhow::Result<usize> {
+    let ParsedSources {
+        mut all_tables,
+        mut source_groups,
+        source_stats,
+        warnings,
+    } = parsed;
+    let parser = &template.parser;
+    let per_source = settings.per_source(final_format, input_sources.len());
+
+    // Coverage describes what the template captured, before any query reshaping.
+    let envelope = final_format == OutputFormat::JsonEnvelope;
+    let coverage = envelope
+        .then(|| parser.result_coverage(&all_tables))
+        .flatten();
+    let group_coverage: Vec<_> = source_groups
+        .iter()
+        .map(|(_, tables)| envelope.then(|| parser.result_coverage(tables)).flatten())
+        .collect();
+    if !settings.result_query.is_empty() {
+        let groups = source_groups.iter_mut().map(|(_, tables)| tables);
+        for tables in std::iter::once(&mut all_tables).chain(groups) {
+            for rows in tables.values_mut() {
+                *rows = settings.result_query.apply(std::mem::take(rows));
+            }
+        }
+    }
+
+    let record_count = std::iter::once(&all_tables)
+        .chain(source_groups.iter().map(|(_, tables)| tables))
+        .flat_map(|tables| tables.values())
+        .map(Vec::len)
+        .sum::<usize>();
+    let renderer = RecordRenderer {
+        template,
+        settings,
+        format: final_format,
+        columns: settings.result_query.columns(parser.column_order()),
+        identity: envelope
+            .then(|| {
+                let resolved = &template.resolved;
+                template_identity(&resolved.path, resolved.name.clone(), resolved.source)
+            })
+            .transpose()?,
+        warnings: &warnings,
+    };
+
+    let output = if final_format == OutputFormat::Sqlite {
+        #[cfg(feature = "sqlite")]
+        export_sqlite(
+            parser,
+            &template.resolved.name,
+            &source_groups,
+            settings
+                .output_path
+                .as_deref()
+                .expect("checked before parsing"),
+        )?;
+        String::new()
+    } else if let Some(dir) = &settings.output_dir {
+        write_output_dir(
+            dir,
+            &settings.output_name,
+            &source_groups,
+            &source_stats,
+            input_sources,
+            final_format,
+            |index, tables, stats| {
+                renderer.render(tables, std::slice::from_ref(stats), group_coverage[index])
+            },
+        )?;
+        String::new()
+    } else if settings.group_by_source {
+        output::serialize_keyed(
+            &source_groups,
+            parser.has_named_tables(),
+            &renderer.columns,
+            final_format,
+        )?
+    } else if per_source && final_format == OutputFormat::OpenMetrics {
+        let records: Vec<_> = source_groups
+            .iter()
+            .flat_map(|(source, tables)| {
+                tables.values().flatten().map(move |r| (source.as_str(), r))
+            })
+            .collect();
+        openmetrics_text(&records, parser, true)
+    } else if per_source {
+        output::serialize_source_groups(
+            &source_groups,
+            parser.has_named_tables(),
+            &renderer.columns,
+            final_format,
+            &settings.render,
+        )?
+    } else {
+        renderer.render(&all_tables, &source_stats, coverage)?
+    };
+    // An empty NDJSON stream has no lines at all; SQLite and --output-dir write no text.
+    let silent = (final_format == OutputFormat::Ndjson && output.is_empty())
+        || final_format == OutputFormat::Sqlite
+        || settings.output_dir.is_some();
+    if !silent {
+        write_output(&output, settings.output_path.as_deref())?;
+    }
+    Ok(record_count)
+}
+
+/// `--output-dir`: write each source's records, rendered by `render(index, tables, stats)`, to
+/// a file named by `output_name`. Groups, stats and sources line up one-to-one here.
+fn write_output_dir(
+    dir: &Path,
+    output_name: &str,
+    source_groups: &[(String, output::RecordTables)],
+    source_stats: &[output::SourceStats],
+    input_sources: &[InputSource],
+    format: OutputFormat,
+    render: impl Fn(usize, &output::RecordTables, &output::SourceStats) -> anyhow::Result<String>,
+) -> anyhow::Result<()> {
+    let mut written: BTreeMap<String, &str> = BTreeMap::new();
+    let files = source_groups.iter().zip(source_stats).zip(input_sources);
+    for (index, (((name, tables), stats), source)) in files.enumerate() {
+        let file_name = output_file_name(output_name, source, index + 1, format)?;
+        if let Some(previous) = written.insert(file_name.clone(), name) {
+            anyhow::bail!(
+                "--output-name '{}' gives '{}' for both {} and {} (add {{parent}} or {{index}})",
+                output_name,
+                file_name,
+                previous,
+                name
+            );
+        }
+        let text = render(index, tables, stats)?;
+        let path = dir.join(&file_name);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).with_context(|| {
+                format!("Failed to create output directory {}", parent.display())
+            })?;
+        }
+        write_output(&text, Some(&path))?;
+    }
+    Ok(())
+}
+
+fn handle_diff(
+    template: &str,
+    template_format: CliTemplateFormat,
+    [before, after]: [&Path; 2],
+    key: &[String],
+    transcript_options: &transcript::TranscriptOptions,
+    format: OutputFormat,
+    quiet: bool,
+) -> anyhow::Result<bool> {
+    let start_time = Instant::now();
+    let format = match format {
+        OutputFormat::Auto if io::stdout().is_terminal() => OutputFormat::Table,
+        OutputFormat::Auto => OutputFormat::Json,
+        OutputFormat::Table | OutputFormat::Json | OutputFormat::Yaml => format,
+        other => anyhow::bail!(
+            "{} format not supported for diff",
+            output_format_label(other)
+        ),
+    };
+
+    let span = tracing::info_span!(
+        target: "cliscrape",
+        "cmd.diff",
+        template_spec = %template,
+        before = %before.display(),
+        after = %after.display(),
+        output_format = output_format_label(format)
+    );
+    let _guard = span.enter();
+
+    let template_path = resolve_template(template, template_format)?.path;
+    let (parser, loader_warnings) = load_parser(&template_path, template_format)?;
+    for warning in &loader_warnings {
+        tracing::warn!(
+            target: "cliscrape::cli",
+            event = "template_loader_warning",
+            kind = %warning.kind,
+            message = %warning.message
+        );
+    }
+    let field_names = parser.field_names();
+    if let Some(unknown) = key.iter().find(|k| !field_names.contains(k)) {
+        anyhow::bail!("--key '{}' is not a template field", unknown);
+    }
+
+    let mut before_tables = parse_capture(&parser, before, transcript_options)?;
+    let mut after_tables = parse_capture(&parser, after, transcript_options)?;
+    let columns = parser.column_order();
+    let diffs: Vec<(String, diff::TableDiff)> = parser
+        .table_names()
+        .into_iter()
+        .map(|table| {
+            let table_key: Vec<String> = if key.is_empty() {
+                parser
+                    .table_columns(&table)
+                    .into_iter()
+                    .filter(|v| v.required)
+                    .map(|v| v.name.clone())
+                    .collect()
+            } else {
+                key.to_vec()
+            };
+            let records = diff::diff_records(
+                before_tables.remove(&table).unwrap_or_default(),
+                after_tables.remove(&table).unwrap_or_default(),
+                &table_key,
+                &columns,
+            );
+            (table, records)
+        })
+        .collect();
+
+    // Multi-table templates render one diff per table, keyed (or headed) by table name.
+    let named = parser.has_named_tables();
+    let rendered = match format {
+        OutputFormat::Table if named => diffs
+            .iter()
+            .map(|(table, diff)| format!("# {}\n{}", table, diff::render_table(diff)))
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        OutputFormat::Table => diffs
+            .first()
+            .map(|(_, diff)| diff::render_table(diff))
+            .unwrap_or_default(),
+        _ => {
+            fn render(document: &impl serde::Serialize, yaml: bool) -> anyhow::Result<String> {
+                Ok(if yaml {
+                    serde_yaml_ng::to_string(document)?.trim_end().to_string()
+                } else {
+                    serde_json::to_string_pretty(document)?
+                })
+            }
+            let yaml = format == OutputFormat::Yaml;
+            if named {
+                let document: BTreeMap<_, _> = diffs.iter().map(|(t, d)| (t, d)).collect();
+                render(&document, yaml)?
+            } else {
+                render(&diffs.first().map(|(_, diff)| diff), yaml)?
+            }
+        }
+    };
+    println!("{}", rendered);
+
+    let differs = diffs.iter().any(|(_, diff)| !diff.is_empty());
+    if !quiet {
+        let count =
+            |f: fn(&diff::TableDiff) -> usize| diffs.iter().map(|(_, d)| f(d)).sum::<usize>();
+        tracing::info!(
+            target: "cliscrape::cli",
+            event = "diff_finish",
+            added = count(|d| d.added.len()),
+            removed = count(|d| d.removed.len()),
+            changed = count(|d| d.changed.len()),
+            unchanged = count(|d| d.unchanged),
+            elapsed_ms = start_time.elapsed().as_millis() as u64
+        );
+    }
+    Ok(differs)
+}
+
+/// The command output in `content`, without session-log wrapping, prompts or echoes.
+fn transcript_text(
+    content: &str,
+    source: &InputSource,
+    transcript_options: &transcript::TranscriptOptions,
+) -> String {
+    let options = transcript_options.for_source(source.path());
+    let (blocks, _) = transcript::preprocess_transcript_with_warnings(content, &options);
+    let text: Vec<&str> = blocks.iter().map(|block| block.text.as_str()).collect();
+    text.join("\n")
+}
+
+/// Score every embedded and user template against `sample` (see `cliscrape::identify`).
+///
+/// Templates that fail to load are skipped with a warning.
+fn identify_templates(sample: &str) -> anyhow::Result<Vec<identify::Candidate>> {
+    let resolver = TemplateResolver::new()
+        .map_err(|e| anyhow::anyhow!("Failed to initialize template resolver: {}", e))?;
+    let mut templates = Vec::new();
+    for name in resolver.list_templates() {
+        let loaded = resolve_template(&name, CliTemplateFormat::Auto)
+            .and_then(|resolved| load_parser(&resolved.path, CliTemplateFormat::Auto));
+        match loaded {
+            Ok((parser, _)) => templates.push((name, parser)),
+            Err(e) => tracing::warn!(
+                target: "cliscrape::cli",
+                event = "template_load_failed",
+                name = %name,
+                error = %format!("{:#}", e)
+            ),
+        }
+    }
+
+    Ok(identify::rank(&templates, sample))
+}
+
+/// Handle identify command: print the best-scoring templates for a capture.
+fn handle_identify(
+    input: Option<&Path>,
+    transcript_options: &transcript::TranscriptOptions,
+    limit: usize,
+    all: bool,
+    format: OutputFormat,
+    quiet: bool,
+) -> anyhow::Result<()> {
+    let start_time = Instant::now();
+    let format = match format {
+        OutputFormat::Auto if io::stdout().is_terminal() => OutputFormat::Table,
+        OutputFormat::Auto => OutputFormat::Json,
+        OutputFormat::Table | OutputFormat::Json | OutputFormat::Yaml => format,
+        other => anyhow::bail!(
+            "{} format not supported for identify",
+            output_format_label(other)
+        ),
+    };
+    let source = input.map_or(InputSource::Stdin, |p| InputSource::File(p.to_path_buf()));
+
+    let span = tracing::info_span!(
+        target: "cliscrape",
+        "cmd.identify",
+        input = %source.display(),
+        output_format = output_format_label(format)
+    );
+    let _guard = span.enter();
+
+    let ranked = identify_templates(&transcript_text(
+        &source.read()?,
+        &source,
+        transcript_options,
+    ))?;
+    let scored = ranked.len();
+    let candidates: Vec<_> = ranked
+        .into_iter()
+        .filter(|candidate| all || candidate.records > 0)
+        .take(limit)
+        .collect();
+
+    match format {
+        OutputFormat::Table => {
+            let mut table = Table::new();
+            table.load_preset(presets::UTF8_FULL);
+            table.set_header(vec![
+                "Rank",
+                "Template",
+                "Confidence",
+                "Records",
+                "Field coverage",
+                "Line coverage",
+            ]);
+            for (rank, candidate) in candidates.iter().enumerate() {
+                table.add_row(vec![
+                    (rank + 1).to_string(),
+                    candidate.name.clone(),
+                    format!("{:.0}%", candidate.confidence * 100.0),
+                    candidate.records.to_string(),
+                    format!("{:.1}%", candidate.field_coverage),
+                    format!("{:.1}%", candidate.line_coverage),
+                ]);
+            }
+            println!("{}", table);
+        }
+        OutputFormat::Yaml => print!("{}", serde_yaml_ng::to_string(&candidates)?),
+        _ => println!("{}", serde_json::to_string_pretty(&candidates)?),
+    }
+
+    if !quiet {
+        tracing::info!(
+            target: "cliscrape::cli",
+            event = "identify_finish",
+            templates = scored,
+            matches = candidates.len(),
+            best = ?candidates.first().map(|c| c.name.as_str()),
+            elapsed_ms = start_time.elapsed().as_millis() as u64
+        );
+    }
+    Ok(())
+}
+
+/// Shift the line numbers of a block's parse warnings and errors to transcript lines.
+fn transcript_lines<T>(
+    block: &transcript::TranscriptBlock,
+    result: Result<(T, Vec<cliscrape::TemplateWarning>), cliscrape::ScraperError>,
+) -> Result<(T, Vec<cliscrape::TemplateWarning>), cliscrape::ScraperError> {
+    match result {
+        Ok((parsed, mut warnings)) => {
+            for warning in &mut warnings {
+                warning.line_idx = warning.line_idx.map(|idx| block.original_line_idx(idx));
+            }
+            Ok((parsed, warnings))
+        }
+        Err(cliscrape::ScraperError::Parse(mut detail)) => {
+            detail.line_idx = block.original_line_idx(detail.line_idx);
+            Err(cliscrape::ScraperError::Parse(detail))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Parse every transcript block of the capture at `path`, merging records per table.
+fn parse_capture(
+    parser: &FsmParser,
+    path: &Path,
+    transcript_options: &transcript::TranscriptOptions,
+) -> anyhow::Result<output::RecordTables> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read input from {}", path.display()))?;
+    let (blocks, transcript_warnings) = transcript::preprocess_transcript_with_warnings(
+        &content,
+        &transcript_options.for_source(Some(path)),
+    );
+    for w in transcript_warnings {
+        tracing::warn!(target: "cliscrape::cli", event = "parse_warning", kind = "transcript", message = %w);
+    }
+
+    let mut tables = output::RecordTables::new();
+    for (idx, block) in blocks.iter().enumerate() {
+        let (parsed, warnings) = transcript_lines(
+            block,
+            parser.tables_with_warnings(&block.text, cliscrape::ParseOptions::default()),
+        )
+        .with_context(|| {
+            format!(
+                "Failed to parse block {} ({}) from {}",
+                idx + 1,
+                block.describe(),
+                path.display()
+            )
+        })?;
+        for warning in &warnings {
+            tracing::warn!(
+                target: "cliscrape::cli",
+                event = "parse_warning",
+                kind = %warning.kind,
+                line_idx = ?warning.line_idx.map(|idx| idx + 1),
+                message = %warning.message
+            );
+        }
+        for (table, mut rows) in parsed {
+            tables.entry(table).or_default().append(&mut rows);
+        }
+    }
+    Ok(tables)
+}
+
+fn handle_pipeline(
+    spec_path: &Path,
+    input_sources: &[InputSource],
+    transcript_options: &transcript::TranscriptOptions,
+    format: OutputFormat,
+    quiet: bool,
+    render: &RenderArgs,
+) -> anyhow::Result<()> {
+    let start_time = Instant::now();
+    if matches!(
+        format,
+        OutputFormat::JsonEnvelope | OutputFormat::Sqlite | OutputFormat::OpenMetrics
+    ) {
+        anyhow::bail!(
+            "{} format not supported for pipelines",
+            output_format_label(format)
+        );
+    }
+    let spec_text = std::fs::read_to_string(spec_path)
+        .with_context(|| format!("Failed to read pipeline spec {}", spec_path.display()))?;
+    let spec = pipeline::PipelineSpec::from_yaml_str(&spec_text)
+        .with_context(|| format!("Failed to load pipeline spec {}", spec_path.display()))?;
+
+    let span = tracing::info_span!(
+        target: "cliscrape",
+        "cmd.pipeline",
+        spec = %spec_path.display(),
+        source_count = spec.sources.len(),
+        input_source_count = input_sources.len(),
+        output_format = output_format_label(format)
+    );
+    let _guard = span.enter();
+
+    // Template paths in the spec are relative to the spec file when they exist there.
+    let spec_dir = spec_path.parent().unwrap_or(Path::new("."));
+    let mut parsers = BTreeMap::new();
+    for (name, source) in &spec.sources {
+        let local = spec_dir.join(&source.template);
+        let template_path = if local.exists() {
+            local
+        } else {
+            resolve_template_spec(&source.template, CliTemplateFormat::Auto)?
+        };
+        let (parser, loader_warnings) = FsmParser::from_file_with_warnings(&template_path)
+            .with_context(|| {
+                format!(
+                    "Failed to load template for source '{}' from {}",
+                    name,
+                    template_path.display()
+                )
+            })?;
+        for warning in &loader_warnings {
+            tracing::warn!(
+                target: "cliscrape::cli",
+                event = "template_loader_warning",
+                source = %name,
+                kind = %warning.kind,
+                message = %warning.message
+            );
+        }
+        parsers.insert(name.clone(), parser);
+    }
+
+    // Each input source is one device's capture; joins never match rows across sources. With
+    // several, base records get a `source` column (a constant, so a template field of that name
+    // wins) to tell the devices apart.
+    let source_column = input_sources.len() > 1;
+    let mut combined = Vec::new();
+    let mut all_warnings = Vec::new();
+    for source in input_sources {
+        let content = source.read()?;
+        let mut results: BTreeMap<String, Vec<pipeline::Record>> = BTreeMap::new();
+
+        let (blocks, transcript_warnings) = transcript::preprocess_transcript_with_warnings(
+            &content,
+            &transcript_options.for_source(source.path()),
+        );
+        for w in transcript_warnings {
+            all_warnings.push(cliscrape::TemplateWarning {
+                kind: "transcript".to_string(),
+                message: w,
+                line_idx: None,
+            });
+        }
+
+        for (idx, block) in blocks.iter().enumerate() {
+            let Some(name) = block.command.as_deref().and_then(|cmd| spec.route(cmd)) else {
+                all_warnings.push(cliscrape::TemplateWarning {
+                    kind: "pipeline_unrouted_block".to_string(),
+                    message: format!(
+                        "Block {} from {} ({}) matches no pipeline source - skipping",
+                        idx + 1,
+                        source.display(),
+                        block.command.as_deref().unwrap_or("no command echo")
+                    ),
+                    line_idx: None,
+                });
+                continue;
+            };
+
+            let mut options = cliscrape::ParseOptions::default();
+            if source_column && name == spec.base {
+                options
+                    .constants
+                    .insert("source".to_string(), source.display());
+            }
+            let (mut parsed, warnings) = transcript_lines(
+                block,
+                parsers[name].results_with_warnings(&block.text, options),
+            )
+            .with_context(|| {
+                format!(
+                    "Failed to parse block {} ({}) from {} with source '{}'",
+                    idx + 1,
+                    block.describe(),
+                    source.display(),
+                    name
+                )
+            })?;
+            results
+                .entry(name.to_string())
+                .or_default()
+                .append(&mut parsed);
+            all_warnings.extend(warnings);
+        }
+
+        for name in spec.sources.keys() {
+            if !results.contains_key(name) {
+                all_warnings.push(cliscrape::TemplateWarning {
+                    kind: "pipeline_missing_source".to_string(),
+                    message: format!(
+                        "No input block from {} matched pipeline source '{}'",
+                        source.display(),
+                        name
+                    ),
+                    line_idx: None,
+                });
+            }
+        }
+
+        combined.extend(spec.combine(results, &mut all_warnings));
+    }
+
+    for warning in &all_warnings {
+        tracing::warn!(
+            target: "cliscrape::cli",
+            event = "parse_warning",
+            kind = %warning.kind,
+            message = %warning.message
+        );
+    }
+
+    let final_format = if format == OutputFormat::Auto {
+        if io::stdout().is_terminal() {
+            OutputFormat::Table
+        } else {
+            OutputFormat::Json
+        }
+    } else {
+        format
+    };
+    // The source column and base source columns lead; joined columns follow by name.
+    let mut columns = parsers
+        .get(&spec.base)
+        .map(FsmParser::column_order)
+        .unwrap_or_default();
+    if source_column && !columns.iter().any(|column| column == "source") {
+        columns.insert(0, "source".to_string());
+    }
+    println!(
+        "{}",
+        output::serialize(&combined, &columns, final_format, render)?
+    );
+
+    if !quiet {
+        tracing::info!(
+            target: "cliscrape::cli",
+            event = "pipeline_finish",
+            records = combined.len(),
+            warnings = all_warnings.len(),
+            sources = input_sources.len(),
+            elapsed_ms = start_time.elapsed().as_millis() as u64
+        );
+    }
+    Ok(())
+}
+
+/// Input for per-command routing: a whole input source, or one bundle output filed under a
+/// host and command.
+struct RoutedCapture {
+    label: String,
+    path: Option<PathBuf>,
+    host: Option<String>,
+    command: Option<String>,
+    content: String,
+}
+
+impl RoutedCapture {
+    /// One capture per bundle output, labelled with the bundle path and host.
+    fn from_bundle(path: &Path) -> anyhow::Result<Vec<Self>> {
+        let mut bundle_warnings = Vec::new();
+        let entries = bundle::load(path, &mut bundle_warnings)?;
+        for message in &bundle_warnings {
+            tracing::warn!(
+                target: "cliscrape::cli",
+                event = "parse_warning",
+                kind = "bundle",
+                message = %message
+            );
+        }
+        Ok(entries
+            .into_iter()
+            .map(|entry| Self {
+                label: format!("{} [{}]", path.display(), entry.host),
+                path: None,
+                host: Some(entry.host),
+                command: Some(entry.command),
+                content: entry.output,
+            })
+            .collect())
+    }
+
+    fn read(source: &InputSource) -> anyhow::Result<Self> {
+        Ok(Self {
+            label: source.display(),
+            path: source.path().map(Path::to_path_buf),
+            host: None,
+            command: None,
+            content: source.read()?,
+        })
+    }
+}
+
+/// `parse --auto-template` and `parse --bundle`: route each capture's blocks to templates (see
+/// `parse_auto_template`) and write the command-keyed record tables.
+fn handle_routed_parse(
+    settings: &ParseSettings,
+    platform: Option<&str>,
+    auto_template: bool,
+    routes: &RouteArgs,
+    input_sources: &[InputSource],
+) -> anyhow::Result<()> {
+    let mode = if routes.bundle.is_some() {
+        "--bundle"
+    } else {
+        "--auto-template"
+    };
+    if matches!(
+        settings.format,
+        OutputFormat::JsonEnvelope | OutputFormat::Sqlite | OutputFormat::OpenMetrics
+    ) {
+        anyhow::bail!(
+            "{} format not supported with {}",
+            output_format_label(settings.format),
+            mode
+        );
+    }
+    let captures = match &routes.bundle {
+        Some(path) => RoutedCapture::from_bundle(path)?,
+        None => input_sources
+            .iter()
+            .map(RoutedCapture::read)
+            .collect::<anyhow::Result<Vec<_>>>()?,
+    };
+    let template_map: BTreeMap<String, String> = routes
+        .template_map
+        .iter()
+        .map(|(command, template)| (normalize_command(command), template.clone()))
+        .collect();
+    let span = tracing::info_span!(
+        target: "cliscrape",
+        "cmd.parse",
+        platform = ?platform,
+        auto_template = auto_template,
+        bundle = ?routes.bundle,
+        input_source_count = captures.len(),
+        output_format = output_format_label(settings.format)
+    );
+    let _guard = span.enter();
+
+    let (mut tables, mut columns, warning_count) = parse_auto_template(
+        platform,
+        &template_map,
+        &settings.transcript_options,
+        &captures,
+        &settings.parse_options,
+    )?;
+    if routes.bundle.is_some() {
+        columns.retain(|column| column != "host" && column != "command");
+        columns.splice(0..0, ["host".to_string(), "command".to_string()]);
+    }
+    for rows in tables.values_mut() {
+        *rows = settings.result_query.apply(std::mem::take(rows));
+    }
+    let final_format = settings.final_format();
+    let columns = settings.result_query.columns(columns);
+    let output = output::serialize_tables(&tables, &columns, final_format, &settings.render)?;
+    if !(final_format == OutputFormat::Ndjson && output.is_empty()) {
+        write_output(&output, settings.output_path.as_deref())?;
+    }
+
+    let record_count = tables.values().map(Vec::len).sum::<usize>();
+    if !settings.quiet {
+        tracing::info!(
+            target: "cliscrape::cli",
+            event = "parse_finish",
+            records = record_count,
+            warnings = warning_count,
+            sources = captures.len(),
+            elapsed_ms = settings.start_time.elapsed().as_millis() as u64
+        );
+    }
+    Ok(())
+}
+
+/// `parse --auto-template` and `parse --bundle`: parse each transcript block with the template
+/// `template_map` (keyed by normalized command) or else the command index gives for its echoed
+/// command, falling back to the command a bundle filed the capture under. Index `Hostname`
+/// filters see the bundle host, or else the hostname in the block's prompt. Records are keyed by
+/// template name, without extension (`template/table` for named tables), so abbreviated and full
+/// spellings of a command land in one table; bundle records also get `host` and `command`
+/// columns. Blocks without a route are reported as warnings and skipped.
+///
+/// Returns the record tables, their columns (in the order templates were first used) and the
+/// warning count.
+fn parse_auto_template(
+    platform: Option<&str>,
+    template_map: &BTreeMap<String, String>,
+    transcript_options: &transcript::TranscriptOptions,
+    captures: &[RoutedCapture],
+    parse_options: &cliscrape::ParseOptions,
+) -> anyhow::Result<(output::RecordTables, Vec<String>, usize)> {
+    let index = TemplateResolver::new()
+        .map_err(|e| anyhow::anyhow!("Failed to initialize template resolver: {}", e))?
+        .load_index()
+        .map_err(|e| anyhow::anyhow!(e))?;
+
+    // Templates load once, on first use; columns follow the order templates were first used.
+    let mut parsers: BTreeMap<String, FsmParser> = BTreeMap::new();
+    let mut columns: Vec<String> = Vec::new();
+    let mut tables = output::RecordTables::new();
+    let mut all_warnings = Vec::new();
+    for capture in captures {
+        let (blocks, transcript_warnings) = transcript::preprocess_transcript_with_warnings(
+            &capture.content,
+            &transcript_options.for_source(capture.path.as_deref()),
+        );
+        for w in transcript_warnings {
+            all_warnings.push(cliscrape::TemplateWarning {
+                kind: "transcript".to_string(),
+                message: w,
+                line_idx: None,
+            });
+        }
+
+        for (idx, block) in blocks.iter().enumerate() {
+            let command = block.command.as_deref().or(capture.command.as_deref());
+            let routed = command.and_then(|cmd| {
+                let key = normalize_command(cmd);
+                let template = match template_map.get(&key) {
+                    Some(template) => template,
+                    None => index
+                        .lookup(
+                            platform,
+                            cmd,
+                            capture.host.as_deref().or(block.hostname.as_deref()),
+                        )?
+                        .templates
+                        .first()?,
+                };
+                Some((key, template))
+            });
+            let Some((key, template)) = routed else {
+                all_warnings.push(cliscrape::TemplateWarning {
+                    kind: "auto_template_unrouted".to_string(),
+                    message: format!(
+                        "Block {} from {} ({}) matches no --template-map or template index entry - skipping",
+                        idx + 1,
+                        capture.label,
+                        command.unwrap_or("no command echo")
+                    ),
+                    line_idx: None,
+                });
+                continue;
+            };
+
+            if !parsers.contains_key(template) {
+                let resolved = resolve_template(template, CliTemplateFormat::Auto)?;
+                let (parser, loader_warnings) =
+                    load_parser(&resolved.path, CliTemplateFormat::Auto)?;
+                for warning in &loader_warnings {
+                    tracing::warn!(
+                        target: "cliscrape::cli",
+                        event = "template_loader_warning",
+                        template = %template,
+                        kind = %warning.kind,
+                        message = %warning.message
+                    );
+                }
+                for column in parser.column_order() {
+                    if !columns.contains(&column) {
+                        columns.push(column);
+                    }
+                }
+                parsers.insert(template.clone(), parser);
+            }
+            let parser = &parsers[template];
+
+            let mut options = parse_options.clone();
+            if let Some(host) = &capture.host {
+                // Explicit --set constants win over the bundle's host and command; template
+                // fields of the same name win over all of them.
+                options
+                    .constants
+                    .entry("host".to_string())
+                    .or_insert_with(|| host.clone());
+                options
+                    .constants
+                    .entry("command".to_string())
+                    .or_insert_with(|| key.clone());
+            }
+            let (parsed, warnings) =
+                transcript_lines(block, parser.tables_with_warnings(&block.text, options))
+                    .with_context(|| {
+                        format!(
+                            "Failed to parse block {} ({}) from {} with template '{}'",
+                            idx + 1,
+                            block.describe(),
+                            capture.label,
+                            template
+                        )
+                    })?;
+            let template_key = Path::new(template)
+                .file_stem()
+                .map_or_else(|| template.clone(), |s| s.to_string_lossy().into_owned());
+            for (table, mut rows) in parsed {
+                let name = if parser.has_named_tables() {
+                    format!("{}/{}", template_key, table)
+                } else {
+                    template_key.clone()
+                };
+                tables.entry(name).or_default().append(&mut rows);
+            }
+            all_warnings.extend(warnings);
+        }
+    }
+
+    for warning in &all_warnings {
+        let one_based_line = warning.line_idx.map(|idx| idx + 1);
+        tracing::warn!(
+            target: "cliscrape::cli",
+            event = "parse_warning",
+            kind = %warning.kind,
+            line_idx = ?one_based_line,
+            message = %warning.message
+        );
+    }
+
+    Ok((tables, columns, all_warnings.len()))
+}
+
 fn default_output_path(input: &Path, format: crate::cli::ConvertFormat) -> PathBuf {
     let mut out = input.to_path_buf();
     match format {
@@ -651,13 +2104,217 @@ fn default_output_path(input: &Path, format: crate::cli::ConvertFormat) -> PathB
     out
 }
 
+/// Load a template, honouring an explicit format override (which skips loader warnings).
+fn load_parser(
+    path: &Path,
+    template_format: CliTemplateFormat,
+) -> anyhow::Result<(FsmParser, Vec<cliscrape::TemplateWarning>)> {
+    let format = match template_format {
+        CliTemplateFormat::Auto => {
+            return FsmParser::from_file_with_warnings(path)
+                .with_context(|| format!("Failed to load template from {}", path.display()));
+        }
+        CliTemplateFormat::Textfsm => cliscrape::TemplateFormat::Textfsm,
+        CliTemplateFormat::Yaml => cliscrape::TemplateFormat::Yaml,
+        CliTemplateFormat::Toml => cliscrape::TemplateFormat::Toml,
+    };
+    let parser = FsmParser::from_file_with_format(path, format)
+        .with_context(|| format!("Failed to load template from {}", path.display()))?;
+    Ok((parser, Vec::new()))
+}
+
+/// OpenMetrics exposition of `records` (tagged with their input source), logging the samples
+/// it had to drop.
+fn openmetrics_text(
+    records: &[(&str, &BTreeMap<String, serde_json::Value>)],
+    parser: &FsmParser,
+    source_label: bool,
+) -> String {
+    let metrics = parser.metrics().expect("checked before parsing");
+    let mut warnings = Vec::new();
+    let text = output::serialize_openmetrics(records, metrics, source_label, &mut warnings);
+    for message in &warnings {
+        tracing::warn!(
+            target: "cliscrape::cli",
+            event = "parse_warning",
+            kind = "openmetrics_duplicate",
+            message = %message
+        );
+    }
+    text
+}
+
+/// `parse --format sqlite`: append each record table, with the source it came from, to `db`.
+#[cfg(feature = "sqlite")]
+fn export_sqlite(
+    parser: &FsmParser,
+    template_name: &str,
+    source_groups: &[(String, output::RecordTables)],
+    db: &Path,
+) -> anyhow::Result<()> {
+    let base = sqlite::table_name(template_name);
+    let tables: Vec<_> = parser
+        .table_names()
+        .into_iter()
+        .map(|table| sqlite::TableExport {
+            name: if parser.has_named_tables() {
+                format!("{}_{}", base, table)
+            } else {
+                base.clone()
+            },
+            fields: parser.table_columns(&table),
+            rows: source_groups
+                .iter()
+                .flat_map(|(source, tables)| {
+                    tables
+                        .get(&table)
+                        .into_iter()
+                        .flatten()
+                        .map(move |record| (source.as_str(), record))
+                })
+                .collect(),
+        })
+        .collect();
+    let parsed_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64);
+    sqlite::export(db, &tables, parsed_at)?;
+    Ok(())
+}
+
+/// Write rendered output to `path` (empty output leaves an empty file), or print it to stdout.
+fn write_output(output: &str, path: Option<&Path>) -> anyhow::Result<()> {
+    match path {
+        Some(path) => {
+            let contents = if output.is_empty() {
+                String::new()
+            } else {
+                format!("{}\n", output)
+            };
+            std::fs::write(path, contents)
+                .with_context(|| format!("Failed to write output file {}", path.display()))
+        }
+        None => {
+            println!("{}", output);
+            Ok(())
+        }
+    }
+}
+
+/// File name for one `--output-dir` file: expands `{stem}`, `{name}` and `{parent}` (the input
+/// file's stem, file name and directory name; `stdin` for stdin), `{index}` (one-based input
+/// position) and `{format}` (the format's file extension) in `pattern`.
+fn output_file_name(
+    pattern: &str,
+    source: &InputSource,
+    index: usize,
+    format: OutputFormat,
+) -> anyhow::Result<String> {
+    let part = |p: Option<&std::ffi::OsStr>| {
+        p.map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+    let (stem, name, parent) = match source {
+        InputSource::Stdin => (
+            "stdin".to_string(),
+            "stdin".to_string(),
+            "stdin".to_string(),
+        ),
+        InputSource::File(path) => (
+            part(path.file_stem()),
+            part(path.file_name()),
+            part(path.parent().and_then(Path::file_name)),
+        ),
+    };
+
+    let mut out = String::new();
+    let mut rest = pattern;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let close = rest[open..]
+            .find('}')
+            .map(|offset| open + offset)
+            .with_context(|| format!("Unclosed '{{' in --output-name '{}'", pattern))?;
+        match &rest[open + 1..close] {
+            "stem" => out.push_str(&stem),
+            "name" => out.push_str(&name),
+            "parent" => out.push_str(&parent),
+            "index" => out.push_str(&index.to_string()),
+            "format" => out.push_str(output::file_extension(format)),
+            other => anyhow::bail!(
+                "Unknown placeholder '{{{}}}' in --output-name (expected stem, name, parent, index or format)",
+                other
+            ),
+        }
+        rest = &rest[close + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Name, version, content hash and origin of a resolved template.
+fn template_identity(
+    path: &Path,
+    name: String,
+    source: &str,
+) -> anyhow::Result<output::TemplateIdentity> {
+    use sha2::{Digest, Sha256};
+
+    let content = std::fs::read(path)
+        .with_context(|| format!("Failed to read template {}", path.display()))?;
+    let meta = metadata::extract_metadata(
+        &String::from_utf8_lossy(&content),
+        format_from_extension(&name),
+    );
+    let sha256 = Sha256::digest(&content)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    Ok(output::TemplateIdentity {
+        name,
+        version: meta.version,
+        sha256,
+        source: source.to_string(),
+    })
+}
+
+/// A resolved template file and where it was found.
+struct ResolvedTemplate {
+    path: PathBuf,
+    /// File name, or the library name for embedded and user templates.
+    name: String,
+    /// `file`, `user` or `embedded`.
+    source: &'static str,
+}
+
+impl ResolvedTemplate {
+    fn file(path: PathBuf) -> Self {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string());
+        Self {
+            path,
+            name,
+            source: "file",
+        }
+    }
+}
+
 /// Resolve template spec: if it's a path, use it; otherwise search CWD for identifier
 fn resolve_template_spec(spec: &str, format_filter: CliTemplateFormat) -> anyhow::Result<PathBuf> {
+    resolve_template(spec, format_filter).map(|t| t.path)
+}
+
+fn resolve_template(
+    spec: &str,
+    format_filter: CliTemplateFormat,
+) -> anyhow::Result<ResolvedTemplate> {
     let spec_path = PathBuf::from(spec);
 
     // If spec points to an existing path, use it directly
     if spec_path.exists() {
-        return Ok(spec_path);
+        return Ok(ResolvedTemplate::file(spec_path));
     }
 
     // Otherwise treat as identifier and search CWD first
@@ -706,7 +2363,11 @@ fn resolve_template_spec(spec: &str, format_filter: CliTemplateFormat) -> anyhow
             if resolved.len() == 1 {
                 let (resolved_name, source) = resolved.pop().unwrap();
                 return match source {
-                    TemplateSource::UserFile(path) => Ok(path),
+                    TemplateSource::UserFile(path) => Ok(ResolvedTemplate {
+                        path,
+                        name: resolved_name,
+                        source: "user",
+                    }),
                     TemplateSource::Embedded(file) => {
                         // Write embedded template to temp file for FsmParser to load
                         let temp_dir = std::env::temp_dir();
@@ -718,13 +2379,21 @@ fn resolve_template_spec(spec: &str, format_filter: CliTemplateFormat) -> anyhow
                         ));
                         std::fs::write(&temp_path, file.data.as_ref())
                             .with_context(|| "Failed to write embedded template to temp file")?;
-                        Ok(temp_path)
+                        Ok(ResolvedTemplate {
+                            path: temp_path,
+                            name: resolved_name,
+                            source: "embedded",
+                        })
                     }
                 };
             }
 
             match resolver.resolve(spec) {
-                Ok(TemplateSource::UserFile(path)) => Ok(path),
+                Ok(TemplateSource::UserFile(path)) => Ok(ResolvedTemplate {
+                    path,
+                    name: spec.to_string(),
+                    source: "user",
+                }),
                 Ok(TemplateSource::Embedded(file)) => {
                     // Write embedded template to temp file for FsmParser to load
                     let temp_dir = std::env::temp_dir();
@@ -736,7 +2405,11 @@ fn resolve_template_spec(spec: &str, format_filter: CliTemplateFormat) -> anyhow
                     ));
                     std::fs::write(&temp_path, file.data.as_ref())
                         .with_context(|| "Failed to write embedded template to temp file")?;
-                    Ok(temp_path)
+                    Ok(ResolvedTemplate {
+                        path: temp_path,
+                        name: spec.to_string(),
+                        source: "embedded",
+                    })
                 }
                 Err(e) => anyhow::bail!(
                     "Template '{}' not found (tried {} and identifier search in CWD, embedded templates, and XDG directories): {}",
@@ -746,7 +2419,9 @@ fn resolve_template_spec(spec: &str, format_filter: CliTemplateFormat) -> anyhow
                 ),
             }
         }
-        1 => Ok(candidates.into_iter().next().unwrap()),
+        1 => Ok(ResolvedTemplate::file(
+            candidates.into_iter().next().unwrap(),
+        )),
         _ => {
             let names: Vec<_> = candidates.iter().map(|p| p.display().to_string()).collect();
             anyhow::bail!(
@@ -771,6 +2446,27 @@ impl InputSource {
             InputSource::File(p) => p.display().to_string(),
         }
     }
+
+    fn path(&self) -> Option<&Path> {
+        match self {
+            InputSource::Stdin => None,
+            InputSource::File(p) => Some(p),
+        }
+    }
+
+    fn read(&self) -> anyhow::Result<String> {
+        match self {
+            InputSource::Stdin => {
+                let mut buffer = String::new();
+                io::stdin()
+                    .read_to_string(&mut buffer)
+                    .context("Failed to read input from stdin")?;
+                Ok(buffer)
+            }
+            InputSource::File(path) => std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read input from {}", path.display())),
+        }
+    }
 }
 
 /// Resolve final input sources: combine positional inputs, --input, --input-glob, and stdin
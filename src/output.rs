@@ -1,46 +1,85 @@
-use crate::cli::OutputFormat;
-use anyhow::{Context, Result};
-use comfy_table::Table;
+use crate::cli::{OutputFormat, RenderArgs, TableLayout, TableOverflow};
+use anyhow::{Context, Result, bail};
+use cliscrape::TemplateWarning;
+use cliscrape::engine::{MetricKind, MetricsMapping};
+use comfy_table::{ContentArrangement, Row, Table};
 use csv::WriterBuilder;
-use serde_json::Value;
+use indexmap::IndexMap;
+use serde::Serialize;
+use serde_json::{Value, json};
 use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
 
-pub fn serialize(results: &[BTreeMap<String, Value>], format: OutputFormat) -> Result<String> {
+pub use cliscrape::RecordTables;
+
+/// Serialize records. JSON, YAML and NDJSON keys and CSV and table headers follow `columns`
+/// (typically the template's column order, or the `--fields` order); keys outside it, such as
+/// constants or joined columns, follow by name. `render` only affects CSV and table output.
+pub fn serialize(
+    results: &[BTreeMap<String, Value>],
+    columns: &[String],
+    format: OutputFormat,
+    render: &RenderArgs,
+) -> Result<String> {
     if results.is_empty() {
-        return match format {
-            OutputFormat::Json | OutputFormat::Auto => Ok("[]".to_string()),
-            OutputFormat::Csv | OutputFormat::Table => Ok(String::new()),
-        };
+        match format {
+            OutputFormat::Json | OutputFormat::JsonEnvelope | OutputFormat::Auto => {
+                return Ok("[]".to_string());
+            }
+            OutputFormat::Csv | OutputFormat::Table | OutputFormat::Ndjson => {
+                return Ok(String::new());
+            }
+            // These still emit a well-formed (empty) document.
+            OutputFormat::Yaml
+            | OutputFormat::Toml
+            | OutputFormat::Markdown
+            | OutputFormat::Html
+            | OutputFormat::Sqlite
+            | OutputFormat::OpenMetrics => {}
+        }
     }
 
     match format {
         OutputFormat::Auto => {
             // Auto mode will be handled by caller based on TTY detection
             // For now, default to JSON (caller should override)
-            serde_json::to_string_pretty(results).context("Failed to serialize to JSON")
+            serde_json::to_string_pretty(&ordered_records(results, columns))
+                .context("Failed to serialize to JSON")
         }
-        OutputFormat::Json => {
-            serde_json::to_string_pretty(results).context("Failed to serialize to JSON")
+        // The envelope itself is assembled by `serialize_envelope`.
+        OutputFormat::Json | OutputFormat::JsonEnvelope => {
+            serde_json::to_string_pretty(&ordered_records(results, columns))
+                .context("Failed to serialize to JSON")
         }
+        OutputFormat::Ndjson => {
+            let lines = results
+                .iter()
+                .map(|record| serde_json::to_string(&ordered(record, columns)))
+                .collect::<Result<Vec<_>, _>>()
+                .context("Failed to serialize to NDJSON")?;
+            Ok(lines.join("\n"))
+        }
+        OutputFormat::Yaml => serde_yaml_ng::to_string(&ordered_records(results, columns))
+            .map(|out| out.trim_end().to_string())
+            .context("Failed to serialize to YAML"),
+        OutputFormat::Toml => to_toml(&json!({ cliscrape::engine::DEFAULT_TABLE: results }))
+            .map(|out| out.trim_end().to_string()),
+        OutputFormat::Sqlite => bail!("SQLite output is written to a database, not serialized"),
+        OutputFormat::OpenMetrics => {
+            bail!("OpenMetrics output needs the template's metrics mapping")
+        }
+        OutputFormat::Markdown | OutputFormat::Html => Ok(report(
+            &[ReportBlock::Records(results)],
+            columns,
+            format,
+            render,
+        )),
         OutputFormat::Csv => {
             let mut wtr = WriterBuilder::new().from_writer(vec![]);
 
-            // Compute deterministic headers: union of all keys, sorted
-            let mut all_keys = BTreeSet::new();
-            for record in results {
-                for key in record.keys() {
-                    all_keys.insert(key.clone());
-                }
-            }
-            let headers: Vec<String> = all_keys.into_iter().collect();
+            let headers = headers(results, columns);
             wtr.write_record(&headers)?;
-
-            for record in results {
-                let mut row = Vec::new();
-                for header in &headers {
-                    let val = record.get(header).cloned().unwrap_or(Value::Null);
-                    row.push(json_value_to_string(&val));
-                }
+            for row in cell_rows(results, &headers, render) {
                 wtr.write_record(&row)?;
             }
 
@@ -49,23 +88,26 @@ pub fn serialize(results: &[BTreeMap<String, Value>], format: OutputFormat) -> R
             Ok(data)
         }
         OutputFormat::Table => {
-            let mut table = Table::new();
+            let headers = headers(results, columns);
+            let rows = cell_rows(results, &headers, render);
+            if render.table_layout == TableLayout::Vertical {
+                return Ok(vertical_table(&headers, &rows));
+            }
 
-            // Compute deterministic headers: union of all keys, sorted
-            let mut all_keys = BTreeSet::new();
-            for record in results {
-                for key in record.keys() {
-                    all_keys.insert(key.clone());
-                }
+            let mut table = Table::new();
+            // Dynamic arrangement fits the terminal width (or --table-width) by wrapping cells;
+            // without either, columns grow to fit their content.
+            table.set_content_arrangement(ContentArrangement::Dynamic);
+            if let Some(width) = render.table_width {
+                table.set_width(width);
             }
-            let headers: Vec<String> = all_keys.into_iter().collect();
+            table.set_truncation_indicator("…");
             table.set_header(&headers);
 
-            for record in results {
-                let mut row = Vec::new();
-                for header in &headers {
-                    let val = record.get(header).cloned().unwrap_or(Value::Null);
-                    row.push(json_value_to_string(&val));
+            for cells in rows {
+                let mut row = Row::from(cells);
+                if render.table_overflow == TableOverflow::Truncate {
+                    row.max_height(1);
                 }
                 table.add_row(row);
             }
@@ -75,19 +117,703 @@ pub fn serialize(results: &[BTreeMap<String, Value>], format: OutputFormat) -> R
     }
 }
 
-fn json_value_to_string(val: &Value) -> String {
-    match val {
+/// Serialize named record tables.
+///
+/// JSON and YAML render a single mapping keyed by table name and TOML renders one array of
+/// tables per name; NDJSON tags each line with its table; table format renders each table
+/// separately under a `# <name>` heading, and Markdown and HTML under a level-2 heading, in
+/// table-name order. CSV holds a single table; several tables are an error.
+pub fn serialize_tables(
+    tables: &RecordTables,
+    columns: &[String],
+    format: OutputFormat,
+    render: &RenderArgs,
+) -> Result<String> {
+    match format {
+        OutputFormat::Json | OutputFormat::JsonEnvelope | OutputFormat::Auto => {
+            serde_json::to_string_pretty(&ordered_tables(tables, columns))
+                .context("Failed to serialize to JSON")
+        }
+        OutputFormat::Ndjson => {
+            let mut lines = Vec::new();
+            for (name, rows) in tables {
+                for record in rows {
+                    lines.push(table_line(name, ordered(record, columns))?);
+                }
+            }
+            Ok(lines.join("\n"))
+        }
+        OutputFormat::Yaml => serde_yaml_ng::to_string(&ordered_tables(tables, columns))
+            .map(|out| out.trim_end().to_string())
+            .context("Failed to serialize to YAML"),
+        OutputFormat::Toml => {
+            let document = serde_json::to_value(tables).context("Failed to serialize to TOML")?;
+            to_toml(&document).map(|out| out.trim_end().to_string())
+        }
+        OutputFormat::Sqlite => bail!("SQLite output is written to a database, not serialized"),
+        OutputFormat::OpenMetrics => {
+            bail!("OpenMetrics output needs the template's metrics mapping")
+        }
+        OutputFormat::Markdown | OutputFormat::Html => {
+            let blocks: Vec<ReportBlock> = tables
+                .iter()
+                .flat_map(|(name, rows)| {
+                    [ReportBlock::Heading(2, name), ReportBlock::Records(rows)]
+                })
+                .collect();
+            Ok(report(&blocks, columns, format, render))
+        }
+        OutputFormat::Csv => match tables.iter().collect::<Vec<_>>().as_slice() {
+            [] => Ok(String::new()),
+            [(_, rows)] => serialize(rows, columns, format, render),
+            _ => bail!(
+                "CSV output holds one record set, but the result has {} tables ({}); use \
+                 --format json or yaml",
+                tables.len(),
+                tables.keys().cloned().collect::<Vec<_>>().join(", ")
+            ),
+        },
+        OutputFormat::Table => {
+            let mut sections = Vec::new();
+            for (name, rows) in tables {
+                let body = serialize(rows, columns, format, render)?;
+                sections.push(format!("# {}\n{}", name, body.trim_end()));
+            }
+            Ok(sections.join("\n\n"))
+        }
+    }
+}
+
+/// Render Markdown or HTML with one level-2 section per input source (and a level-3 section per
+/// record table for multi-table templates).
+pub fn serialize_source_groups(
+    groups: &[(String, RecordTables)],
+    named_tables: bool,
+    columns: &[String],
+    format: OutputFormat,
+    render: &RenderArgs,
+) -> Result<String> {
+    let mut blocks = Vec::new();
+    for (source, tables) in groups {
+        blocks.push(ReportBlock::Heading(2, source));
+        if named_tables {
+            for (name, rows) in tables {
+                blocks.push(ReportBlock::Heading(3, name));
+                blocks.push(ReportBlock::Records(rows));
+            }
+        } else {
+            let rows = tables
+                .get(cliscrape::engine::DEFAULT_TABLE)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            blocks.push(ReportBlock::Records(rows));
+        }
+    }
+    Ok(report(&blocks, columns, format, render))
+}
+
+/// JSON or YAML object keyed by source, each value a record array (or, for multi-table
+/// templates, an object keyed by table name). Keys keep input order; record keys follow
+/// `columns`.
+pub fn serialize_keyed(
+    groups: &[(String, RecordTables)],
+    named_tables: bool,
+    columns: &[String],
+    format: OutputFormat,
+) -> Result<String> {
+    let document: IndexMap<&str, RecordsDocument> = groups
+        .iter()
+        .map(|(source, tables)| {
+            (
+                source.as_str(),
+                records_document(tables, named_tables, columns),
+            )
+        })
+        .collect();
+    match format {
+        OutputFormat::Json | OutputFormat::Auto => {
+            serde_json::to_string_pretty(&document).context("Failed to serialize to JSON")
+        }
+        OutputFormat::Yaml => serde_yaml_ng::to_string(&document)
+            .map(|out| out.trim_end().to_string())
+            .context("Failed to serialize to YAML"),
+        _ => bail!("--group-by-source supports json and yaml output"),
+    }
+}
+
+/// OpenMetrics text exposition of the fields mapped by `metrics`: per mapped field a family
+/// with `# TYPE` (and `# HELP`) lines and one sample per record holding a numeric value,
+/// labelled by the mapping's label columns; the exposition ends with `# EOF`.
+///
+/// Each record comes with the input source it was parsed from; with `source_label` samples also
+/// get a `source` label (unless the mapping labels a column of that name) so captures of
+/// different devices stay distinct series.
+///
+/// Records with a missing or non-numeric value (or a negative counter) are skipped, as are
+/// records repeating a label set the family already exposed; the latter are reported in
+/// `warnings`.
+pub fn serialize_openmetrics(
+    records: &[(&str, &BTreeMap<String, Value>)],
+    metrics: &MetricsMapping,
+    source_label: bool,
+    warnings: &mut Vec<String>,
+) -> String {
+    let source_label = source_label && !metrics.labels.iter().any(|label| label == "source");
+    let mut out = String::new();
+    for metric in &metrics.metrics {
+        out.push_str(&format!(
+            "# TYPE {} {}\n",
+            metric.name,
+            metric.kind.as_str()
+        ));
+        if let Some(help) = &metric.help {
+            out.push_str(&format!(
+                "# HELP {} {}\n",
+                metric.name,
+                escape_openmetrics(help)
+            ));
+        }
+        let sample = match metric.kind {
+            MetricKind::Gauge => metric.name.clone(),
+            MetricKind::Counter => format!("{}_total", metric.name),
+        };
+
+        let mut seen = BTreeSet::new();
+        let mut duplicates = Vec::new();
+        for (source, record) in records {
+            let Some((value, text)) = record.get(&metric.field).and_then(metric_value) else {
+                continue;
+            };
+            if metric.kind == MetricKind::Counter && value < 0.0 {
+                continue;
+            }
+            let mut labels: Vec<String> = metrics
+                .labels
+                .iter()
+                .filter_map(|label| {
+                    let text = label_text(record.get(label)?)?;
+                    Some(format!("{}=\"{}\"", label, escape_openmetrics(&text)))
+                })
+                .collect();
+            if source_label {
+                labels.push(format!("source=\"{}\"", escape_openmetrics(source)));
+            }
+            let labels = if labels.is_empty() {
+                String::new()
+            } else {
+                format!("{{{}}}", labels.join(","))
+            };
+            if seen.insert(labels.clone()) {
+                out.push_str(&format!("{}{} {}\n", sample, labels, text));
+            } else {
+                duplicates.push(labels);
+            }
+        }
+        if let Some(first) = duplicates.first() {
+            warnings.push(format!(
+                "Dropped {} {} sample(s) repeating an exposed label set (first: {}{}); map more \
+                 label columns to tell them apart",
+                duplicates.len(),
+                sample,
+                sample,
+                first
+            ));
+        }
+    }
+    out.push_str("# EOF");
+    out
+}
+
+/// Numeric sample value and its text: JSON numbers, or strings (untyped fields) that parse as a
+/// finite float. The text is kept as captured so large integer counters stay exact.
+fn metric_value(value: &Value) -> Option<(f64, String)> {
+    let text = match value {
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.trim().to_string(),
+        _ => return None,
+    };
+    let number: f64 = text.parse().ok()?;
+    number.is_finite().then_some((number, text))
+}
+
+/// Label value text; null and empty values leave the label off (OpenMetrics treats them alike).
+fn label_text(value: &Value) -> Option<String> {
+    let text = match value {
+        Value::Null => return None,
         Value::String(s) => s.clone(),
-        Value::Array(arr) => {
-            // Join list values with newline for better table/csv representation
-            arr.iter()
-                .map(|v| json_value_to_string(v))
-                .collect::<Vec<String>>()
-                .join(
-                    "
-",
+        Value::Array(items) => items
+            .iter()
+            .map(|item| match item {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(","),
+        other => other.to_string(),
+    };
+    (!text.is_empty()).then_some(text)
+}
+
+/// Escape a label value or HELP text: backslash, double quote and newline.
+fn escape_openmetrics(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// File extension for output written in `format`.
+pub fn file_extension(format: OutputFormat) -> &'static str {
+    match format {
+        OutputFormat::Auto | OutputFormat::Json | OutputFormat::JsonEnvelope => "json",
+        OutputFormat::Ndjson => "ndjson",
+        OutputFormat::Csv => "csv",
+        OutputFormat::Table => "txt",
+        OutputFormat::Yaml => "yaml",
+        OutputFormat::Toml => "toml",
+        OutputFormat::Markdown => "md",
+        OutputFormat::Html => "html",
+        OutputFormat::Sqlite => "sqlite",
+        // The node-exporter textfile collector reads `*.prom`.
+        OutputFormat::OpenMetrics => "prom",
+    }
+}
+
+enum ReportBlock<'a> {
+    Heading(usize, &'a str),
+    Records(&'a [BTreeMap<String, Value>]),
+}
+
+/// Markdown (GitHub-flavoured) or standalone HTML report.
+fn report(
+    blocks: &[ReportBlock],
+    columns: &[String],
+    format: OutputFormat,
+    render: &RenderArgs,
+) -> String {
+    let html = format == OutputFormat::Html;
+    let parts: Vec<String> = blocks
+        .iter()
+        .map(|block| match block {
+            ReportBlock::Heading(level, text) if html => {
+                format!("<h{level}>{}</h{level}>", escape_html(text))
+            }
+            ReportBlock::Heading(level, text) => format!("{} {}", "#".repeat(*level), text),
+            ReportBlock::Records([]) if html => "<p>No records.</p>".to_string(),
+            ReportBlock::Records([]) => "_No records._".to_string(),
+            ReportBlock::Records(records) => {
+                let headers = headers(records, columns);
+                let rows = cell_rows(records, &headers, render);
+                if html {
+                    html_table(&headers, &rows)
+                } else {
+                    markdown_table(&headers, &rows)
+                }
+            }
+        })
+        .collect();
+
+    if html {
+        html_document(&parts.join("\n"))
+    } else {
+        parts.join("\n\n")
+    }
+}
+
+fn markdown_table(headers: &[String], rows: &[Vec<String>]) -> String {
+    let line = |cells: &[String]| {
+        let cells: Vec<String> = cells
+            .iter()
+            .map(|c| c.replace('|', "\\|").replace('\n', "<br>"))
+            .collect();
+        format!("| {} |", cells.join(" | "))
+    };
+    let mut lines = vec![line(headers), line(&vec!["---".to_string(); headers.len()])];
+    lines.extend(rows.iter().map(|row| line(row)));
+    lines.join("\n")
+}
+
+fn html_table(headers: &[String], rows: &[Vec<String>]) -> String {
+    let cells = |tag: &str, cells: &[String]| {
+        cells
+            .iter()
+            .map(|c| format!("<{tag}>{}</{tag}>", escape_html(c).replace('\n', "<br>")))
+            .collect::<String>()
+    };
+    let mut lines = vec![
+        "<table>".to_string(),
+        format!("<thead><tr>{}</tr></thead>", cells("th", headers)),
+        "<tbody>".to_string(),
+    ];
+    lines.extend(
+        rows.iter()
+            .map(|row| format!("<tr>{}</tr>", cells("td", row))),
+    );
+    lines.push("</tbody>".to_string());
+    lines.push("</table>".to_string());
+    lines.join("\n")
+}
+
+fn html_document(body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>cliscrape report</title>\n\
+         <style>table {{ border-collapse: collapse; }} th, td {{ border: 1px solid #999; \
+         padding: 2px 6px; text-align: left; vertical-align: top; }}</style>\n\
+         </head>\n<body>\n{}\n</body>\n</html>",
+        body
+    )
+}
+
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Writes NDJSON lines as records are produced instead of buffering the whole result set.
+pub struct NdjsonWriter<W: Write> {
+    out: W,
+    tag_tables: bool,
+    columns: Vec<String>,
+    records: usize,
+}
+
+impl<W: Write> NdjsonWriter<W> {
+    /// `tag_tables` wraps each record as `{"table": ..., "record": ...}` for multi-table templates;
+    /// record keys follow `columns`.
+    pub fn new(out: W, tag_tables: bool, columns: Vec<String>) -> Self {
+        Self {
+            out,
+            tag_tables,
+            columns,
+            records: 0,
+        }
+    }
+
+    pub fn write_tables(&mut self, tables: &RecordTables) -> Result<()> {
+        for (name, rows) in tables {
+            for record in rows {
+                let record = ordered(record, &self.columns);
+                let line = if self.tag_tables {
+                    table_line(name, record)?
+                } else {
+                    serde_json::to_string(&record).context("Failed to serialize to NDJSON")?
+                };
+                writeln!(self.out, "{}", line).context("Failed to write NDJSON output")?;
+                self.records += 1;
+            }
+        }
+        self.out.flush().context("Failed to write NDJSON output")
+    }
+
+    pub fn write_warning(&mut self, source: &str, warning: &TemplateWarning) -> Result<()> {
+        writeln!(self.out, "{}", warning_line(source, warning)?)
+            .context("Failed to write NDJSON output")
+    }
+
+    /// Number of record lines written so far.
+    pub fn records(&self) -> usize {
+        self.records
+    }
+}
+
+/// Serialize a JSON document as TOML. TOML has no null, so null fields and list items are
+/// dropped instead of failing the whole document.
+pub fn to_toml(document: &Value) -> Result<String> {
+    let document = strip_nulls(document).unwrap_or(Value::Null);
+    toml::to_string(&document).context("Failed to serialize to TOML")
+}
+
+fn strip_nulls(value: &Value) -> Option<Value> {
+    match value {
+        Value::Null => None,
+        Value::Array(items) => Some(Value::Array(items.iter().filter_map(strip_nulls).collect())),
+        Value::Object(fields) => Some(Value::Object(
+            fields
+                .iter()
+                .filter_map(|(k, v)| Some((k.clone(), strip_nulls(v)?)))
+                .collect(),
+        )),
+        other => Some(other.clone()),
+    }
+}
+
+fn table_line(name: &str, record: OrderedRecord) -> Result<String> {
+    #[derive(Serialize)]
+    struct TableLine<'a> {
+        table: &'a str,
+        record: OrderedRecord<'a>,
+    }
+
+    serde_json::to_string(&TableLine {
+        table: name,
+        record,
+    })
+    .context("Failed to serialize to NDJSON")
+}
+
+/// Tagged NDJSON line for a warning.
+pub fn warning_line(source: &str, warning: &TemplateWarning) -> Result<String> {
+    serde_json::to_string(&json!({ "warning": warning_json(source, warning) }))
+        .context("Failed to serialize warning to NDJSON")
+}
+
+/// Warning as a JSON object; `line` is one-based.
+fn warning_json(source: &str, warning: &TemplateWarning) -> Value {
+    json!({
+        "source": source,
+        "kind": warning.kind,
+        "line": warning.line_idx.map(|idx| idx + 1),
+        "message": warning.message,
+    })
+}
+
+/// Template a parse ran with, as reported in the JSON envelope.
+#[derive(Debug, Serialize)]
+pub struct TemplateIdentity {
+    pub name: String,
+    pub version: String,
+    /// Hex SHA-256 of the template file contents.
+    pub sha256: String,
+    /// `file`, `user` or `embedded`.
+    pub source: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SourceStats {
+    pub name: String,
+    pub lines: usize,
+    pub records: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EnvelopeStats {
+    pub lines: usize,
+    pub records: usize,
+    pub elapsed_ms: u64,
+    /// Aggregate field coverage percentage; null when no records were produced.
+    pub coverage: Option<f64>,
+}
+
+/// Single JSON document carrying records together with the diagnostics of the run.
+///
+/// `records` is an array, or an object keyed by table name for multi-table templates; record
+/// keys follow `columns`.
+pub fn serialize_envelope(
+    template: &TemplateIdentity,
+    sources: &[SourceStats],
+    tables: &RecordTables,
+    named_tables: bool,
+    columns: &[String],
+    warnings: &[(String, TemplateWarning)],
+    stats: &EnvelopeStats,
+) -> Result<String> {
+    #[derive(Serialize)]
+    struct Envelope<'a> {
+        ok: bool,
+        template: &'a TemplateIdentity,
+        sources: &'a [SourceStats],
+        records: RecordsDocument<'a>,
+        warnings: Vec<Value>,
+        stats: &'a EnvelopeStats,
+    }
+
+    let envelope = Envelope {
+        ok: true,
+        template,
+        sources,
+        records: records_document(tables, named_tables, columns),
+        warnings: warnings
+            .iter()
+            .map(|(source, warning)| warning_json(source, warning))
+            .collect(),
+        stats,
+    };
+    serde_json::to_string_pretty(&envelope).context("Failed to serialize JSON envelope")
+}
+
+/// JSON envelope for a run that failed (e.g. a strict-mode or threshold error), carrying the
+/// error with the warnings and stats gathered up to the failure.
+pub fn serialize_failed_envelope(
+    error: &str,
+    warnings: &[(String, TemplateWarning)],
+    stats: &EnvelopeStats,
+) -> Result<String> {
+    let envelope = json!({
+        "ok": false,
+        "error": error,
+        "warnings": warnings
+            .iter()
+            .map(|(source, warning)| warning_json(source, warning))
+            .collect::<Vec<_>>(),
+        "stats": stats,
+    });
+    serde_json::to_string_pretty(&envelope).context("Failed to serialize JSON envelope")
+}
+
+/// A record with its keys in output order (see `ordered`).
+type OrderedRecord<'a> = IndexMap<&'a str, &'a Value>;
+
+/// `record` with the `columns` it holds first, in order, then every other key by name: the
+/// order of the CSV and table headers.
+fn ordered<'a>(record: &'a BTreeMap<String, Value>, columns: &[String]) -> OrderedRecord<'a> {
+    let mut out: OrderedRecord = columns
+        .iter()
+        .filter_map(|column| record.get_key_value(column))
+        .map(|(key, value)| (key.as_str(), value))
+        .collect();
+    for (key, value) in record {
+        out.entry(key.as_str()).or_insert(value);
+    }
+    out
+}
+
+fn ordered_records<'a>(
+    results: &'a [BTreeMap<String, Value>],
+    columns: &[String],
+) -> Vec<OrderedRecord<'a>> {
+    results
+        .iter()
+        .map(|record| ordered(record, columns))
+        .collect()
+}
+
+fn ordered_tables<'a>(
+    tables: &'a RecordTables,
+    columns: &[String],
+) -> BTreeMap<&'a str, Vec<OrderedRecord<'a>>> {
+    tables
+        .iter()
+        .map(|(name, rows)| (name.as_str(), ordered_records(rows, columns)))
+        .collect()
+}
+
+/// A run's records in a JSON or YAML document: the record array, or an object keyed by table
+/// name for multi-table templates.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum RecordsDocument<'a> {
+    Records(Vec<OrderedRecord<'a>>),
+    Tables(BTreeMap<&'a str, Vec<OrderedRecord<'a>>>),
+}
+
+fn records_document<'a>(
+    tables: &'a RecordTables,
+    named_tables: bool,
+    columns: &[String],
+) -> RecordsDocument<'a> {
+    if named_tables {
+        return RecordsDocument::Tables(ordered_tables(tables, columns));
+    }
+    let rows = tables
+        .get(cliscrape::engine::DEFAULT_TABLE)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    RecordsDocument::Records(ordered_records(rows, columns))
+}
+
+/// Deterministic header row: the `columns` present in any record, in order, then every other
+/// key by name.
+fn headers(results: &[BTreeMap<String, Value>], columns: &[String]) -> Vec<String> {
+    let mut rest = BTreeSet::new();
+    for record in results {
+        rest.extend(record.keys().filter(|k| !columns.contains(k)));
+    }
+    columns
+        .iter()
+        .filter(|c| results.iter().any(|r| r.contains_key(*c)))
+        .chain(rest)
+        .cloned()
+        .collect()
+}
+
+/// Cell text for each record, in `headers` order. With `explode_lists`, a record with list
+/// values yields one row per list position (lists are zipped, shorter ones padded with the
+/// null text) and scalar values repeat on every row.
+fn cell_rows(
+    results: &[BTreeMap<String, Value>],
+    headers: &[String],
+    render: &RenderArgs,
+) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    for record in results {
+        if !render.explode_lists {
+            rows.push(
+                headers
+                    .iter()
+                    .map(|h| cell_text(record.get(h), render))
+                    .collect(),
+            );
+            continue;
+        }
+
+        let height = headers
+            .iter()
+            .filter_map(|h| record.get(h)?.as_array().map(Vec::len))
+            .max()
+            .unwrap_or(1)
+            .max(1);
+        for idx in 0..height {
+            rows.push(
+                headers
+                    .iter()
+                    .map(|h| match record.get(h) {
+                        Some(Value::Array(items)) => cell_text(items.get(idx), render),
+                        other => cell_text(other, render),
+                    })
+                    .collect(),
+            );
+        }
+    }
+    rows
+}
+
+fn cell_text(val: Option<&Value>, render: &RenderArgs) -> String {
+    match val {
+        None | Some(Value::Null) => render.null_text.clone(),
+        Some(Value::String(s)) if s.is_empty() => render.null_text.clone(),
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Array(items)) if items.is_empty() => render.null_text.clone(),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|v| cell_text(Some(v), render))
+            .collect::<Vec<String>>()
+            .join(render.list_delimiter.as_deref().unwrap_or("\n")),
+        Some(other) => other.to_string(),
+    }
+}
+
+/// One `-[ RECORD n ]-` block per row with aligned `FIELD | VALUE` lines; multi-line values
+/// continue under the value column.
+fn vertical_table(headers: &[String], rows: &[Vec<String>]) -> String {
+    let key_width = headers.iter().map(|h| h.chars().count()).max().unwrap_or(0);
+    let mut blocks = Vec::new();
+    for (idx, row) in rows.iter().enumerate() {
+        let mut lines = vec![format!("-[ RECORD {} ]-", idx + 1)];
+        for (header, value) in headers.iter().zip(row) {
+            let mut value_lines = value.lines();
+            lines.push(
+                format!(
+                    "{:<key_width$} | {}",
+                    header,
+                    value_lines.next().unwrap_or("")
                 )
+                .trim_end()
+                .to_string(),
+            );
+            for more in value_lines {
+                lines.push(format!("{:<key_width$} | {}", "", more));
+            }
         }
-        _ => val.to_string(),
+        blocks.push(lines.join("\n"));
     }
+    blocks.join("\n")
 }
@@ -0,0 +1,347 @@
+//! Multi-template pipelines: route transcript blocks to templates by their echoed command,
+//! then join the per-command result sets into one record set.
+//!
+//! ```yaml
+//! version: 1
+//! sources:
+//!   interfaces: { command: show interfaces, template: cisco_ios_show_interfaces }
+//!   brief: { command: show ip interface brief, template: ip_brief.textfsm }
+//! base: interfaces
+//! joins:
+//!   - source: brief
+//!     on: [interface]
+//!     right_on: [intf]
+//!     type: left
+//!     prefix: brief_
+//! ```
+
+use anyhow::{Context, Result, bail};
+use cliscrape::TemplateWarning;
+use cliscrape::template::index;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet};
+
+pub type Record = BTreeMap<String, Value>;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineSpec {
+    pub version: u32,
+    pub sources: BTreeMap<String, SourceDef>,
+    /// Source whose records drive the joins.
+    pub base: String,
+    #[serde(default)]
+    pub joins: Vec<JoinDef>,
+    /// Source commands compiled to template index `Command` patterns, in source order.
+    #[serde(skip)]
+    routes: Vec<(String, Regex)>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SourceDef {
+    /// Command whose output feeds this source; echoed commands may abbreviate each word, as in
+    /// the template index (`sh ip int br` for `show ip interface brief`).
+    pub command: String,
+    /// Template spec (path or identifier); relative paths resolve against the spec file first.
+    pub template: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JoinDef {
+    pub source: String,
+    /// Key columns on the accumulated (left) records.
+    pub on: Vec<String>,
+    /// Key columns on the joined source, when named differently from `on`.
+    #[serde(default)]
+    pub right_on: Vec<String>,
+    #[serde(default, rename = "type")]
+    pub kind: JoinKind,
+    /// Prefix applied to the joined source's non-key columns.
+    #[serde(default)]
+    pub prefix: String,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JoinKind {
+    /// Keep every left record; unmatched records get empty joined columns.
+    #[default]
+    Left,
+    /// Keep only left records with at least one match.
+    Inner,
+}
+
+impl PipelineSpec {
+    pub fn from_yaml_str(input: &str) -> Result<Self> {
+        let de = serde_yaml_ng::Deserializer::from_str(input);
+        let mut spec: PipelineSpec =
+            serde_path_to_error::deserialize(de).context("Invalid pipeline spec")?;
+        spec.validate()?;
+        spec.routes = spec
+            .sources
+            .iter()
+            .map(|(name, def)| {
+                let pattern = index::abbreviable_command(&def.command);
+                let re = index::command_regex(&pattern).with_context(|| {
+                    format!("Invalid command '{}' for source '{}'", def.command, name)
+                })?;
+                Ok((name.clone(), re))
+            })
+            .collect::<Result<_>>()?;
+        Ok(spec)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.version != 1 {
+            bail!(
+                "Unsupported pipeline spec version {} (supported: 1)",
+                self.version
+            );
+        }
+        if !self.sources.contains_key(&self.base) {
+            bail!("Pipeline base '{}' is not a defined source", self.base);
+        }
+        for (idx, join) in self.joins.iter().enumerate() {
+            if !self.sources.contains_key(&join.source) {
+                bail!(
+                    "joins[{}] references undefined source '{}'",
+                    idx,
+                    join.source
+                );
+            }
+            if join.source == self.base {
+                bail!("joins[{}] cannot join the base source onto itself", idx);
+            }
+            if join.on.is_empty() {
+                bail!("joins[{}] must name at least one key column in 'on'", idx);
+            }
+            if !join.right_on.is_empty() && join.right_on.len() != join.on.len() {
+                bail!(
+                    "joins[{}] 'right_on' must list as many columns as 'on' ({})",
+                    idx,
+                    join.on.len()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Source that should parse a block echoed with `command`, if any.
+    pub fn route(&self, command: &str) -> Option<&str> {
+        let command = index::normalize_command(command);
+        self.routes
+            .iter()
+            .find(|(_, re)| re.is_match(&command))
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Join the per-source result sets into one record set, starting from the base source.
+    pub fn combine(
+        &self,
+        mut results: BTreeMap<String, Vec<Record>>,
+        warnings: &mut Vec<TemplateWarning>,
+    ) -> Vec<Record> {
+        let mut combined = results.remove(&self.base).unwrap_or_default();
+        for join in &self.joins {
+            let right = results.get(&join.source).map(Vec::as_slice).unwrap_or(&[]);
+            combined = join_records(combined, right, join, warnings);
+        }
+        combined
+    }
+}
+
+fn join_key(record: &Record, columns: &[String]) -> Option<Vec<String>> {
+    columns
+        .iter()
+        .map(|c| match record.get(c)? {
+            Value::String(s) => Some(s.clone()),
+            other => Some(other.to_string()),
+        })
+        .collect()
+}
+
+fn join_records(
+    left: Vec<Record>,
+    right: &[Record],
+    join: &JoinDef,
+    warnings: &mut Vec<TemplateWarning>,
+) -> Vec<Record> {
+    let right_on = if join.right_on.is_empty() {
+        &join.on
+    } else {
+        &join.right_on
+    };
+
+    let mut index: BTreeMap<Vec<String>, Vec<&Record>> = BTreeMap::new();
+    let mut right_columns = BTreeSet::new();
+    for record in right {
+        right_columns.extend(record.keys().filter(|k| !right_on.contains(k)).cloned());
+        if let Some(key) = join_key(record, right_on) {
+            index.entry(key).or_default().push(record);
+        }
+    }
+
+    let mut collisions = BTreeSet::new();
+    let mut joined = Vec::new();
+    for record in left {
+        let matches = join_key(&record, &join.on)
+            .and_then(|key| index.get(&key))
+            .map(Vec::as_slice)
+            .unwrap_or(&[]);
+
+        if matches.is_empty() {
+            if join.kind == JoinKind::Left {
+                let mut row = record;
+                for column in &right_columns {
+                    let name = format!("{}{}", join.prefix, column);
+                    insert_joined(
+                        &mut row,
+                        name,
+                        Value::String(String::new()),
+                        &mut collisions,
+                    );
+                }
+                joined.push(row);
+            }
+            continue;
+        }
+
+        for matched in matches {
+            let mut row = record.clone();
+            for (column, value) in matched.iter() {
+                if right_on.contains(column) {
+                    continue;
+                }
+                let name = format!("{}{}", join.prefix, column);
+                insert_joined(&mut row, name, value.clone(), &mut collisions);
+            }
+            joined.push(row);
+        }
+    }
+
+    if !collisions.is_empty() {
+        warnings.push(TemplateWarning {
+            kind: "pipeline_column_collision".to_string(),
+            message: format!(
+                "Join with '{}' kept existing values for columns {} (set 'prefix' to keep both)",
+                join.source,
+                collisions.into_iter().collect::<Vec<_>>().join(", ")
+            ),
+            line_idx: None,
+        });
+    }
+
+    joined
+}
+
+/// Add a joined column, keeping any existing value and recording the collision.
+fn insert_joined(row: &mut Record, name: String, value: Value, collisions: &mut BTreeSet<String>) {
+    match row.entry(name) {
+        Entry::Occupied(existing) => {
+            collisions.insert(existing.key().clone());
+        }
+        Entry::Vacant(slot) => {
+            slot.insert(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(pairs: &[(&str, &str)]) -> Record {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), Value::String(v.to_string())))
+            .collect()
+    }
+
+    fn spec(joins: &str) -> PipelineSpec {
+        PipelineSpec::from_yaml_str(&format!(
+            r#"
+version: 1
+sources:
+  interfaces: {{ command: show interfaces, template: a.textfsm }}
+  brief: {{ command: show ip interface brief, template: b.textfsm }}
+base: interfaces
+joins:
+{joins}
+"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn routes_abbreviated_commands() {
+        let spec = spec("  - { source: brief, on: [interface] }");
+        assert_eq!(spec.route("sh ip int br"), Some("brief"));
+        assert_eq!(spec.route("sh  ip  int  br"), Some("brief"));
+        assert_eq!(spec.route("show interfaces"), Some("interfaces"));
+        assert_eq!(spec.route("show interfaces description"), None);
+    }
+
+    #[test]
+    fn left_join_keeps_unmatched_rows_with_prefixed_columns() {
+        let spec = spec("  - { source: brief, on: [interface], right_on: [intf], prefix: ip_ }");
+        let mut results = BTreeMap::new();
+        results.insert(
+            "interfaces".to_string(),
+            vec![
+                record(&[("interface", "Gi0/1"), ("mtu", "1500")]),
+                record(&[("interface", "Gi0/2"), ("mtu", "9000")]),
+            ],
+        );
+        results.insert(
+            "brief".to_string(),
+            vec![record(&[("intf", "Gi0/1"), ("address", "10.0.0.1")])],
+        );
+
+        let mut warnings = Vec::new();
+        let combined = spec.combine(results, &mut warnings);
+
+        assert_eq!(combined.len(), 2);
+        assert_eq!(combined[0]["ip_address"], "10.0.0.1");
+        assert_eq!(combined[1]["ip_address"], "");
+        assert!(!combined[0].contains_key("ip_intf"));
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn inner_join_drops_unmatched_rows_and_reports_collisions() {
+        let spec = spec("  - { source: brief, on: [interface], type: inner }");
+        let mut results = BTreeMap::new();
+        results.insert(
+            "interfaces".to_string(),
+            vec![
+                record(&[("interface", "Gi0/1"), ("status", "up")]),
+                record(&[("interface", "Gi0/2"), ("status", "down")]),
+            ],
+        );
+        results.insert(
+            "brief".to_string(),
+            vec![record(&[("interface", "Gi0/1"), ("status", "admin")])],
+        );
+
+        let mut warnings = Vec::new();
+        let combined = spec.combine(results, &mut warnings);
+
+        assert_eq!(combined.len(), 1);
+        assert_eq!(combined[0]["status"], "up");
+        assert_eq!(warnings[0].kind, "pipeline_column_collision");
+    }
+
+    #[test]
+    fn rejects_joins_on_undefined_sources() {
+        let err = PipelineSpec::from_yaml_str(
+            "version: 1\nsources:\n  a: { command: show x, template: x }\nbase: a\njoins:\n  - { source: b, on: [k] }\n",
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("undefined source 'b'"), "{err}");
+    }
+}
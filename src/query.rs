@@ -0,0 +1,702 @@
+//! Post-parse query layer applied between parsing and serialization.
+//!
+//! Stages run in a fixed order: `--query` path selection, `--where` filters, `--sort`,
+//! `--limit`, then `--fields` projection, so filters and sort keys always see the
+//! template's own column names.
+
+use regex::Regex;
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+pub type Record = BTreeMap<String, Value>;
+
+/// Query options collected from the command line.
+#[derive(Debug, Clone, Default)]
+pub struct ResultQuery {
+    pub path: Option<PathQuery>,
+    pub filters: Vec<Expr>,
+    pub sort: Vec<SortKey>,
+    pub limit: Option<usize>,
+    pub fields: Vec<FieldSpec>,
+}
+
+impl ResultQuery {
+    pub fn is_empty(&self) -> bool {
+        self.path.is_none()
+            && self.filters.is_empty()
+            && self.sort.is_empty()
+            && self.limit.is_none()
+            && self.fields.is_empty()
+    }
+
+    /// Whether the query can run block by block: filters and projection only.
+    pub fn is_streamable(&self) -> bool {
+        self.path.is_none() && self.sort.is_empty() && self.limit.is_none()
+    }
+
+    /// Output column order: the `--fields` order when projecting, else `template_columns`.
+    pub fn columns(&self, template_columns: Vec<String>) -> Vec<String> {
+        if self.fields.is_empty() {
+            return template_columns;
+        }
+        self.fields
+            .iter()
+            .map(|f| f.output_name().to_string())
+            .collect()
+    }
+
+    pub fn apply(&self, records: Vec<Record>) -> Vec<Record> {
+        let mut records = match &self.path {
+            Some(path) => path.select(records),
+            None => records,
+        };
+
+        records.retain(|r| self.filters.iter().all(|f| f.eval(r)));
+
+        if !self.sort.is_empty() {
+            records.sort_by(|a, b| {
+                self.sort
+                    .iter()
+                    .map(|key| key.compare(a, b))
+                    .find(|o| o.is_ne())
+                    .unwrap_or(Ordering::Equal)
+            });
+        }
+
+        if let Some(limit) = self.limit {
+            records.truncate(limit);
+        }
+
+        if self.fields.is_empty() {
+            return records;
+        }
+        records
+            .into_iter()
+            .map(|r| {
+                self.fields
+                    .iter()
+                    .map(|f| {
+                        let value = r.get(&f.source).cloned().unwrap_or(Value::Null);
+                        (f.output_name().to_string(), value)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// `--fields` entry: `NAME` or `NAME:ALIAS`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldSpec {
+    pub source: String,
+    pub alias: Option<String>,
+}
+
+impl FieldSpec {
+    fn output_name(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.source)
+    }
+}
+
+/// Parse one `--fields` entry.
+pub fn parse_field(s: &str) -> Result<FieldSpec, String> {
+    let f = s.trim();
+    match f.split_once(':') {
+        Some((source, alias)) if !source.trim().is_empty() && !alias.trim().is_empty() => {
+            Ok(FieldSpec {
+                source: source.trim().to_string(),
+                alias: Some(alias.trim().to_string()),
+            })
+        }
+        None if !f.is_empty() => Ok(FieldSpec {
+            source: f.to_string(),
+            alias: None,
+        }),
+        _ => Err(format!("expected NAME or NAME:ALIAS, got '{f}'")),
+    }
+}
+
+/// `--sort` key: `NAME`, `NAME:asc` or `NAME:desc`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SortKey {
+    pub field: String,
+    pub descending: bool,
+}
+
+impl SortKey {
+    fn compare(&self, a: &Record, b: &Record) -> Ordering {
+        let ord = match (a.get(&self.field), b.get(&self.field)) {
+            (Some(x), Some(y)) => compare_values(x, y),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        };
+        if self.descending { ord.reverse() } else { ord }
+    }
+}
+
+/// Parse one `--sort` key.
+pub fn parse_sort_key(s: &str) -> Result<SortKey, String> {
+    let k = s.trim();
+    let (field, dir) = k.split_once(':').unwrap_or((k, "asc"));
+    if field.trim().is_empty() {
+        return Err(format!("expected NAME[:asc|desc], got '{k}'"));
+    }
+    let descending = match dir.trim().to_lowercase().as_str() {
+        "asc" => false,
+        "desc" => true,
+        other => {
+            return Err(format!(
+                "unknown sort direction '{other}' (use asc or desc)"
+            ));
+        }
+    };
+    Ok(SortKey {
+        field: field.trim().to_string(),
+        descending,
+    })
+}
+
+/// Numbers compare numerically, everything else by display string; nulls sort last.
+fn compare_values(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Null, Value::Null) => Ordering::Equal,
+        (Value::Null, _) => Ordering::Greater,
+        (_, Value::Null) => Ordering::Less,
+        (Value::Number(x), Value::Number(y)) => x
+            .as_f64()
+            .partial_cmp(&y.as_f64())
+            .unwrap_or(Ordering::Equal),
+        _ => display(a).cmp(&display(b)),
+    }
+}
+
+fn display(v: &Value) -> String {
+    match v {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Boolean filter expression for `--where`.
+#[derive(Debug, Clone)]
+pub enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    /// Bare field name: true when the value is present and non-empty.
+    Truthy(String),
+    Compare(String, CmpOp, Value),
+    Matches(String, Regex, bool),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Expr {
+    pub fn eval(&self, record: &Record) -> bool {
+        match self {
+            Expr::And(a, b) => a.eval(record) && b.eval(record),
+            Expr::Or(a, b) => a.eval(record) || b.eval(record),
+            Expr::Not(e) => !e.eval(record),
+            Expr::Truthy(field) => record.get(field).is_some_and(truthy),
+            Expr::Compare(field, op, literal) => {
+                let value = record.get(field).unwrap_or(&Value::Null);
+                match (value, op) {
+                    // List fields: `==` means "contains", `!=` means "does not contain".
+                    (Value::Array(items), CmpOp::Eq) => items.iter().any(|v| equals(v, literal)),
+                    (Value::Array(items), CmpOp::Ne) => !items.iter().any(|v| equals(v, literal)),
+                    (_, CmpOp::Eq) => equals(value, literal),
+                    (_, CmpOp::Ne) => !equals(value, literal),
+                    _ => order(value, literal).is_some_and(|o| match op {
+                        CmpOp::Lt => o.is_lt(),
+                        CmpOp::Le => o.is_le(),
+                        CmpOp::Gt => o.is_gt(),
+                        CmpOp::Ge => o.is_ge(),
+                        CmpOp::Eq | CmpOp::Ne => unreachable!("handled above"),
+                    }),
+                }
+            }
+            Expr::Matches(field, re, negate) => {
+                let hit = match record.get(field) {
+                    Some(Value::Array(items)) => items.iter().any(|v| re.is_match(&display(v))),
+                    Some(Value::Null) | None => false,
+                    Some(v) => re.is_match(&display(v)),
+                };
+                hit != *negate
+            }
+        }
+    }
+}
+
+fn truthy(v: &Value) -> bool {
+    match v {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::String(s) => !s.is_empty(),
+        Value::Array(a) => !a.is_empty(),
+        Value::Number(n) => n.as_f64() != Some(0.0),
+        Value::Object(o) => !o.is_empty(),
+    }
+}
+
+/// Typed equality with the coercion of `order` (`"10" == 10`); `null` equals only `null`.
+fn equals(value: &Value, literal: &Value) -> bool {
+    match (value, literal) {
+        (Value::Null, Value::Null) => true,
+        _ => order(value, literal) == Some(Ordering::Equal),
+    }
+}
+
+/// Ordering shared by all comparison operators: numeric when either side is a number and the
+/// other a number or numeric text (trimmed), else lexicographic. Nulls, and numbers against
+/// non-numeric values, have no ordering.
+fn order(value: &Value, literal: &Value) -> Option<Ordering> {
+    let number = |v: &Value| match v {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse::<f64>().ok(),
+        _ => None,
+    };
+    match (value, literal) {
+        (Value::Null, _) | (_, Value::Null) => None,
+        (Value::Number(_), _) | (_, Value::Number(_)) => {
+            number(value)?.partial_cmp(&number(literal)?)
+        }
+        _ => Some(display(value).cmp(&display(literal))),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Num(f64, String),
+    Op(&'static str),
+    LParen,
+    RParen,
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c == '(' || c == ')' {
+            tokens.push(if c == '(' {
+                Token::LParen
+            } else {
+                Token::RParen
+            });
+            i += 1;
+            continue;
+        }
+        if c == '\'' || c == '"' {
+            let mut out = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err(format!("unterminated string in '{s}'")),
+                    Some(&q) if q == c => break,
+                    Some('\\') if chars.get(i + 1).is_some() => {
+                        out.push(chars[i + 1]);
+                        i += 2;
+                    }
+                    Some(&ch) => {
+                        out.push(ch);
+                        i += 1;
+                    }
+                }
+            }
+            i += 1;
+            tokens.push(Token::Str(out));
+            continue;
+        }
+
+        let rest: String = chars[i..].iter().take(2).collect();
+        if let Some(op) = ["==", "!=", "<=", ">=", "=~", "!~", "&&", "||"]
+            .into_iter()
+            .find(|op| rest.starts_with(op))
+        {
+            tokens.push(Token::Op(op));
+            i += 2;
+            continue;
+        }
+        if let Some(op) = ["<", ">", "!", "="]
+            .into_iter()
+            .find(|op| rest.starts_with(op))
+        {
+            // A single `=` is accepted as equality.
+            tokens.push(Token::Op(if op == "=" { "==" } else { op }));
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        while i < chars.len() && !chars[i].is_whitespace() && !"()<>=!'\"&|".contains(chars[i]) {
+            i += 1;
+        }
+        if start == i {
+            return Err(format!("unexpected character '{c}' in '{s}'"));
+        }
+        let word: String = chars[start..i].iter().collect();
+        match word.parse::<f64>() {
+            Ok(n) => tokens.push(Token::Num(n, word)),
+            Err(_) => tokens.push(Token::Ident(word)),
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Parse a `--where` expression, e.g. `STATUS == down and (MTU > 1500 or NAME =~ '^Gi')`.
+pub fn parse_expr(s: &str) -> Result<Expr, String> {
+    let tokens = tokenize(s)?;
+    let mut parser = ExprParser { tokens, pos: 0 };
+    let expr = parser.or_expr()?;
+    if parser.pos != parser.tokens.len() {
+        return Err(format!(
+            "unexpected {} in '{s}'",
+            describe(&parser.tokens[parser.pos])
+        ));
+    }
+    Ok(expr)
+}
+
+fn describe(t: &Token) -> String {
+    match t {
+        Token::Ident(w) | Token::Num(_, w) => format!("'{w}'"),
+        Token::Str(w) => format!("'\"{w}\"'"),
+        Token::Op(op) => format!("'{op}'"),
+        Token::LParen => "'('".to_string(),
+        Token::RParen => "')'".to_string(),
+    }
+}
+
+struct ExprParser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl ExprParser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let t = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        t
+    }
+
+    fn keyword(&self, ops: &[&str], words: &[&str]) -> bool {
+        match self.peek() {
+            Some(Token::Op(op)) => ops.contains(op),
+            Some(Token::Ident(w)) => words.contains(&w.to_lowercase().as_str()),
+            _ => false,
+        }
+    }
+
+    fn or_expr(&mut self) -> Result<Expr, String> {
+        let mut left = self.and_expr()?;
+        while self.keyword(&["||"], &["or"]) {
+            self.pos += 1;
+            left = Expr::Or(Box::new(left), Box::new(self.and_expr()?));
+        }
+        Ok(left)
+    }
+
+    fn and_expr(&mut self) -> Result<Expr, String> {
+        let mut left = self.unary()?;
+        while self.keyword(&["&&"], &["and"]) {
+            self.pos += 1;
+            left = Expr::And(Box::new(left), Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.keyword(&["!"], &["not"]) {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        match self.next() {
+            Some(Token::LParen) => {
+                let inner = self.or_expr()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(inner),
+                    _ => Err("missing closing ')'".to_string()),
+                }
+            }
+            Some(Token::Ident(field)) | Some(Token::Str(field)) => self.comparison(field),
+            Some(other) => Err(format!("expected a field name, got {}", describe(&other))),
+            None => Err("expression ended early".to_string()),
+        }
+    }
+
+    fn comparison(&mut self, field: String) -> Result<Expr, String> {
+        let op = match self.peek() {
+            Some(Token::Op(op)) if !matches!(*op, "&&" | "||" | "!") => *op,
+            _ => return Ok(Expr::Truthy(field)),
+        };
+        self.pos += 1;
+
+        let literal = match self.next() {
+            Some(Token::Str(s)) => Value::String(s),
+            Some(Token::Num(n, raw)) => match raw.parse::<i64>() {
+                Ok(i) => Value::from(i),
+                Err(_) => serde_json::Number::from_f64(n)
+                    .map(Value::Number)
+                    .unwrap_or(Value::String(raw)),
+            },
+            Some(Token::Ident(w)) => match w.as_str() {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                "null" => Value::Null,
+                _ => Value::String(w),
+            },
+            Some(other) => {
+                return Err(format!(
+                    "expected a value after '{op}', got {}",
+                    describe(&other)
+                ));
+            }
+            None => return Err(format!("expected a value after '{op}'")),
+        };
+
+        if op == "=~" || op == "!~" {
+            let pattern = display(&literal);
+            let re = Regex::new(&pattern).map_err(|e| format!("invalid regex '{pattern}': {e}"))?;
+            return Ok(Expr::Matches(field, re, op == "!~"));
+        }
+
+        let op = match op {
+            "==" => CmpOp::Eq,
+            "!=" => CmpOp::Ne,
+            "<" => CmpOp::Lt,
+            "<=" => CmpOp::Le,
+            ">" => CmpOp::Gt,
+            ">=" => CmpOp::Ge,
+            other => return Err(format!("unsupported operator '{other}'")),
+        };
+        Ok(Expr::Compare(field, op, literal))
+    }
+}
+
+/// jq-like path over the result set, e.g. `.[].ADDRESSES[]` or `.[0].NAME`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathQuery {
+    pub segments: Vec<PathSegment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+    Iterate,
+}
+
+/// Parse a `--query` path. The input is the record array, so paths usually start with `.[]`.
+pub fn parse_path(s: &str) -> Result<PathQuery, String> {
+    let s = s.trim();
+    let rest = s
+        .strip_prefix('.')
+        .ok_or_else(|| format!("query must start with '.', got '{s}'"))?;
+
+    let chars: Vec<char> = rest.chars().collect();
+    let mut segments = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '.' => i += 1,
+            '[' => {
+                let end = chars[i..]
+                    .iter()
+                    .position(|&c| c == ']')
+                    .map(|p| i + p)
+                    .ok_or_else(|| format!("missing ']' in '{s}'"))?;
+                let inner: String = chars[i + 1..end].iter().collect();
+                let inner = inner.trim();
+                segments.push(if inner.is_empty() {
+                    PathSegment::Iterate
+                } else if let Ok(idx) = inner.parse::<usize>() {
+                    PathSegment::Index(idx)
+                } else {
+                    let key = inner.trim_matches(|c| c == '"' || c == '\'');
+                    PathSegment::Key(key.to_string())
+                });
+                i = end + 1;
+            }
+            _ => {
+                let start = i;
+                while i < chars.len() && chars[i] != '.' && chars[i] != '[' {
+                    i += 1;
+                }
+                segments.push(PathSegment::Key(chars[start..i].iter().collect()));
+            }
+        }
+    }
+
+    Ok(PathQuery { segments })
+}
+
+impl PathQuery {
+    /// Evaluate against the record array and turn the selected values back into records:
+    /// objects stay records, arrays are flattened, scalars become `{"value": ...}`.
+    pub fn select(&self, records: Vec<Record>) -> Vec<Record> {
+        let root = Value::Array(
+            records
+                .into_iter()
+                .map(|r| Value::Object(r.into_iter().collect()))
+                .collect(),
+        );
+        let mut current = vec![root];
+        for segment in &self.segments {
+            current = current
+                .into_iter()
+                .flat_map(|v| match (segment, v) {
+                    (PathSegment::Key(k), Value::Object(mut o)) => {
+                        o.remove(k).into_iter().collect()
+                    }
+                    (PathSegment::Index(i), Value::Array(mut a)) if *i < a.len() => {
+                        vec![a.swap_remove(*i)]
+                    }
+                    (PathSegment::Iterate, Value::Array(a)) => a,
+                    (PathSegment::Iterate, Value::Object(o)) => {
+                        o.into_iter().map(|(_, v)| v).collect()
+                    }
+                    _ => Vec::new(),
+                })
+                .collect();
+        }
+
+        current
+            .into_iter()
+            .flat_map(|v| match v {
+                Value::Array(items) => items,
+                other => vec![other],
+            })
+            .map(|v| match v {
+                Value::Object(o) => o.into_iter().collect(),
+                other => Record::from([("value".to_string(), other)]),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn records() -> Vec<Record> {
+        let rows = json!([
+            { "NAME": "Gi0/1", "STATUS": "up", "MTU": 1500, "VLANS": ["10", "20"] },
+            { "NAME": "Gi0/2", "STATUS": "down", "MTU": 9000, "VLANS": ["30"] },
+            { "NAME": "Te1/1", "STATUS": "down", "MTU": 1500, "VLANS": [] }
+        ]);
+        serde_json::from_value(rows).unwrap()
+    }
+
+    fn names(rows: &[Record]) -> Vec<&str> {
+        rows.iter().map(|r| r["NAME"].as_str().unwrap()).collect()
+    }
+
+    #[test]
+    fn where_combines_typed_comparisons_and_regex() {
+        let query = ResultQuery {
+            filters: vec![parse_expr("STATUS == down and (MTU > 1500 or NAME =~ '^Te')").unwrap()],
+            ..Default::default()
+        };
+        assert_eq!(names(&query.apply(records())), vec!["Gi0/2", "Te1/1"]);
+    }
+
+    #[test]
+    fn where_on_list_field_tests_membership_and_truthiness() {
+        let contains = parse_expr("VLANS == 20").unwrap();
+        let nonempty = parse_expr("not VLANS").unwrap();
+        let rows = records();
+        assert!(contains.eval(&rows[0]));
+        assert!(!contains.eval(&rows[1]));
+        assert!(nonempty.eval(&rows[2]));
+    }
+
+    #[test]
+    fn comparisons_coerce_numeric_text_either_way_round() {
+        let record: Record =
+            serde_json::from_value(json!({ "MTU": 1500, "SPEED": " 10 " })).unwrap();
+        for (expr, expected) in [
+            // Number field, text literal.
+            ("MTU > '900'", true),
+            ("MTU < '900'", false),
+            ("MTU == ' 1500 '", true),
+            ("MTU >= 'auto'", false),
+            // Text field, number literal.
+            ("SPEED > 9", true),
+            ("SPEED <= 9", false),
+            ("SPEED == 10", true),
+        ] {
+            assert_eq!(parse_expr(expr).unwrap().eval(&record), expected, "{expr}");
+        }
+    }
+
+    #[test]
+    fn sort_limit_and_rename() {
+        let query = ResultQuery {
+            sort: vec![
+                parse_sort_key("MTU:desc").unwrap(),
+                parse_sort_key("NAME").unwrap(),
+            ],
+            limit: Some(2),
+            fields: vec![
+                parse_field("NAME:interface").unwrap(),
+                parse_field("MTU").unwrap(),
+            ],
+            ..Default::default()
+        };
+        let out = query.apply(records());
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0]["interface"], "Gi0/2");
+        assert_eq!(out[1]["interface"], "Gi0/1");
+        assert_eq!(out[1].len(), 2);
+        // Output follows the requested field order, not the records' key order.
+        assert_eq!(query.columns(Vec::new()), ["interface", "MTU"]);
+    }
+
+    #[test]
+    fn path_query_flattens_nested_lists() {
+        let query = ResultQuery {
+            path: Some(parse_path(".[].VLANS[]").unwrap()),
+            ..Default::default()
+        };
+        let out = query.apply(records());
+        let values: Vec<&Value> = out.iter().map(|r| &r["value"]).collect();
+        assert_eq!(values, vec!["10", "20", "30"]);
+
+        let first = parse_path(".[1]").unwrap().select(records());
+        assert_eq!(names(&first), vec!["Gi0/2"]);
+    }
+
+    #[test]
+    fn rejects_malformed_expressions() {
+        assert!(parse_expr("STATUS ==").is_err());
+        assert!(parse_expr("(STATUS == up").is_err());
+        assert!(parse_expr("NAME =~ '['").is_err());
+        assert!(parse_sort_key("MTU:sideways").is_err());
+        assert!(parse_field(":alias").is_err());
+        assert!(parse_path("[]").is_err());
+    }
+}
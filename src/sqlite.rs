@@ -0,0 +1,301 @@
+//! SQLite export: append parsed records to a database for offline analysis.
+//!
+//! Each record table becomes one SQL table named after the template (`<template>` for the
+//! default table, `<template>_<table>` for named tables). Scalar fields are columns typed from
+//! the template (`INTEGER` for `int`, `TEXT` for `string`); every list field gets a child table
+//! `<table>_<field>` with one row per item. Provenance columns record the input source and the
+//! parse time (Unix seconds). Existing tables are appended to, gaining columns as needed.
+
+use anyhow::{Context, Result};
+use cliscrape::engine::{FieldType, Value as FieldDef};
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{Connection, Transaction, params_from_iter};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+pub type Record = BTreeMap<String, Value>;
+
+/// SQL table name derived from a template file name: extension dropped, anything outside
+/// `[A-Za-z0-9_]` replaced by `_`.
+pub fn table_name(template_name: &str) -> String {
+    let stem = Path::new(template_name)
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| template_name.to_string());
+    stem.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// Records of one record table, tagged with the input source they came from.
+pub struct TableExport<'a> {
+    pub name: String,
+    pub fields: Vec<&'a FieldDef>,
+    pub rows: Vec<(&'a str, &'a Record)>,
+}
+
+/// Append every table to the database at `path` in a single transaction; returns the number
+/// of records written.
+pub fn export(path: &Path, tables: &[TableExport], parsed_at: i64) -> Result<usize> {
+    let mut conn = Connection::open(path)
+        .with_context(|| format!("Failed to open SQLite database {}", path.display()))?;
+    let tx = conn
+        .transaction()
+        .context("Failed to start SQLite transaction")?;
+
+    let mut written = 0;
+    for table in tables {
+        written += export_table(&tx, table, parsed_at)
+            .with_context(|| format!("Failed to write SQLite table '{}'", table.name))?;
+    }
+
+    tx.commit().context("Failed to commit SQLite transaction")?;
+    Ok(written)
+}
+
+fn export_table(tx: &Transaction, table: &TableExport, parsed_at: i64) -> Result<usize> {
+    let lists: BTreeSet<&str> = table
+        .fields
+        .iter()
+        .filter(|f| f.list)
+        .map(|f| f.name.as_str())
+        .collect();
+
+    // Template scalars first, then injected columns (constants, path captures) as untyped.
+    let mut columns: Vec<(String, &str)> = table
+        .fields
+        .iter()
+        .filter(|f| !f.list)
+        .map(|f| (f.name.clone(), sql_type(f.type_hint)))
+        .collect();
+    let mut extra = BTreeSet::new();
+    for (_, record) in &table.rows {
+        extra.extend(record.keys().filter(|k| {
+            !lists.contains(k.as_str()) && !columns.iter().any(|(name, _)| name == *k)
+        }));
+    }
+    columns.extend(extra.into_iter().map(|name| (name.clone(), "")));
+
+    ensure_table(
+        tx,
+        &table.name,
+        "\"_id\" INTEGER PRIMARY KEY, \"_source\" TEXT NOT NULL, \"_parsed_at\" INTEGER NOT NULL",
+        &columns,
+    )?;
+    for list in &lists {
+        let item_type = table
+            .fields
+            .iter()
+            .find(|f| f.name == *list)
+            .map_or("", |f| sql_type(f.type_hint));
+        ensure_table(
+            tx,
+            &format!("{}_{}", table.name, list),
+            &format!(
+                "\"_parent_id\" INTEGER NOT NULL REFERENCES {}(\"_id\"), \"_position\" INTEGER NOT NULL",
+                quote(&table.name)
+            ),
+            &[("value".to_string(), item_type)],
+        )?;
+    }
+
+    let column_list: Vec<String> = ["_source", "_parsed_at"]
+        .into_iter()
+        .map(quote)
+        .chain(columns.iter().map(|(name, _)| quote(name)))
+        .collect();
+    let placeholders = vec!["?"; column_list.len()].join(", ");
+    let mut insert = tx.prepare(&format!(
+        "INSERT INTO {} ({}) VALUES ({})",
+        quote(&table.name),
+        column_list.join(", "),
+        placeholders
+    ))?;
+    let mut insert_items = lists
+        .iter()
+        .map(|list| {
+            let sql = format!(
+                "INSERT INTO {} (\"_parent_id\", \"_position\", \"value\") VALUES (?, ?, ?)",
+                quote(&format!("{}_{}", table.name, list))
+            );
+            Ok((*list, tx.prepare(&sql)?))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    for (source, record) in &table.rows {
+        let values = [
+            SqlValue::Text(source.to_string()),
+            SqlValue::Integer(parsed_at),
+        ]
+        .into_iter()
+        .chain(columns.iter().map(|(name, _)| sql_value(record.get(name))));
+        insert.execute(params_from_iter(values))?;
+        let parent = tx.last_insert_rowid();
+
+        for (list, stmt) in &mut insert_items {
+            let Some(Value::Array(items)) = record.get(*list) else {
+                continue;
+            };
+            for (position, item) in items.iter().enumerate() {
+                stmt.execute(params_from_iter([
+                    SqlValue::Integer(parent),
+                    SqlValue::Integer(position as i64),
+                    sql_value(Some(item)),
+                ]))?;
+            }
+        }
+    }
+
+    Ok(table.rows.len())
+}
+
+/// Create `name` if missing, otherwise add any of `columns` it does not have yet.
+fn ensure_table(
+    tx: &Transaction,
+    name: &str,
+    fixed: &str,
+    columns: &[(String, &str)],
+) -> Result<()> {
+    let defs: Vec<String> = columns
+        .iter()
+        .map(|(column, ty)| format!("{} {}", quote(column), ty).trim_end().to_string())
+        .collect();
+    let mut create = format!("CREATE TABLE IF NOT EXISTS {} ({}", quote(name), fixed);
+    for def in &defs {
+        create.push_str(", ");
+        create.push_str(def);
+    }
+    create.push(')');
+    tx.execute(&create, [])?;
+
+    let existing: BTreeSet<String> = tx
+        .prepare(&format!("PRAGMA table_info({})", quote(name)))?
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<rusqlite::Result<_>>()?;
+    for ((column, _), def) in columns.iter().zip(&defs) {
+        if !existing.contains(column) {
+            tx.execute(
+                &format!("ALTER TABLE {} ADD COLUMN {}", quote(name), def),
+                [],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn sql_type(hint: Option<FieldType>) -> &'static str {
+    match hint {
+        Some(FieldType::Int) => "INTEGER",
+        Some(FieldType::String) => "TEXT",
+        // Untyped TextFSM values keep whatever the engine produced.
+        None => "",
+    }
+}
+
+fn sql_value(value: Option<&Value>) -> SqlValue {
+    match value {
+        None | Some(Value::Null) => SqlValue::Null,
+        Some(Value::Bool(b)) => SqlValue::Integer(i64::from(*b)),
+        Some(Value::Number(n)) => match n.as_i64() {
+            Some(i) => SqlValue::Integer(i),
+            None => SqlValue::Real(n.as_f64().unwrap_or_default()),
+        },
+        Some(Value::String(s)) => SqlValue::Text(s.clone()),
+        Some(other) => SqlValue::Text(other.to_string()),
+    }
+}
+
+fn quote(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn field(name: &str, type_hint: Option<FieldType>, list: bool) -> FieldDef {
+        FieldDef {
+            name: name.to_string(),
+            regex: String::new(),
+            filldown: false,
+            required: false,
+            list,
+            type_hint,
+            optional: false,
+            tables: Vec::new(),
+        }
+    }
+
+    fn record(value: serde_json::Value) -> Record {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn table_name_drops_extension_and_sanitizes() {
+        assert_eq!(
+            table_name("cisco_ios_show_version.yaml"),
+            "cisco_ios_show_version"
+        );
+        assert_eq!(table_name("modern/ios-show.int.toml"), "ios_show_int");
+    }
+
+    #[test]
+    fn export_appends_rows_with_child_list_tables() {
+        let dir = std::env::temp_dir().join(format!("cliscrape-sqlite-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = dir.join("export.sqlite");
+        let _ = std::fs::remove_file(&db);
+
+        let port = field("port", Some(FieldType::String), false);
+        let mtu = field("mtu", Some(FieldType::Int), false);
+        let vlans = field("vlans", Some(FieldType::String), true);
+        let first = record(json!({ "port": "Gi0/1", "mtu": 1500, "vlans": ["10", "20"] }));
+        let second = record(json!({ "port": "Gi0/2", "mtu": 9000, "vlans": [], "site": "nyc" }));
+
+        let export_one = |row: &Record| {
+            let table = TableExport {
+                name: "ports".to_string(),
+                fields: vec![&port, &mtu, &vlans],
+                rows: vec![("a.txt", row)],
+            };
+            export(&db, &[table], 1_700_000_000).unwrap()
+        };
+        assert_eq!(export_one(&first), 1);
+        assert_eq!(export_one(&second), 1, "second run appends and adds 'site'");
+
+        let conn = Connection::open(&db).unwrap();
+        let rows: Vec<(i64, String, i64, Option<String>)> = conn
+            .prepare("SELECT _id, port, mtu, site FROM ports ORDER BY _id")
+            .unwrap()
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].2, 1500);
+        assert_eq!(rows[0].3, None);
+        assert_eq!(rows[1].3.as_deref(), Some("nyc"));
+
+        let items: Vec<String> = conn
+            .prepare("SELECT value FROM ports_vlans WHERE _parent_id = ? ORDER BY _position")
+            .unwrap()
+            .query_map([rows[0].0], |r| r.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(items, vec!["10", "20"]);
+
+        let mtu_type: String = conn
+            .query_row(
+                "SELECT type FROM pragma_table_info('ports') WHERE name = 'mtu'",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(mtu_type, "INTEGER");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
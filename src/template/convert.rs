@@ -1,8 +1,9 @@
 use crate::engine::types::{Action, FieldType, TemplateIR};
 use crate::template::modern::{
-    ActionDef, FieldDef, FieldTypeDef, LineActionDef, ModernTemplateDoc, RecordActionDef,
-    StateRuleDef,
+    ActionDef, ColumnDef, ColumnSpecDef, FieldDef, FieldTypeDef, LineActionDef, ModernTemplateDoc,
+    RecordActionDef, StateRuleDef,
 };
+use indexmap::IndexMap;
 use std::collections::BTreeMap;
 
 /// Best-effort conversion from the legacy `TemplateIR` (TextFSM lowering target)
@@ -11,22 +12,27 @@ use std::collections::BTreeMap;
 /// Notes:
 /// - Output defaults all fields to explicit `string` typing unless the IR already has a type hint.
 /// - Output uses explicit `states` (not `patterns`).
+/// - Fields keep the IR's declaration order; values missing from it follow by name.
+/// - A declared coverage threshold is carried over as `metadata.coverage_threshold`.
 pub fn template_ir_to_modern_doc(ir: &TemplateIR) -> ModernTemplateDoc {
-    let mut fields = BTreeMap::new();
-    for (name, v) in &ir.values {
+    let mut fields = IndexMap::new();
+    for name in ir.ordered_field_names() {
+        let v = &ir.values[&name];
         let r#type = Some(match v.type_hint {
             Some(FieldType::Int) => FieldTypeDef::Int,
             Some(FieldType::String) | None => FieldTypeDef::String,
         });
 
         fields.insert(
-            name.clone(),
+            name,
             FieldDef {
                 r#type,
                 pattern: Some(v.regex.clone()),
                 filldown: v.filldown,
                 required: v.required,
                 list: v.list,
+                optional: v.optional,
+                tables: v.tables.clone(),
             },
         );
     }
@@ -49,11 +55,17 @@ pub fn template_ir_to_modern_doc(ir: &TemplateIR) -> ModernTemplateDoc {
                 };
 
                 let next = r.next_state.clone();
+                let table = r.table.clone();
 
                 let action = if line.is_none() && record.is_none() && next.is_none() {
                     None
                 } else {
-                    Some(ActionDef { line, record, next })
+                    Some(ActionDef {
+                        line,
+                        record,
+                        next,
+                        table,
+                    })
                 };
 
                 StateRuleDef {
@@ -70,9 +82,25 @@ pub fn template_ir_to_modern_doc(ir: &TemplateIR) -> ModernTemplateDoc {
         version: 1,
         macros: ir.macros.clone(),
         fields,
+        columns: ir
+            .columns
+            .iter()
+            .map(|c| {
+                if c.hidden {
+                    ColumnDef::Spec(ColumnSpecDef {
+                        name: c.name.clone(),
+                        hidden: true,
+                    })
+                } else {
+                    ColumnDef::Name(c.name.clone())
+                }
+            })
+            .collect(),
         states: Some(states),
         patterns: None,
-        metadata: None,
+        metadata: ir
+            .coverage_threshold
+            .map(|t| serde_json::json!({ "coverage_threshold": t })),
     }
 }
 
@@ -0,0 +1,249 @@
+//! ntc-templates style index files for command-based template selection
+//!
+//! An index is a CSV-like table whose first non-comment line names the columns:
+//!
+//! ```text
+//! # Comments and blank lines are ignored
+//! Template, Hostname, Platform, Command
+//!
+//! cisco_ios_show_version.yaml, .*, cisco_ios, sh[[ow]] ver[[sion]]
+//! ```
+//!
+//! `Template` and `Command` are required; `Platform` and `Hostname` are optional. `Platform`,
+//! `Hostname` and `Command` are regexes matched against the whole value; in `Command`,
+//! `[[xyz]]` marks an optional completion (`sh[[ow]]` matches `sh`, `sho` and `show`).
+//! `Template` may list several colon-separated templates; entries are tried in file order.
+
+use regex::Regex;
+
+/// One index row
+#[derive(Debug, Clone)]
+pub struct IndexEntry {
+    /// Template names, in the order listed
+    pub templates: Vec<String>,
+    pub hostname: Option<Regex>,
+    pub platform: Option<Regex>,
+    pub command: Regex,
+    /// One-based line number in the index file
+    pub line: usize,
+}
+
+/// Parsed template index
+#[derive(Debug, Clone, Default)]
+pub struct TemplateIndex {
+    pub entries: Vec<IndexEntry>,
+}
+
+impl TemplateIndex {
+    /// Parse index file content
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(TemplateIndex)` with entries in file order
+    /// - `Err(String)` naming the offending line when the header or a row is invalid
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut rows = content.lines().enumerate().filter(|(_, line)| {
+            let line = line.trim();
+            !line.is_empty() && !line.starts_with('#')
+        });
+
+        let Some((_, header)) = rows.next() else {
+            return Ok(TemplateIndex::default());
+        };
+        let columns: Vec<String> = header.split(',').map(|c| c.trim().to_string()).collect();
+        let column = |name: &str| columns.iter().position(|c| c == name);
+        let template_col = column("Template").ok_or("Index header has no 'Template' column")?;
+        let command_col = column("Command").ok_or("Index header has no 'Command' column")?;
+        let platform_col = column("Platform");
+        let hostname_col = column("Hostname");
+
+        let mut entries = Vec::new();
+        for (idx, line) in rows {
+            let line_no = idx + 1;
+            let cells: Vec<&str> = line.split(',').map(str::trim).collect();
+            if cells.len() != columns.len() {
+                return Err(format!(
+                    "Index line {}: expected {} columns, found {}",
+                    line_no,
+                    columns.len(),
+                    cells.len()
+                ));
+            }
+            let pattern = |col: Option<usize>, completion: bool| -> Result<Option<Regex>, String> {
+                let Some(text) = col.map(|c| cells[c]) else {
+                    return Ok(None);
+                };
+                let compiled = if completion {
+                    command_regex(text)
+                } else {
+                    Regex::new(&format!("^(?:{})$", text))
+                };
+                compiled
+                    .map(Some)
+                    .map_err(|e| format!("Index line {}: invalid regex '{}': {}", line_no, text, e))
+            };
+
+            entries.push(IndexEntry {
+                templates: cells[template_col]
+                    .split(':')
+                    .map(|t| t.trim().to_string())
+                    .filter(|t| !t.is_empty())
+                    .collect(),
+                hostname: pattern(hostname_col, false)?,
+                platform: pattern(platform_col, false)?,
+                command: pattern(Some(command_col), true)?.expect("command column is present"),
+                line: line_no,
+            });
+        }
+
+        Ok(TemplateIndex { entries })
+    }
+
+    /// First entry matching `command` (and `platform` and `hostname`, when given)
+    ///
+    /// Whitespace in `command` is normalized before matching.
+    pub fn lookup(
+        &self,
+        platform: Option<&str>,
+        command: &str,
+        hostname: Option<&str>,
+    ) -> Option<&IndexEntry> {
+        let command = normalize_command(command);
+        let matches = |value: Option<&str>, pattern: &Option<Regex>| match (value, pattern) {
+            (Some(value), Some(re)) => re.is_match(value),
+            _ => true,
+        };
+        self.entries.iter().find(|entry| {
+            entry.command.is_match(&command)
+                && matches(platform, &entry.platform)
+                && matches(hostname, &entry.hostname)
+        })
+    }
+
+    /// Append the entries of another index (lower precedence)
+    pub fn extend(&mut self, other: TemplateIndex) {
+        self.entries.extend(other.entries);
+    }
+}
+
+/// Collapse whitespace runs in a command, as [`TemplateIndex::lookup`] does before matching.
+pub fn normalize_command(command: &str) -> String {
+    command.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Compile an index `Command` pattern: `[[xyz]]` completions expanded, anchored to match a whole
+/// normalized command.
+pub fn command_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    Regex::new(&format!("^(?:{})$", expand_completions(pattern)))
+}
+
+/// `Command` pattern for a plain command whose words may each be abbreviated to any prefix:
+/// `show ip` becomes `s[[how]] i[[p]]`.
+pub fn abbreviable_command(command: &str) -> String {
+    command
+        .split_whitespace()
+        .map(|word| {
+            let mut chars = word.chars();
+            let first = chars.next().map(String::from).unwrap_or_default();
+            let rest = chars.as_str();
+            if rest.is_empty() {
+                regex::escape(&first)
+            } else {
+                format!("{}[[{}]]", regex::escape(&first), rest)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Rewrite `[[xyz]]` completions as nested optional groups: `(x(y(z)?)?)?`
+fn expand_completions(command: &str) -> String {
+    let mut out = String::new();
+    let mut rest = command;
+    while let Some(start) = rest.find("[[") {
+        let Some(len) = rest[start + 2..].find("]]") else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let chars: Vec<char> = rest[start + 2..start + 2 + len].chars().collect();
+        for c in &chars {
+            out.push('(');
+            out.push_str(&regex::escape(&c.to_string()));
+        }
+        out.push_str(&")?".repeat(chars.len()));
+        rest = &rest[start + 2 + len + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INDEX: &str = "\
+# ntc-templates style index
+Template, Hostname, Platform, Command
+
+cisco_ios_show_ip_int_brief.textfsm, .*, cisco_ios, sh[[ow]] ip int[[erface]] br[[ief]]
+cisco_ios_show_version.yaml:extra.yaml, .*, cisco_ios, sh[[ow]] ver[[sion]]
+arista_eos_show_version.yaml, .*, arista_eos, sh[[ow]] ver[[sion]]
+";
+
+    #[test]
+    fn expands_completions_into_nested_optional_groups() {
+        assert_eq!(expand_completions("sh[[ow]] ver"), "sh(o(w)?)? ver");
+        assert_eq!(expand_completions("show"), "show");
+    }
+
+    #[test]
+    fn lookup_matches_abbreviated_commands_per_platform() {
+        let index = TemplateIndex::parse(INDEX).unwrap();
+        assert_eq!(index.entries.len(), 3);
+
+        let entry = index.lookup(Some("cisco_ios"), "sh  ver", None).unwrap();
+        assert_eq!(
+            entry.templates,
+            ["cisco_ios_show_version.yaml", "extra.yaml"]
+        );
+        assert_eq!(entry.line, 5);
+
+        let entry = index
+            .lookup(Some("cisco_ios"), "show ip interface br", None)
+            .unwrap();
+        assert_eq!(entry.templates, ["cisco_ios_show_ip_int_brief.textfsm"]);
+
+        assert!(
+            index
+                .lookup(Some("arista_eos"), "show version", None)
+                .is_some()
+        );
+        assert!(
+            index
+                .lookup(Some("cisco_ios"), "show versions", None)
+                .is_none()
+        );
+        assert!(
+            index
+                .lookup(Some("juniper_junos"), "show version", None)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn abbreviable_commands_accept_word_prefixes() {
+        assert_eq!(abbreviable_command("show  ip"), "s[[how]] i[[p]]");
+        let re = command_regex(&abbreviable_command("show run | include bgp")).unwrap();
+        assert!(re.is_match("sh run | inc bgp"));
+        assert!(re.is_match("show run | include bgp"));
+        assert!(!re.is_match("sh run"));
+        assert!(!re.is_match("show run | include bgpx"));
+    }
+
+    #[test]
+    fn rejects_rows_with_wrong_column_count() {
+        let err =
+            TemplateIndex::parse("Template, Platform, Command\na.yaml, cisco_ios\n").unwrap_err();
+        assert!(err.contains("line 2"), "{err}");
+    }
+}
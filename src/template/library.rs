@@ -13,6 +13,7 @@
 //! - `.yaml` - Template metadata and configuration
 //! - `.toml` - Template metadata and configuration
 //! - `.textfsm` - TextFSM template definitions
+//! - `index` - command index mapping platform and command to a template (see `index.rs`)
 //!
 //! Test and documentation files (`.md`, `tests/*`) are excluded from embedding.
 
@@ -27,13 +28,15 @@ use rust_embed::RustEmbed;
 #[include = "*.yaml"]
 #[include = "*.toml"]
 #[include = "*.textfsm"]
+#[include = "index"]
 #[exclude = "*.md"]
 #[exclude = "tests/*"]
 pub struct EmbeddedTemplates;
 
 /// List all embedded template names
 ///
-/// Returns the filenames of all templates embedded in the binary.
+/// Returns the filenames of all templates embedded in the binary (the command `index` is not
+/// a template and is left out).
 ///
 /// # Examples
 ///
@@ -46,7 +49,10 @@ pub struct EmbeddedTemplates;
 /// }
 /// ```
 pub fn list_embedded() -> Vec<String> {
-    EmbeddedTemplates::iter().map(|s| s.to_string()).collect()
+    EmbeddedTemplates::iter()
+        .filter(|s| s != super::resolver::INDEX_FILE)
+        .map(|s| s.to_string())
+        .collect()
 }
 
 /// Get an embedded template by name
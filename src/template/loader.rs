@@ -20,6 +20,7 @@ impl TextFsmLoader {
             .map_err(|e| ScraperError::Template(format!("Pest error: {}", e)))?;
 
         let mut values = HashMap::new();
+        let mut field_order = Vec::new();
         let mut states = HashMap::new();
         let mut warnings = Vec::new();
 
@@ -29,6 +30,9 @@ impl TextFsmLoader {
                 PestRule::val_def => {
                     let (value, val_warnings) = self::parse_definition_with_warnings(pair)?;
                     warnings.extend(val_warnings);
+                    if !field_order.contains(&value.name) {
+                        field_order.push(value.name.clone());
+                    }
                     values.insert(value.name.clone(), value);
                 }
                 PestRule::state_block => {
@@ -44,17 +48,130 @@ impl TextFsmLoader {
             }
         }
 
+        let coverage_threshold = apply_coverage_directives(input, &mut values, &mut warnings);
+        apply_table_directives(input, &mut values, &mut warnings);
+
         Ok((
             TemplateIR {
                 values,
                 states,
                 macros: HashMap::new(),
+                coverage_threshold,
+                field_order,
+                columns: Vec::new(),
+                metrics: None,
             },
             warnings,
         ))
     }
 }
 
+/// Apply coverage directives carried in comments (so templates stay loadable by TextFSM):
+///
+/// - `# Coverage-Exclude: DESCRIPTION, SECONDARY_IP` marks values as optional
+/// - `# Coverage-Threshold: 60` sets the template's default coverage threshold
+fn apply_coverage_directives(
+    input: &str,
+    values: &mut HashMap<String, Value>,
+    warnings: &mut Vec<TemplateWarning>,
+) -> Option<f64> {
+    let mut threshold = None;
+
+    for (idx, line) in input.lines().enumerate() {
+        let Some(comment) = line.trim().strip_prefix('#') else {
+            continue;
+        };
+        let Some((key, rest)) = comment.split_once(':') else {
+            continue;
+        };
+
+        match key.trim().to_lowercase().as_str() {
+            "coverage-exclude" | "coverage_exclude" => {
+                for name in rest.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+                    match values.get_mut(name) {
+                        Some(value) => value.optional = true,
+                        None => warnings.push(TemplateWarning {
+                            kind: "unknown_coverage_field".to_string(),
+                            message: format!(
+                                "Coverage-Exclude references undefined Value '{}' - ignoring",
+                                name
+                            ),
+                            line_idx: Some(idx),
+                        }),
+                    }
+                }
+            }
+            "coverage-threshold" | "coverage_threshold" => match rest.trim().parse::<f64>() {
+                Ok(t) if (0.0..=100.0).contains(&t) => threshold = Some(t),
+                _ => warnings.push(TemplateWarning {
+                    kind: "invalid_coverage_threshold".to_string(),
+                    message: format!(
+                        "Coverage-Threshold '{}' is not a number between 0 and 100 - ignoring",
+                        rest.trim()
+                    ),
+                    line_idx: Some(idx),
+                }),
+            },
+            _ => {}
+        }
+    }
+
+    threshold
+}
+
+/// Assign values to named record tables with `# Table ports: PORT, STATUS` comments.
+///
+/// A value may be listed under several tables; unlisted values stay in the default table.
+fn apply_table_directives(
+    input: &str,
+    values: &mut HashMap<String, Value>,
+    warnings: &mut Vec<TemplateWarning>,
+) {
+    for (idx, line) in input.lines().enumerate() {
+        let Some(comment) = line.trim().strip_prefix('#') else {
+            continue;
+        };
+        let Some((key, rest)) = comment.split_once(':') else {
+            continue;
+        };
+        let mut key_parts = key.split_whitespace();
+        let (Some(keyword), Some(table), None) =
+            (key_parts.next(), key_parts.next(), key_parts.next())
+        else {
+            continue;
+        };
+        if !keyword.eq_ignore_ascii_case("table") {
+            continue;
+        }
+
+        for name in rest.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            match values.get_mut(name) {
+                Some(value) => {
+                    if !value.tables.iter().any(|t| t == table) {
+                        value.tables.push(table.to_string());
+                    }
+                }
+                None => warnings.push(TemplateWarning {
+                    kind: "unknown_table_field".to_string(),
+                    message: format!(
+                        "Table '{}' references undefined Value '{}' - ignoring",
+                        table, name
+                    ),
+                    line_idx: Some(idx),
+                }),
+            }
+        }
+    }
+}
+
+/// Split `Record(ports)` into the action keyword and its table name.
+fn split_record_table(action_str: &str) -> (&str, Option<String>) {
+    match action_str.split_once('(') {
+        Some((keyword, rest)) => (keyword, Some(rest.trim_end_matches(')').to_string())),
+        None => (action_str, None),
+    }
+}
+
 fn parse_definition(pair: Pair<PestRule>) -> Result<Value, ScraperError> {
     let (value, _warnings) = parse_definition_with_warnings(pair)?;
     Ok(value)
@@ -110,6 +227,8 @@ fn parse_definition_with_warnings(
             required,
             list,
             type_hint: None,
+            optional: false,
+            tables: Vec::new(),
         },
         warnings,
     ))
@@ -159,6 +278,7 @@ fn parse_rule_with_warnings(
     let mut line_action = Action::Next;
     let mut record_action = Action::Next; // Next acts as NoRecord in our engine
     let mut next_state = None;
+    let mut table = None;
     let mut warnings = Vec::new();
     let mut skip_rule = false;
 
@@ -166,7 +286,7 @@ fn parse_rule_with_warnings(
         match inner.as_rule() {
             PestRule::rule_regex => regex = inner.as_str().trim_end().to_string(),
             PestRule::action => {
-                let (la, ra, ns, action_warnings, skip) = parse_action_with_warnings(inner)?;
+                let (la, ra, ns, tbl, action_warnings, skip) = parse_action_with_warnings(inner)?;
                 warnings.extend(action_warnings);
                 if skip {
                     skip_rule = true;
@@ -174,6 +294,7 @@ fn parse_rule_with_warnings(
                     line_action = la;
                     record_action = ra;
                     next_state = ns;
+                    table = tbl;
                 }
             }
             _ => {}
@@ -189,6 +310,7 @@ fn parse_rule_with_warnings(
                 line_action,
                 record_action,
                 next_state,
+                table,
             }),
             warnings,
         ))
@@ -196,23 +318,51 @@ fn parse_rule_with_warnings(
 }
 
 fn parse_action(pair: Pair<PestRule>) -> Result<(Action, Action, Option<String>), ScraperError> {
-    let (la, ra, ns, _warnings, _skip) = parse_action_with_warnings(pair)?;
+    let (la, ra, ns, _table, _warnings, _skip) = parse_action_with_warnings(pair)?;
     Ok((la, ra, ns))
 }
 
 fn parse_action_with_warnings(
     pair: Pair<PestRule>,
-) -> Result<(Action, Action, Option<String>, Vec<TemplateWarning>, bool), ScraperError> {
+) -> Result<
+    (
+        Action,
+        Action,
+        Option<String>,
+        Option<String>,
+        Vec<TemplateWarning>,
+        bool,
+    ),
+    ScraperError,
+> {
     let mut line_action = Action::Next;
     let mut record_action = Action::Next;
     let mut next_state = None;
+    let mut table = None;
     let mut warnings = Vec::new();
     let mut skip_rule = false;
 
     for inner in pair.into_inner() {
         match inner.as_rule() {
-            PestRule::line_action => {
-                let action_str = inner.as_str();
+            PestRule::line_action | PestRule::record_action => {
+                let (action_str, action_table) = split_record_table(inner.as_str());
+                if action_table.is_some() && action_str != "Record" {
+                    warnings.push(TemplateWarning {
+                        kind: "unknown_record_action".to_string(),
+                        message: format!(
+                            "Only Record accepts a table name, got '{}' - skipping rule",
+                            inner.as_str()
+                        ),
+                        line_idx: None,
+                    });
+                    skip_rule = true;
+                    continue;
+                }
+                table = action_table.or(table);
+                if inner.as_rule() == PestRule::record_action {
+                    record_action = parse_record_keyword(action_str, &mut warnings, &mut skip_rule);
+                    continue;
+                }
                 // Check if this is actually a record action keyword that was misparsed as line_action
                 if matches!(
                     action_str,
@@ -239,29 +389,6 @@ fn parse_action_with_warnings(
                     next_state = Some(action_str.to_string());
                 }
             }
-            PestRule::record_action => {
-                let action_str = inner.as_str();
-                record_action = match action_str {
-                    "Record" => Action::Record,
-                    "Clear" => Action::Clear,
-                    "Clearall" => Action::ClearAll,
-                    "Error" => Action::Error,
-                    "NoRecord" => Action::Next,
-                    _ => {
-                        // Unknown record action: warn and skip this rule
-                        warnings.push(TemplateWarning {
-                            kind: "unknown_record_action".to_string(),
-                            message: format!(
-                                "Unknown record action '{}' - skipping rule",
-                                action_str
-                            ),
-                            line_idx: None,
-                        });
-                        skip_rule = true;
-                        Action::Next
-                    }
-                };
-            }
             PestRule::next_state => {
                 next_state = Some(inner.as_str().to_string());
             }
@@ -269,7 +396,38 @@ fn parse_action_with_warnings(
         }
     }
 
-    Ok((line_action, record_action, next_state, warnings, skip_rule))
+    Ok((
+        line_action,
+        record_action,
+        next_state,
+        table,
+        warnings,
+        skip_rule,
+    ))
+}
+
+fn parse_record_keyword(
+    action_str: &str,
+    warnings: &mut Vec<TemplateWarning>,
+    skip_rule: &mut bool,
+) -> Action {
+    match action_str {
+        "Record" => Action::Record,
+        "Clear" => Action::Clear,
+        "Clearall" => Action::ClearAll,
+        "Error" => Action::Error,
+        "NoRecord" => Action::Next,
+        _ => {
+            // Unknown record action: warn and skip this rule
+            warnings.push(TemplateWarning {
+                kind: "unknown_record_action".to_string(),
+                message: format!("Unknown record action '{}' - skipping rule", action_str),
+                line_idx: None,
+            });
+            *skip_rule = true;
+            Action::Next
+        }
+    }
 }
 
 #[cfg(test)]
@@ -313,4 +471,61 @@ Start
         assert_eq!(rules[2].next_state, Some("NextState".to_string()));
         assert_eq!(rules[2].line_action, Action::Next);
     }
+
+    #[test]
+    fn test_coverage_directives_mark_optional_and_threshold() {
+        let input = r#"# Coverage-Exclude: DESCRIPTION, MISSING
+# Coverage-Threshold: 60
+Value INTERFACE (\S+)
+Value DESCRIPTION (.+)
+
+Start
+  ^Interface ${INTERFACE} -> Record
+"#;
+        let (ir, warnings) = TextFsmLoader::parse_str_with_warnings(input).unwrap();
+        assert!(ir.values["DESCRIPTION"].optional);
+        assert!(!ir.values["INTERFACE"].optional);
+        assert_eq!(ir.coverage_threshold, Some(60.0));
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind, "unknown_coverage_field");
+    }
+
+    #[test]
+    fn test_record_table_actions_and_directives() {
+        let input = r#"# Table ports: PORT, MISSING
+Value PORT (\S+)
+
+Start
+  ^Port ${PORT} -> Record(ports)
+  ^Next ${PORT} -> Continue.Record(ports) Start
+  ^Bad -> Clear(ports)
+"#;
+        let (ir, warnings) = TextFsmLoader::parse_str_with_warnings(input).unwrap();
+        let rules = &ir.states["Start"].rules;
+
+        assert_eq!(rules.len(), 2, "Clear(ports) is skipped");
+        assert_eq!(rules[0].record_action, Action::Record);
+        assert_eq!(rules[0].table.as_deref(), Some("ports"));
+        assert_eq!(rules[1].line_action, Action::Continue);
+        assert_eq!(rules[1].table.as_deref(), Some("ports"));
+        assert_eq!(rules[1].next_state.as_deref(), Some("Start"));
+        assert_eq!(ir.values["PORT"].tables, vec!["ports".to_string()]);
+
+        let kinds: Vec<&str> = warnings.iter().map(|w| w.kind.as_str()).collect();
+        assert_eq!(kinds, vec!["unknown_record_action", "unknown_table_field"]);
+    }
+
+    #[test]
+    fn test_values_keep_declaration_order() {
+        let input = r#"Value STATUS (\S+)
+Value INTERFACE (\S+)
+Value ADDRESS (\S+)
+
+Start
+  ^${INTERFACE} ${ADDRESS} ${STATUS} -> Record
+"#;
+        let ir = TextFsmLoader::parse_str(input).unwrap();
+        assert_eq!(ir.field_order, vec!["STATUS", "INTERFACE", "ADDRESS"]);
+    }
 }
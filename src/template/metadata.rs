@@ -9,6 +9,9 @@ pub struct TemplateMetadata {
     pub author: String,
     #[serde(default)]
     pub maintainer: Option<String>,
+    /// Default minimum field coverage percentage for parses using this template.
+    #[serde(default)]
+    pub coverage_threshold: Option<f64>,
 }
 
 /// Extract metadata from template content.
@@ -97,6 +100,9 @@ fn extract_from_textfsm_comments(content: &str) -> TemplateMetadata {
                 "version" => metadata.version = value,
                 "author" => metadata.author = value,
                 "maintainer" => metadata.maintainer = Some(value),
+                "coverage-threshold" | "coverage_threshold" => {
+                    metadata.coverage_threshold = value.parse().ok()
+                }
                 _ => {} // Ignore unknown metadata keys
             }
         }
@@ -113,6 +119,7 @@ fn default_metadata() -> TemplateMetadata {
         version: "1.0.0".to_string(),
         author: "Unknown".to_string(),
         maintainer: None,
+        coverage_threshold: None,
     }
 }
 
@@ -274,6 +281,37 @@ Start
         assert_eq!(metadata.compatibility, "Unknown");
     }
 
+    #[test]
+    fn test_coverage_threshold_extraction() {
+        let yaml = r#"
+version: 1
+metadata:
+  description: "Interfaces"
+  compatibility: "Cisco IOS"
+  version: "1.0.0"
+  author: "Network Team"
+  coverage_threshold: 60
+fields:
+  x:
+    type: string
+patterns:
+  - regex: '^(?P<x>\S+)$'
+    record: true
+"#;
+        let metadata = extract_metadata(yaml, TemplateFormat::Yaml);
+        assert_eq!(metadata.coverage_threshold, Some(60.0));
+
+        let textfsm = r#"# Description: Interfaces
+# Coverage-Threshold: 55.5
+
+Value X (\S+)
+Start
+  ^X ${X} -> Record
+"#;
+        let metadata = extract_metadata(textfsm, TemplateFormat::Textfsm);
+        assert_eq!(metadata.coverage_threshold, Some(55.5));
+    }
+
     #[test]
     fn test_textfsm_case_insensitive_keys() {
         let template = r#"# DESCRIPTION: Test template
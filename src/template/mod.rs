@@ -1,4 +1,5 @@
 pub mod convert;
+pub mod index;
 pub mod library;
 pub mod loader;
 pub mod metadata;
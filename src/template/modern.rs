@@ -1,5 +1,9 @@
 use crate::ScraperError;
-use crate::engine::types::{Action, FieldType, Rule, State, TemplateIR, Value};
+use crate::engine::types::{
+    Action, Column, FieldType, MetricDef, MetricKind, MetricsMapping, Rule, State, TemplateIR,
+    Value,
+};
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap, HashSet};
 
@@ -17,8 +21,13 @@ pub struct ModernTemplateDoc {
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub macros: HashMap<String, String>,
 
-    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
-    pub fields: BTreeMap<String, FieldDef>,
+    /// Declaration order is kept: it is the default output column order.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub fields: IndexMap<String, FieldDef>,
+
+    /// Output column order; fields not listed follow in declaration order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub columns: Vec<ColumnDef>,
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub states: Option<BTreeMap<String, Vec<StateRuleDef>>>,
@@ -26,8 +35,9 @@ pub struct ModernTemplateDoc {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub patterns: Option<Vec<PatternRuleDef>>,
 
-    /// Metadata section - parsed separately by metadata module, ignored by template loader
-    #[serde(default, skip_serializing)]
+    /// Metadata section - parsed separately by metadata module; the loader only reads
+    /// `coverage_threshold` and `metrics` from it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub metadata: Option<serde_json::Value>,
 }
 
@@ -48,6 +58,75 @@ pub struct FieldDef {
 
     #[serde(default, skip_serializing_if = "is_false")]
     pub list: bool,
+
+    /// Field may be legitimately absent; excluded from coverage accounting.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub optional: bool,
+
+    /// Named record tables the field belongs to; empty means the default table.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tables: Vec<String>,
+}
+
+/// `columns` entry: a field name, or `{ name, hidden }` to capture a field without emitting it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ColumnDef {
+    Name(String),
+    Spec(ColumnSpecDef),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColumnSpecDef {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub hidden: bool,
+}
+
+impl From<&ColumnDef> for Column {
+    fn from(def: &ColumnDef) -> Self {
+        match def {
+            ColumnDef::Name(name) => Column {
+                name: name.clone(),
+                hidden: false,
+            },
+            ColumnDef::Spec(spec) => Column {
+                name: spec.name.clone(),
+                hidden: spec.hidden,
+            },
+        }
+    }
+}
+
+/// `metadata.metrics`: which fields become OpenMetrics samples and which columns label them.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MetricsDef {
+    #[serde(default)]
+    pub labels: Vec<String>,
+    pub fields: Vec<MetricFieldDef>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MetricFieldDef {
+    pub field: String,
+    /// Metric family name; defaults to the field name.
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub r#type: MetricTypeDef,
+    #[serde(default)]
+    pub help: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MetricTypeDef {
+    #[default]
+    Gauge,
+    Counter,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
@@ -86,6 +165,10 @@ pub struct ActionDef {
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub next: Option<String>,
+
+    /// Named table receiving the record; only valid with `record: record`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub table: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
@@ -110,6 +193,10 @@ pub struct PatternRuleDef {
 
     #[serde(default, skip_serializing_if = "is_false")]
     pub record: bool,
+
+    /// Named table receiving the record; only valid with `record: true`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub table: Option<String>,
 }
 
 pub fn load_str(format: ModernFormat, input: &str) -> Result<TemplateIR, ScraperError> {
@@ -231,9 +318,139 @@ impl ModernTemplateDoc {
             }
         }
 
+        for (name, def) in &self.fields {
+            if def.required && def.optional {
+                return Err(ScraperError::Template(format!(
+                    "Field 'fields.{name}' cannot be both required and optional"
+                )));
+            }
+        }
+
+        if let Some(states) = &self.states {
+            for (state_name, rules) in states {
+                for rule in rules {
+                    let Some(action) = &rule.action else {
+                        continue;
+                    };
+                    if action.table.is_some() && action.record != Some(RecordActionDef::Record) {
+                        return Err(ScraperError::Template(format!(
+                            "Rule in state '{state_name}' names a table but does not record"
+                        )));
+                    }
+                }
+            }
+        }
+
+        if let Some(patterns) = &self.patterns {
+            for p in patterns {
+                if p.table.is_some() && !p.record {
+                    return Err(ScraperError::Template(format!(
+                        "Pattern '{}' names a table but does not record",
+                        p.regex
+                    )));
+                }
+            }
+        }
+
+        self.coverage_threshold()?;
+        self.metrics()?;
+
         Ok(())
     }
 
+    /// Read and validate `metadata.metrics`, if declared. Metric fields must be declared
+    /// scalar fields; labels may also name injected columns (`--set`, `--path-capture`).
+    fn metrics(&self) -> Result<Option<MetricsMapping>, ScraperError> {
+        let Some(raw) = self.metadata.as_ref().and_then(|m| m.get("metrics")) else {
+            return Ok(None);
+        };
+        let def: MetricsDef = serde_json::from_value(raw.clone())
+            .map_err(|e| ScraperError::Template(format!("Invalid 'metadata.metrics': {e}")))?;
+
+        for label in &def.labels {
+            if !is_label_name(label) {
+                return Err(ScraperError::Template(format!(
+                    "'metadata.metrics' label '{label}' is not a valid label name"
+                )));
+            }
+        }
+
+        let mut metrics: Vec<MetricDef> = Vec::new();
+        for field in &def.fields {
+            match self.fields.get(&field.field) {
+                None => {
+                    return Err(ScraperError::Template(format!(
+                        "'metadata.metrics' field '{}' is not a declared field",
+                        field.field
+                    )));
+                }
+                Some(f) if f.list => {
+                    return Err(ScraperError::Template(format!(
+                        "'metadata.metrics' field '{}' is a list field",
+                        field.field
+                    )));
+                }
+                Some(_) => {}
+            }
+            if def.labels.contains(&field.field) {
+                return Err(ScraperError::Template(format!(
+                    "'metadata.metrics' field '{}' is also a label",
+                    field.field
+                )));
+            }
+
+            let kind = match field.r#type {
+                MetricTypeDef::Gauge => MetricKind::Gauge,
+                MetricTypeDef::Counter => MetricKind::Counter,
+            };
+            let mut name = field.name.clone().unwrap_or_else(|| field.field.clone());
+            if kind == MetricKind::Counter
+                && let Some(family) = name.strip_suffix("_total")
+            {
+                name = family.to_string();
+            }
+            if !is_metric_name(&name) {
+                return Err(ScraperError::Template(format!(
+                    "'metadata.metrics' name '{name}' is not a valid metric name"
+                )));
+            }
+            if metrics.iter().any(|m| m.name == name) {
+                return Err(ScraperError::Template(format!(
+                    "'metadata.metrics' declares metric '{name}' more than once"
+                )));
+            }
+            metrics.push(MetricDef {
+                field: field.field.clone(),
+                name,
+                kind,
+                help: field.help.clone(),
+            });
+        }
+
+        Ok(Some(MetricsMapping {
+            labels: def.labels,
+            metrics,
+        }))
+    }
+
+    /// Read `metadata.coverage_threshold`, if declared.
+    fn coverage_threshold(&self) -> Result<Option<f64>, ScraperError> {
+        let Some(raw) = self
+            .metadata
+            .as_ref()
+            .and_then(|m| m.get("coverage_threshold"))
+        else {
+            return Ok(None);
+        };
+
+        match raw.as_f64() {
+            Some(t) if (0.0..=100.0).contains(&t) => Ok(Some(t)),
+            _ => Err(ScraperError::Template(format!(
+                "'metadata.coverage_threshold' must be a number between 0 and 100 (got {raw})"
+            ))),
+        }
+    }
+
     fn lower(&self) -> Result<TemplateIR, ScraperError> {
         let mut values = HashMap::new();
         for (name, def) in &self.fields {
@@ -251,6 +468,8 @@ impl ModernTemplateDoc {
                     required: def.required,
                     list: def.list,
                     type_hint: Some(hint),
+                    optional: def.optional,
+                    tables: def.tables.clone(),
                 },
             );
         }
@@ -282,6 +501,7 @@ impl ModernTemplateDoc {
                         Action::Next
                     },
                     next_state: None,
+                    table: p.table.clone(),
                 });
             }
             states.insert(
@@ -297,17 +517,39 @@ impl ModernTemplateDoc {
             values,
             states,
             macros: self.macros.clone(),
+            coverage_threshold: self.coverage_threshold()?,
+            field_order: self.fields.keys().cloned().collect(),
+            columns: self.columns.iter().map(Column::from).collect(),
+            metrics: self.metrics()?,
         })
     }
 }
 
+/// `[a-zA-Z_:][a-zA-Z0-9_:]*`
+fn is_metric_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == ':')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+}
+
+/// `[a-zA-Z_][a-zA-Z0-9_]*`
+fn is_label_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
 fn is_false(v: &bool) -> bool {
     !*v
 }
 
 impl StateRuleDef {
     fn lower(&self) -> Result<Rule, ScraperError> {
-        let (line_action, record_action, next_state) = match &self.action {
+        let (line_action, record_action, next_state, table) = match &self.action {
             Some(a) => {
                 let la = match a.line.unwrap_or(LineActionDef::Next) {
                     LineActionDef::Next => Action::Next,
@@ -318,9 +560,9 @@ impl StateRuleDef {
                     RecordActionDef::Record => Action::Record,
                     RecordActionDef::Clear => Action::Clear,
                 };
-                (la, ra, a.next.clone())
+                (la, ra, a.next.clone(), a.table.clone())
             }
-            None => (Action::Next, Action::Next, None),
+            None => (Action::Next, Action::Next, None, None),
         };
 
         Ok(Rule {
@@ -328,6 +570,7 @@ impl StateRuleDef {
             line_action,
             record_action,
             next_state,
+            table,
         })
     }
 }
@@ -586,4 +829,262 @@ record = true
         assert_eq!(results.len(), 1);
         assert_eq!(results[0]["hostname"], "Router1");
     }
+
+    #[test]
+    fn modern_optional_field_is_excluded_from_coverage() {
+        let doc = r#"
+version: 1
+metadata:
+  coverage_threshold: 90
+fields:
+  iface:
+    type: string
+  description:
+    type: string
+    optional: true
+patterns:
+  - regex: '^Interface (?P<iface>\S+)(?: desc (?P<description>\S+))?$'
+    record: true
+"#;
+
+        let ir = load_yaml_str(doc).unwrap();
+        assert!(ir.values["description"].optional);
+        assert_eq!(ir.coverage_threshold, Some(90.0));
+
+        let template = Template::from_ir(ir).unwrap();
+        assert_eq!(template.coverage_fields(), vec!["iface".to_string()]);
+
+        let (results, warnings) = template
+            .parse_internal(
+                "Interface Gi0/1\nInterface Gi0/2 desc uplink",
+                None,
+                crate::ParseOptions::default(),
+            )
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(warnings.is_empty(), "{warnings:?}");
+    }
+
+    #[test]
+    fn modern_rejects_required_and_optional_field() {
+        let doc = r#"
+version = 1
+
+[fields]
+ip = { type = "string", required = true, optional = true }
+
+[[patterns]]
+regex = '^IP (?P<ip>\S+)$'
+record = true
+"#;
+
+        let err = load_toml_str(doc).unwrap_err();
+        assert!(
+            err.to_string().contains("both required and optional"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn modern_rejects_out_of_range_coverage_threshold() {
+        let doc = r#"
+version = 1
+
+[metadata]
+coverage_threshold = 150
+
+[fields]
+ip = { type = "string" }
+
+[[patterns]]
+regex = '^IP (?P<ip>\S+)$'
+record = true
+"#;
+
+        let err = load_toml_str(doc).unwrap_err();
+        assert!(
+            err.to_string().contains("metadata.coverage_threshold"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn modern_yaml_records_into_named_tables() {
+        let doc = r#"
+version: 1
+fields:
+  chassis: { pattern: '\S+', filldown: true, tables: [chassis, ports] }
+  port: { pattern: '\S+', tables: [ports] }
+states:
+  Start:
+    - regex: '^Chassis ${chassis}'
+      action: { record: record, table: chassis }
+    - regex: '^Port ${port}'
+      action: { record: record, table: ports }
+"#;
+
+        let template = Template::from_ir(load_yaml_str(doc).unwrap()).unwrap();
+        let (tables, _) = template
+            .parse_tables_internal(
+                "Chassis sw1\nPort Gi0/1",
+                None,
+                crate::ParseOptions::default(),
+            )
+            .unwrap();
+        assert_eq!(tables["chassis"].len(), 1);
+        assert_eq!(tables["ports"][0]["chassis"], "sw1");
+        assert_eq!(tables["ports"][0]["port"], "Gi0/1");
+    }
+
+    #[test]
+    fn modern_rejects_table_without_record() {
+        let doc = r#"
+version = 1
+
+[fields]
+ip = { type = "string", tables = ["addrs"] }
+
+[[patterns]]
+regex = '^IP (?P<ip>\S+)$'
+table = "addrs"
+"#;
+
+        let err = load_toml_str(doc).unwrap_err();
+        assert!(err.to_string().contains("does not record"), "{err}");
+    }
+
+    #[test]
+    fn modern_toml_columns_order_and_hide_fields() {
+        let doc = r#"
+version = 1
+columns = ["port", { name = "raw", hidden = true }]
+
+[fields]
+status = { pattern = '\S+' }
+raw = { pattern = '.+' }
+port = { pattern = '\S+' }
+
+[[patterns]]
+regex = '^${port} ${status} ${raw}'
+record = true
+"#;
+
+        let ir = load_toml_str(doc).unwrap();
+        assert_eq!(ir.field_order, vec!["status", "raw", "port"]);
+
+        let template = Template::from_ir(ir).unwrap();
+        assert_eq!(template.columns, vec!["port", "status"]);
+
+        let results = template.parse("Gi0/1 up line protocol").unwrap();
+        assert_eq!(results[0]["port"], "Gi0/1");
+        assert!(!results[0].contains_key("raw"));
+    }
+
+    #[test]
+    fn modern_hidden_fields_stay_hidden_when_parsing_ends_early() {
+        let doc = r#"
+version = 1
+columns = ["port", { name = "raw", hidden = true }]
+
+[fields]
+port = { pattern = '\S+' }
+raw = { pattern = '.+' }
+
+[[states.Start]]
+regex = '^${port} ${raw}$'
+action = { record = "record" }
+
+[[states.Start]]
+regex = '^stop$'
+action = { next = "End" }
+"#;
+
+        let template = Template::from_ir(load_toml_str(doc).unwrap()).unwrap();
+        for input in ["Gi0/1 up", "Gi0/1 up\nstop\nGi0/2 down"] {
+            let results = template.parse(input).unwrap();
+            assert_eq!(results.len(), 1, "{input:?}");
+            assert!(
+                !results[0].contains_key("raw"),
+                "{input:?}: {:?}",
+                results[0]
+            );
+        }
+    }
+
+    #[test]
+    fn modern_rejects_columns_naming_undefined_fields() {
+        let doc = r#"
+version: 1
+columns: [port, speed]
+fields:
+  port: { pattern: '\S+' }
+patterns:
+  - regex: '^${port}'
+    record: true
+"#;
+
+        let err = Template::from_ir(load_yaml_str(doc).unwrap()).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Column 'speed' is not a declared field"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn modern_yaml_metrics_mapping_defaults_and_counter_names() {
+        let doc = r#"
+version: 1
+metadata:
+  metrics:
+    labels: [port, site]
+    fields:
+      - { field: mtu, help: Configured MTU }
+      - { field: in_octets, name: port_in_octets_total, type: counter }
+fields:
+  port: { pattern: '\S+' }
+  mtu: { type: int, pattern: '\d+' }
+  in_octets: { type: int, pattern: '\d+' }
+patterns:
+  - regex: '^${port} ${mtu} ${in_octets}'
+    record: true
+"#;
+
+        let metrics = load_yaml_str(doc).unwrap().metrics.unwrap();
+        assert_eq!(metrics.labels, ["port", "site"]);
+        assert_eq!(metrics.metrics[0].name, "mtu");
+        assert_eq!(metrics.metrics[0].kind, MetricKind::Gauge);
+        assert_eq!(metrics.metrics[0].help.as_deref(), Some("Configured MTU"));
+        assert_eq!(metrics.metrics[1].name, "port_in_octets");
+        assert_eq!(metrics.metrics[1].kind, MetricKind::Counter);
+    }
+
+    #[test]
+    fn modern_rejects_metrics_on_undeclared_or_list_fields() {
+        let doc = |field: &str| {
+            format!(
+                r#"
+version: 1
+metadata:
+  metrics:
+    fields:
+      - {{ field: {field} }}
+fields:
+  port: {{ pattern: '\S+' }}
+  vlans: {{ pattern: '\d+', list: true }}
+patterns:
+  - regex: '^${{port}} ${{vlans}}'
+    record: true
+"#
+            )
+        };
+
+        let err = load_yaml_str(&doc("speed")).unwrap_err();
+        assert!(
+            err.to_string().contains("'speed' is not a declared field"),
+            "{err}"
+        );
+        let err = load_yaml_str(&doc("vlans")).unwrap_err();
+        assert!(err.to_string().contains("'vlans' is a list field"), "{err}");
+    }
 }
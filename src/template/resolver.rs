@@ -12,13 +12,23 @@
 //!
 //! Template names are validated BEFORE any filesystem operations to prevent path traversal attacks.
 //! Only alphanumeric characters, underscores, hyphens, and dots are allowed.
+//!
+//! # Command index
+//!
+//! `index` files (ntc-templates format, see [`super::index`]) in the same locations map a
+//! platform and command to a template name, which then resolves as above. Entries from
+//! higher-priority index files are tried first.
 
 use rust_embed::EmbeddedFile;
 use std::path::PathBuf;
 use xdg::BaseDirectories;
 
+use super::index::TemplateIndex;
 use super::library;
 
+/// File name of the command index in each template location
+pub const INDEX_FILE: &str = "index";
+
 /// Template source location
 pub enum TemplateSource {
     /// Template from XDG user directory
@@ -230,6 +240,102 @@ impl TemplateResolver {
             template_name
         ))
     }
+
+    /// Load and merge every command index
+    ///
+    /// Precedence follows template resolution: `$XDG_DATA_HOME`, then `$XDG_DATA_DIRS`,
+    /// then the embedded index.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(TemplateIndex)` with entries ordered by precedence (empty if no index exists)
+    /// - `Err(String)` if an index file cannot be read or parsed
+    pub fn load_index(&self) -> Result<TemplateIndex, String> {
+        let mut index = TemplateIndex::default();
+
+        // find_data_files yields the lowest priority first
+        let user_files: Vec<PathBuf> = self
+            .xdg
+            .find_data_files(format!("templates/{}", INDEX_FILE))
+            .collect();
+        for path in user_files.into_iter().rev() {
+            let content = std::fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read index {}: {}", path.display(), e))?;
+            let parsed = TemplateIndex::parse(&content)
+                .map_err(|e| format!("Invalid index {}: {}", path.display(), e))?;
+            index.extend(parsed);
+        }
+
+        if let Some(embedded) = library::get_embedded(INDEX_FILE) {
+            let content = String::from_utf8_lossy(&embedded.data);
+            let parsed = TemplateIndex::parse(&content)
+                .map_err(|e| format!("Invalid embedded index: {}", e))?;
+            index.extend(parsed);
+        }
+
+        Ok(index)
+    }
+
+    /// Find the template name the command index maps `platform` and `command` to
+    ///
+    /// When an entry lists several templates, the first one is used.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(String)` with the template name (resolve it with [`TemplateResolver::resolve`])
+    /// - `Err(String)` if no index entry matches
+    pub fn resolve_command(&self, platform: &str, command: &str) -> Result<String, String> {
+        let index = self.load_index()?;
+        let entry = index.lookup(Some(platform), command, None).ok_or_else(|| {
+            format!(
+                "No template index entry matches platform '{}' and command '{}'",
+                platform, command
+            )
+        })?;
+        let name = entry.templates.first().cloned().ok_or_else(|| {
+            format!(
+                "Template index entry on line {} names no template",
+                entry.line
+            )
+        })?;
+
+        tracing::info!(
+            target: "cliscrape::template",
+            event = "template_index_match",
+            platform,
+            command,
+            name = %name,
+            line = entry.line
+        );
+        Ok(name)
+    }
+
+    /// List every template name visible to [`TemplateResolver::resolve`]
+    ///
+    /// Covers templates directly under the user and system template directories and the
+    /// embedded templates, without duplicates (a user template shadows the embedded one).
+    ///
+    /// # Returns
+    ///
+    /// Template names sorted alphabetically
+    pub fn list_templates(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .xdg
+            .list_data_files_once("templates")
+            .into_iter()
+            .filter(|path| {
+                matches!(
+                    path.extension().and_then(|e| e.to_str()),
+                    Some("textfsm" | "yaml" | "yml" | "toml")
+                )
+            })
+            .filter_map(|path| Some(path.file_name()?.to_string_lossy().into_owned()))
+            .chain(library::list_embedded())
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,395 @@
+//! Prompt dialects: how each vendor's CLI prompt looks in a captured session.
+//!
+//! A dialect recognises prompt lines (splitting off the echoed command) and, optionally,
+//! context lines the CLI prints around prompts (Junos `[edit]`, SR OS MD-CLI `[/]`) that are
+//! not command output. [`detect`] picks the dialect whose prompts best explain a capture.
+
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// A recognised prompt line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromptLine<'a> {
+    /// Stable part of the prompt (hostname, `user@host`), used to tell real prompts from
+    /// output lines that merely look like one.
+    pub base: &'a str,
+    /// Device hostname shown in the prompt.
+    pub hostname: &'a str,
+    /// CLI context shown in the prompt (`config-if`, `config>router`, a VDOM), if any.
+    pub mode: Option<&'a str>,
+    /// Command echoed after the prompt, if any.
+    pub cmd: Option<&'a str>,
+}
+
+/// A vendor CLI prompt style.
+pub trait PromptDialect: Sync {
+    /// Name accepted by `--transcript-dialect` and reported in logs.
+    fn name(&self) -> &'static str;
+
+    /// Parse `line` as a prompt, with or without a command echo.
+    fn parse_prompt<'a>(&self, line: &'a str) -> Option<PromptLine<'a>>;
+
+    /// Whether `line` is prompt context printed by the CLI rather than command output.
+    fn is_context_line(&self, _line: &str) -> bool {
+        false
+    }
+}
+
+/// Parse a prompt with `re`, whose `base` group is the stable prompt part; optional groups
+/// are `host` (defaults to `base`), `mode` and the `cmd` echo.
+fn parse_with<'a>(re: &Regex, line: &'a str) -> Option<PromptLine<'a>> {
+    let caps = re.captures(line)?;
+    let base = caps.name("base")?.as_str();
+    Some(PromptLine {
+        base,
+        hostname: caps.name("host").map_or(base, |m| m.as_str()),
+        mode: caps
+            .name("mode")
+            .map(|m| m.as_str())
+            .filter(|s| !s.is_empty()),
+        cmd: command(caps.name("cmd")),
+    })
+}
+
+/// A command echo, unless blank.
+fn command(echo: Option<regex::Match<'_>>) -> Option<&str> {
+    echo.map(|m| m.as_str()).filter(|s| !s.trim().is_empty())
+}
+
+macro_rules! regex {
+    ($pattern:expr) => {{
+        static RE: OnceLock<Regex> = OnceLock::new();
+        RE.get_or_init(|| Regex::new($pattern).expect("prompt regex must compile"))
+    }};
+}
+
+/// Cisco IOS and lookalikes: `host#`, `host>`, `host(config-if)#`.
+pub struct Ios;
+
+impl PromptDialect for Ios {
+    fn name(&self) -> &'static str {
+        "ios"
+    }
+
+    fn parse_prompt<'a>(&self, line: &'a str) -> Option<PromptLine<'a>> {
+        // Hostname-ish token, optional mode parens, then # or > and an optional command echo.
+        parse_with(
+            regex!(
+                r"^(?P<base>[A-Za-z0-9_.:-]+)(?:\((?P<mode>[^\r\n\)]*)\))?[#>](?:[ \t]+(?P<cmd>.*))?$"
+            ),
+            line,
+        )
+    }
+}
+
+/// Juniper Junos: `user@host>`, `user@host#`, with `[edit ...]` and `{master:0}` lines.
+pub struct Junos;
+
+impl PromptDialect for Junos {
+    fn name(&self) -> &'static str {
+        "junos"
+    }
+
+    fn parse_prompt<'a>(&self, line: &'a str) -> Option<PromptLine<'a>> {
+        parse_with(
+            regex!(
+                r"^(?P<base>[A-Za-z0-9_.-]+@(?P<host>[A-Za-z0-9_.:-]+))[>#%](?:[ \t]+(?P<cmd>.*))?$"
+            ),
+            line,
+        )
+    }
+
+    fn is_context_line(&self, line: &str) -> bool {
+        regex!(r"^(?:\[edit(?: [^\]]*)?\]|\{[A-Za-z0-9_:-]+\})$").is_match(line.trim())
+    }
+}
+
+/// Huawei VRP and H3C Comware: `<host>` in user view, `[host]` or `[~host-view]` in system
+/// view; commands follow the prompt without a space. Lines with text after a space (`[INFO]
+/// link up`) or closing an XML element (`<name>GE1/0/1</name>`) are output, not prompts.
+pub struct Huawei;
+
+impl PromptDialect for Huawei {
+    fn name(&self) -> &'static str {
+        "huawei"
+    }
+
+    fn parse_prompt<'a>(&self, line: &'a str) -> Option<PromptLine<'a>> {
+        let caps = regex!(
+            r"^(?:<(?P<user>[A-Za-z][^<>\s]*)>|\[[~*]?(?P<system>[A-Za-z][^\[\]\s@]*)\])(?P<cmd>[A-Za-z].*?)?[ \t]*$"
+        )
+        .captures(line)?;
+        if caps
+            .name("cmd")
+            .is_some_and(|cmd| cmd.as_str().contains("</"))
+        {
+            return None;
+        }
+        let base = caps.name("user").or_else(|| caps.name("system"))?.as_str();
+        Some(PromptLine {
+            base,
+            hostname: base,
+            mode: None,
+            cmd: command(caps.name("cmd")),
+        })
+    }
+}
+
+/// MikroTik RouterOS: `[admin@host] >`, `[admin@host] /ip address>`.
+pub struct Mikrotik;
+
+impl PromptDialect for Mikrotik {
+    fn name(&self) -> &'static str {
+        "mikrotik"
+    }
+
+    fn parse_prompt<'a>(&self, line: &'a str) -> Option<PromptLine<'a>> {
+        parse_with(
+            regex!(
+                r"^\[(?P<base>[^\[\]@\s]+@(?P<host>[^\[\]\s]+))\] (?:/(?P<mode>[^>]*))?>(?:[ \t]+(?P<cmd>.*))?$"
+            ),
+            line,
+        )
+    }
+}
+
+/// Nokia SR OS: classic `A:host#`, `*A:host>config>router#`; MD-CLI `A:admin@host#` with
+/// `[/]` or `(ex)[/configure]` context lines.
+pub struct Sros;
+
+impl PromptDialect for Sros {
+    fn name(&self) -> &'static str {
+        "sros"
+    }
+
+    fn parse_prompt<'a>(&self, line: &'a str) -> Option<PromptLine<'a>> {
+        parse_with(
+            regex!(
+                r"^\*?(?P<base>[AB]:(?:[A-Za-z0-9_.-]+@)?(?P<host>[A-Za-z0-9_.-]+))(?:>(?P<mode>[A-Za-z0-9_>-]*))?#(?:[ \t]+(?P<cmd>.*))?$"
+            ),
+            line,
+        )
+    }
+
+    fn is_context_line(&self, line: &str) -> bool {
+        regex!(r"^\*?(?:\([a-z]{2}\))?\[(?:[a-z]{2}:)?/[^\]]*\]$").is_match(line.trim())
+    }
+}
+
+/// POSIX shells: `user@host:~$`, `root@host:/etc#`, `[user@host dir]$`.
+pub struct Linux;
+
+impl PromptDialect for Linux {
+    fn name(&self) -> &'static str {
+        "linux"
+    }
+
+    fn parse_prompt<'a>(&self, line: &'a str) -> Option<PromptLine<'a>> {
+        let caps = regex!(
+            r"^(?:\[(?P<bracketed>[\w.-]+@(?P<bracketed_host>[\w.-]+))(?: [^\]]*)?\]|(?P<plain>[\w.-]+@(?P<plain_host>[\w.-]+))(?::[^\s$#]*)?)[$#](?:[ \t]+(?P<cmd>.*))?$"
+        )
+        .captures(line)?;
+        let base = caps
+            .name("bracketed")
+            .or_else(|| caps.name("plain"))?
+            .as_str();
+        let hostname = caps
+            .name("bracketed_host")
+            .or_else(|| caps.name("plain_host"))?
+            .as_str();
+        Some(PromptLine {
+            base,
+            hostname,
+            mode: None,
+            cmd: command(caps.name("cmd")),
+        })
+    }
+}
+
+/// Fortinet FortiOS: `host # `, `host (vdom) # `, `host $ ` (space before the sigil).
+pub struct Fortios;
+
+impl PromptDialect for Fortios {
+    fn name(&self) -> &'static str {
+        "fortios"
+    }
+
+    fn parse_prompt<'a>(&self, line: &'a str) -> Option<PromptLine<'a>> {
+        parse_with(
+            regex!(
+                r"^(?P<base>[A-Za-z0-9_.-]+)(?: \((?P<mode>[^)]*)\))? [#$](?:[ \t]+(?P<cmd>.*))?$"
+            ),
+            line,
+        )
+    }
+}
+
+/// Every dialect, in detection tie-break order.
+pub fn dialects() -> &'static [&'static dyn PromptDialect] {
+    &[&Ios, &Junos, &Huawei, &Mikrotik, &Sros, &Linux, &Fortios]
+}
+
+/// Pick the dialect that best explains `raw`.
+///
+/// A dialect scores the prompt lines whose base repeats, plus its context lines; the first
+/// line counts on its own when it is a prompt with a command echo. Returns `None` when no
+/// dialect scores.
+pub fn detect(raw: &str) -> Option<&'static dyn PromptDialect> {
+    let mut best: Option<(&'static dyn PromptDialect, usize)> = None;
+    for &dialect in dialects() {
+        let score = score(dialect, raw);
+        if score > 0 && best.is_none_or(|(_, top)| score > top) {
+            best = Some((dialect, score));
+        }
+    }
+    best.map(|(dialect, _)| dialect)
+}
+
+fn score(dialect: &dyn PromptDialect, raw: &str) -> usize {
+    let mut base_counts: HashMap<&str, usize> = HashMap::new();
+    let mut context = 0;
+    let mut first_line_is_echo = false;
+    for (idx, line) in raw.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if let Some(p) = dialect.parse_prompt(line) {
+            *base_counts.entry(p.base).or_insert(0) += 1;
+            if idx == 0 && p.cmd.is_some() {
+                first_line_is_echo = true;
+            }
+        } else if dialect.is_context_line(line) {
+            context += 1;
+        }
+    }
+    let repeated: usize = base_counts.values().filter(|&&n| n >= 2).sum();
+    match (repeated, first_line_is_echo) {
+        (0, false) => 0,
+        (0, true) => 1 + context,
+        (n, _) => n + context,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prompt(dialect: &dyn PromptDialect, line: &str) -> Option<(String, Option<String>)> {
+        dialect
+            .parse_prompt(line)
+            .map(|p| (p.base.to_string(), p.cmd.map(str::to_string)))
+    }
+
+    fn some(base: &str, cmd: Option<&str>) -> Option<(String, Option<String>)> {
+        Some((base.to_string(), cmd.map(str::to_string)))
+    }
+
+    #[test]
+    fn parses_vendor_prompts() {
+        assert_eq!(
+            prompt(&Junos, "admin@mx1> show interfaces terse"),
+            some("admin@mx1", Some("show interfaces terse"))
+        );
+        assert_eq!(prompt(&Junos, "admin@mx1#"), some("admin@mx1", None));
+        assert_eq!(
+            prompt(&Huawei, "<CE1>display version"),
+            some("CE1", Some("display version"))
+        );
+        assert_eq!(prompt(&Huawei, "[~CE1-GE1/0/1]"), some("CE1-GE1/0/1", None));
+        assert_eq!(prompt(&Huawei, "<CE1>  "), some("CE1", None));
+        assert_eq!(
+            prompt(&Mikrotik, "[admin@MikroTik] /ip address> print"),
+            some("admin@MikroTik", Some("print"))
+        );
+        assert_eq!(
+            prompt(&Sros, "*A:pe1>config>router# show router interface"),
+            some("A:pe1", Some("show router interface"))
+        );
+        assert_eq!(
+            prompt(&Sros, "A:admin@pe1# show version"),
+            some("A:admin@pe1", Some("show version"))
+        );
+        assert_eq!(
+            prompt(&Linux, "root@web1:/etc# ip -br addr"),
+            some("root@web1", Some("ip -br addr"))
+        );
+        assert_eq!(
+            prompt(&Linux, "[ops@web1 ~]$ uptime"),
+            some("ops@web1", Some("uptime"))
+        );
+        assert_eq!(
+            prompt(&Fortios, "FGT60E (root) # get system status"),
+            some("FGT60E", Some("get system status"))
+        );
+        assert_eq!(prompt(&Fortios, "FGT60E# get system status"), None);
+    }
+
+    #[test]
+    fn huawei_rejects_xml_and_log_lines() {
+        for line in [
+            r#"<rpc-reply message-id="101" xmlns="urn:ietf:params:xml:ns:netconf:base:1.0">"#,
+            "<name>GE1/0/1</name>",
+            "<ifName>10GE1/0/1</ifName>",
+            "</rpc-reply>",
+            "[INFO] GE1/0/1 link up",
+            "[INFO]: GE1/0/1 link up",
+            "[2024-01-01 10:00:00] link down",
+            "[edit interfaces ge-0/0/0]",
+            "<CE1> display version",
+        ] {
+            assert_eq!(prompt(&Huawei, line), None, "{line}");
+        }
+
+        let netconf = concat!(
+            "<rpc-reply>\n",
+            "<data><ifName>GE1/0/1</ifName></data>\n",
+            "<data><ifName>GE1/0/2</ifName></data>\n",
+            "</rpc-reply>\n",
+        );
+        assert!(detect(netconf).is_none());
+    }
+
+    #[test]
+    fn reports_prompt_hostname_and_mode() {
+        let p = Ios
+            .parse_prompt("r1(config-if)# description uplink")
+            .unwrap();
+        assert_eq!((p.hostname, p.mode), ("r1", Some("config-if")));
+        let p = Junos.parse_prompt("admin@mx1> show version").unwrap();
+        assert_eq!((p.hostname, p.mode), ("mx1", None));
+        let p = Sros
+            .parse_prompt("*A:admin@pe1>config>router# info")
+            .unwrap();
+        assert_eq!((p.base, p.hostname), ("A:admin@pe1", "pe1"));
+        assert_eq!(p.mode, Some("config>router"));
+        let p = Mikrotik
+            .parse_prompt("[admin@MikroTik] /ip address> print")
+            .unwrap();
+        assert_eq!((p.hostname, p.mode), ("MikroTik", Some("ip address")));
+        let p = Linux.parse_prompt("[ops@web1 ~]$ uptime").unwrap();
+        assert_eq!(p.hostname, "web1");
+        let p = Fortios
+            .parse_prompt("FGT60E (root) # get system status")
+            .unwrap();
+        assert_eq!((p.hostname, p.mode), ("FGT60E", Some("root")));
+    }
+
+    #[test]
+    fn detects_dialect_from_repeated_prompts_and_context_lines() {
+        let junos = "admin@mx1> show version\nJunos: 21.4R3\n\n[edit]\nadmin@mx1# \n";
+        assert_eq!(detect(junos).map(|d| d.name()), Some("junos"));
+
+        let sros = "[/]\nA:admin@pe1# show version\nTiMOS-B-22.10.R1\n\n[/]\nA:admin@pe1#\n";
+        assert_eq!(detect(sros).map(|d| d.name()), Some("sros"));
+
+        let huawei = "<CE1>display clock\n2024-01-01 10:00:00\n<CE1>\n";
+        assert_eq!(detect(huawei).map(|d| d.name()), Some("huawei"));
+
+        let fortios = "FGT60E # get system status\nVersion: FortiGate-60E v7.2.5\nFGT60E # \n";
+        assert_eq!(detect(fortios).map(|d| d.name()), Some("fortios"));
+
+        let ios = "r1# show clock\n10:00:00 UTC\nr1#\n";
+        assert_eq!(detect(ios).map(|d| d.name()), Some("ios"));
+
+        assert!(detect("just some output\nwith no prompts\n").is_none());
+    }
+}
@@ -1,29 +1,89 @@
-pub mod ios_prompt;
+pub mod dialect;
+pub mod noise;
+pub mod segment;
+pub mod session;
+pub mod showtech;
 
-/// Preprocess raw CLI input that may include Cisco IOS-style prompts and command echoes.
-///
-/// Returns one or more per-command output blocks. When confidence is low, returns the original
-/// input as a single block.
-pub fn preprocess_ios_transcript(raw: &str) -> Vec<String> {
-    let (blocks, _warnings) = preprocess_ios_transcript_with_warnings(raw);
-    blocks
+pub use dialect::PromptDialect;
+pub use noise::CleanupOptions;
+pub use segment::TranscriptBlock;
+pub use session::SessionFormat;
+
+use std::path::Path;
+
+/// How raw transcripts are cleaned and split into blocks.
+#[derive(Clone, Copy, Default)]
+pub struct TranscriptOptions {
+    /// Prompt dialect; `None` detects it (see [`dialect::detect`]).
+    pub dialect: Option<&'static dyn PromptDialect>,
+    pub cleanup: CleanupOptions,
+    /// Session log wrapping to decode first (see [`session::detect`]).
+    pub input_format: SessionFormat,
+}
+
+impl TranscriptOptions {
+    /// These options for the input at `path`, whose name may settle the session format.
+    pub fn for_source(&self, path: Option<&Path>) -> Self {
+        Self {
+            input_format: self.input_format.for_path(path),
+            ..*self
+        }
+    }
 }
 
-/// Preprocess raw CLI input, returning blocks plus any warnings (e.g., ANSI stripping).
-pub fn preprocess_ios_transcript_with_warnings(raw: &str) -> (Vec<String>, Vec<String>) {
+/// Preprocess raw CLI input that may include session log wrapping, vendor prompts and
+/// command echoes, returning blocks plus any warnings (decoding, ANSI stripping, each
+/// terminal-noise cleanup).
+///
+/// Input no dialect recognises is one block; `show tech-support` output is further split at
+/// its section banners (see [`showtech`]). Block line ranges refer to `raw`, including lines
+/// cleanup removed.
+pub fn preprocess_transcript_with_warnings(
+    raw: &str,
+    options: &TranscriptOptions,
+) -> (Vec<TranscriptBlock>, Vec<String>) {
     let mut warnings = Vec::new();
+    let raw = session::decode(raw, options.input_format, &mut warnings);
+    let raw = raw.as_ref();
+    let cleaned = if options.cleanup.terminal {
+        strip_ansi(&noise::emulate_terminal(raw, &mut warnings), &mut warnings)
+    } else {
+        strip_ansi(raw, &mut warnings)
+    };
+    let (cleaned, removed) = noise::remove_noise_lines(&cleaned, &options.cleanup, &mut warnings);
+    let mut blocks = match options.dialect.or_else(|| dialect::detect(&cleaned)) {
+        Some(dialect) => {
+            tracing::debug!(
+                target: "cliscrape::transcript",
+                event = "transcript_dialect",
+                dialect = dialect.name()
+            );
+            segment::segment_transcript(&cleaned, dialect)
+        }
+        None => vec![TranscriptBlock::whole(&cleaned)],
+    };
+    if !removed.is_empty() {
+        for block in &mut blocks {
+            block.restore_removed_lines(&removed);
+        }
+    }
+    let blocks = blocks
+        .into_iter()
+        .flat_map(showtech::split_sections)
+        .collect();
+    (blocks, warnings)
+}
 
+fn strip_ansi(raw: &str, warnings: &mut Vec<String>) -> String {
     // Strip ANSI escape sequences before processing
     let clean_bytes = strip_ansi_escapes::strip(raw.as_bytes());
-    let cleaned = String::from_utf8_lossy(&clean_bytes);
+    let cleaned = String::from_utf8_lossy(&clean_bytes).into_owned();
 
     // Check if ANSI stripping removed anything
     if cleaned.len() < raw.len() || cleaned.as_bytes() != raw.as_bytes() {
         warnings.push("ANSI escape sequences were stripped from input".to_string());
     }
-
-    let blocks = ios_prompt::preprocess_ios_transcript(&cleaned);
-    (blocks, warnings)
+    cleaned
 }
 
 #[cfg(test)]
@@ -33,10 +93,15 @@ mod tests {
     #[test]
     fn strips_ansi_escape_sequences_and_warns() {
         let input = "Host# show \x1b[31mcolored\x1b[0m output\nsome data";
-        let (blocks, warnings) = preprocess_ios_transcript_with_warnings(input);
+        let (blocks, warnings) =
+            preprocess_transcript_with_warnings(input, &TranscriptOptions::default());
 
         // Should not contain ANSI codes
-        let combined = blocks.join("\n");
+        let combined = blocks
+            .iter()
+            .map(|block| block.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
         assert!(!combined.contains("\x1b["));
         assert!(!combined.contains("\x1b"));
 
@@ -45,10 +110,43 @@ mod tests {
         assert!(warnings[0].contains("ANSI"));
     }
 
+    #[test]
+    fn block_lines_count_lines_removed_by_noise_cleanup() {
+        let input = concat!(
+            "r1# show ports\n",
+            "Port Gi0/1 mtu 1500\n",
+            " --More-- \n",
+            "%LINK-3-UPDOWN: Interface Gi0/2, changed state to down\n",
+            "Port Gi0/2 mtu 9000\n",
+            "r1# show clock\n",
+            "10:00:00 UTC\n",
+            "r1#\n",
+        );
+        let options = TranscriptOptions {
+            cleanup: CleanupOptions {
+                syslog: true,
+                ..CleanupOptions::default()
+            },
+            ..TranscriptOptions::default()
+        };
+        let (blocks, warnings) = preprocess_transcript_with_warnings(input, &options);
+
+        assert_eq!(blocks[0].text, "Port Gi0/1 mtu 1500\nPort Gi0/2 mtu 9000");
+        assert_eq!((blocks[0].start_line, blocks[0].end_line), (2, 5));
+        assert_eq!(blocks[0].removed_lines, [3, 4]);
+        assert_eq!(blocks[0].original_line_idx(1), 4);
+        assert_eq!((blocks[1].start_line, blocks[1].end_line), (7, 7));
+        assert_eq!(
+            warnings,
+            ["Removed 1 pager prompt(s)", "Removed 1 syslog line(s)"]
+        );
+    }
+
     #[test]
     fn no_warning_when_no_ansi_sequences() {
         let input = "Host# show plain output\nsome data";
-        let (_blocks, warnings) = preprocess_ios_transcript_with_warnings(input);
+        let (_blocks, warnings) =
+            preprocess_transcript_with_warnings(input, &TranscriptOptions::default());
 
         // No ANSI codes, so no warnings
         assert!(warnings.is_empty());
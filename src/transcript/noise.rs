@@ -0,0 +1,306 @@
+//! Terminal-noise cleanup for captured sessions.
+//!
+//! Session logs record what the terminal was sent, not what it showed: pagers draw
+//! `--More--` and then erase it with backspaces or `\r` redraws, bells ring, and devices
+//! interleave syslog messages with command output. Cleanup runs in two passes around ANSI
+//! stripping (which discards the control characters the first pass needs):
+//!
+//! 1. [`emulate_terminal`] replays backspace, `\r` and erase-line sequences on each line and
+//!    drops bell characters.
+//! 2. [`remove_noise_lines`] removes vendor pager markers and, optionally, syslog lines,
+//!    returning which transcript lines it dropped so block line numbers stay exact.
+
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// Which cleanup stages run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CleanupOptions {
+    /// Replay backspace/carriage-return overwrites and drop bell characters.
+    pub terminal: bool,
+    /// Remove pager markers (`--More--`, `---(more)---`, `---- More ----`, ...).
+    pub pager: bool,
+    /// Remove syslog lines (`%LINK-3-UPDOWN: ...`) interleaved with command output.
+    pub syslog: bool,
+}
+
+impl Default for CleanupOptions {
+    fn default() -> Self {
+        Self {
+            terminal: true,
+            pager: true,
+            syslog: false,
+        }
+    }
+}
+
+impl CleanupOptions {
+    /// No cleanup beyond ANSI stripping.
+    pub fn none() -> Self {
+        Self {
+            terminal: false,
+            pager: false,
+            syslog: false,
+        }
+    }
+}
+
+/// Pager prompts by vendor, anchored at the start of a line (after overwrites are replayed).
+fn pager_marker() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        let markers = [
+            // Cisco IOS/NX-OS, Arista, FortiOS: --More--, --More--(45%); Huawei and HPE:
+            // ---- More ----, -- MORE --; Brocade and others: <--- More --->
+            r"<?-{2,}\s*(?i:more)\s*-{2,}>?(?:\(\d+%\))?",
+            // Junos: ---(more)---, ---(more 45%)---
+            r"-{3}\(more(?: \d+%)?\)-{3}",
+            // Nokia SR OS classic CLI
+            r"Press any key to continue \(Q to quit\)",
+            // MikroTik RouterOS
+            r"-- \[Q quit\|D dump\|(?:up\|)?down\]",
+        ];
+        Regex::new(&format!(r"^\s*(?:{})\s*", markers.join("|"))).expect("pager regex must compile")
+    })
+}
+
+/// Syslog messages: Cisco-style `%FACILITY-SEVERITY-MNEMONIC:` and Huawei-style
+/// `%%01IFNET/4/LINK_STATE(l)[0]:`, optionally preceded by sequence numbers and timestamps.
+fn syslog_line() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r"(?:^|\s)(?:%[A-Z][A-Z0-9_]*(?:-[A-Z0-9_]+)?-[0-7]-[A-Z0-9_]+|%%\d+[A-Z0-9_]+/[0-7]/[A-Z0-9_]+(?:\([a-z]\))?(?:\[\d+\])?):",
+        )
+        .expect("syslog regex must compile")
+    })
+}
+
+/// Replay in-line terminal control on every line that uses it.
+///
+/// Lines with backspaces or bare carriage returns are rendered as a terminal would show them
+/// (trailing blanks trimmed); bells are dropped. Escape sequences on rendered lines are
+/// consumed (`ESC [ K` erases, cursor moves move); other lines pass through untouched.
+pub fn emulate_terminal(raw: &str, warnings: &mut Vec<String>) -> String {
+    let mut overwritten = 0usize;
+    let mut bells = 0usize;
+    let mut out = String::with_capacity(raw.len());
+
+    for (idx, line) in raw.split('\n').enumerate() {
+        if idx > 0 {
+            out.push('\n');
+        }
+        let (line, crlf) = match line.strip_suffix('\r') {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+        let redraws = line.contains(['\x08', '\r']);
+        if redraws || line.contains('\x07') {
+            let (rendered, line_bells) = render_line(line);
+            bells += line_bells;
+            if redraws {
+                overwritten += 1;
+                out.push_str(rendered.trim_end());
+            } else {
+                out.push_str(&rendered);
+            }
+        } else {
+            out.push_str(line);
+        }
+        if crlf {
+            out.push('\r');
+        }
+    }
+
+    if overwritten > 0 {
+        warnings.push(format!(
+            "Replayed backspace/carriage-return overwrites on {} line(s)",
+            overwritten
+        ));
+    }
+    if bells > 0 {
+        warnings.push(format!("Removed {} bell character(s)", bells));
+    }
+    out
+}
+
+/// Render one line on a virtual terminal row, returning the row and the bells it rang.
+fn render_line(line: &str) -> (String, usize) {
+    let mut row: Vec<char> = Vec::new();
+    let mut col = 0usize;
+    let mut bells = 0usize;
+    let mut chars = line.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\x08' => col = col.saturating_sub(1),
+            '\r' => col = 0,
+            '\x07' => bells += 1,
+            '\x1b' => match chars.next() {
+                Some('[') => {
+                    let mut param = String::new();
+                    let mut fin = None;
+                    for c in chars.by_ref() {
+                        if ('@'..='~').contains(&c) {
+                            fin = Some(c);
+                            break;
+                        }
+                        param.push(c);
+                    }
+                    let n = param.parse::<usize>().unwrap_or(1).max(1);
+                    match (fin, param.as_str()) {
+                        (Some('K'), "" | "0") => row.truncate(col),
+                        (Some('K'), "1") => row.iter_mut().take(col).for_each(|c| *c = ' '),
+                        (Some('K'), "2") => row.clear(),
+                        (Some('D'), _) => col = col.saturating_sub(n),
+                        (Some('C'), _) => col += n,
+                        (Some('G'), _) => col = n - 1,
+                        _ => {}
+                    }
+                }
+                // OSC (e.g. window title) runs to BEL or ST.
+                Some(']') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            },
+            c => {
+                if col < row.len() {
+                    row[col] = c;
+                } else {
+                    row.resize(col, ' ');
+                    row.push(c);
+                }
+                col += 1;
+            }
+        }
+    }
+    (row.into_iter().collect(), bells)
+}
+
+/// Remove pager markers and (if enabled) syslog lines.
+///
+/// A marker is cut from the start of its line along with the blanks after it; lines left
+/// empty are dropped. Returns the cleaned text and the 0-based indices of dropped lines.
+pub fn remove_noise_lines(
+    text: &str,
+    options: &CleanupOptions,
+    warnings: &mut Vec<String>,
+) -> (String, Vec<usize>) {
+    let mut markers = 0usize;
+    let mut syslog = 0usize;
+    let mut dropped = Vec::new();
+    let mut kept: Vec<&str> = Vec::new();
+
+    for (idx, line) in text.split('\n').enumerate() {
+        let mut line = line;
+        if let Some(m) = pager_marker().find(line).filter(|_| options.pager) {
+            markers += 1;
+            line = &line[m.end()..];
+            if line.trim().is_empty() {
+                dropped.push(idx);
+                continue;
+            }
+        }
+        if syslog_line().is_match(line) {
+            syslog += 1;
+            if options.syslog {
+                dropped.push(idx);
+                continue;
+            }
+        }
+        kept.push(line);
+    }
+
+    if markers > 0 {
+        warnings.push(format!("Removed {} pager prompt(s)", markers));
+    }
+    if syslog > 0 {
+        warnings.push(if options.syslog {
+            format!("Removed {} syslog line(s)", syslog)
+        } else {
+            format!(
+                "Found {} syslog-like line(s) in command output; --strip-syslog removes them",
+                syslog
+            )
+        });
+    }
+    (kept.join("\n"), dropped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replays_pager_erase_and_carriage_return_redraws() {
+        let raw = concat!(
+            "Gi0/1 up\r\n",
+            " --More-- \x08\x08\x08\x08\x08\x08\x08\x08\x08\x08          \x08\x08\x08\x08\x08\x08\x08\x08\x08\x08Gi0/2 down\r\n",
+            "---(more)---\r                                        \rge-0/0/1 up\n",
+            "\x1b[7m--More--\x1b[m\r\x1b[KGi0/3 up\x07\n",
+        );
+        let mut warnings = Vec::new();
+        let cleaned = emulate_terminal(raw, &mut warnings);
+
+        assert_eq!(cleaned, "Gi0/1 up\r\nGi0/2 down\r\nge-0/0/1 up\nGi0/3 up\n");
+        assert_eq!(
+            warnings,
+            [
+                "Replayed backspace/carriage-return overwrites on 3 line(s)",
+                "Removed 1 bell character(s)"
+            ]
+        );
+    }
+
+    #[test]
+    fn removes_vendor_pager_markers_and_reports_dropped_lines() {
+        let text = concat!(
+            "line one\n",
+            " --More-- \n",
+            "  ---- More ----         line two\n",
+            "<--- More --->\n",
+            "---(more 45%)---\n",
+            "-- [Q quit|D dump|down]\n",
+            "line three",
+        );
+        let mut warnings = Vec::new();
+        let (cleaned, dropped) =
+            remove_noise_lines(text, &CleanupOptions::default(), &mut warnings);
+
+        assert_eq!(cleaned, "line one\nline two\nline three");
+        assert_eq!(dropped, [1, 3, 4, 5]);
+        assert_eq!(warnings, ["Removed 5 pager prompt(s)"]);
+    }
+
+    #[test]
+    fn syslog_lines_are_removed_only_on_request() {
+        let text = concat!(
+            "Gi0/1 up\n",
+            "*Mar  1 00:01:02.123: %LINK-3-UPDOWN: Interface Gi0/2, changed state to down\n",
+            "Oct 18 2026 10:00:00 R1 %%01IFNET/4/LINK_STATE(l)[0]:The line protocol went down\n",
+            "Gi0/2 down",
+        );
+
+        let mut warnings = Vec::new();
+        let (cleaned, dropped) =
+            remove_noise_lines(text, &CleanupOptions::default(), &mut warnings);
+        assert_eq!(cleaned, text);
+        assert!(dropped.is_empty());
+        assert!(warnings[0].starts_with("Found 2 syslog-like line(s)"));
+
+        let options = CleanupOptions {
+            syslog: true,
+            ..CleanupOptions::default()
+        };
+        let mut warnings = Vec::new();
+        let (cleaned, dropped) = remove_noise_lines(text, &options, &mut warnings);
+        assert_eq!(cleaned, "Gi0/1 up\nGi0/2 down");
+        assert_eq!(dropped, [1, 2]);
+        assert_eq!(warnings, ["Removed 2 syslog line(s)"]);
+    }
+}
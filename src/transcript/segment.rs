@@ -0,0 +1,342 @@
+//! Dialect-driven transcript segmentation.
+
+use super::dialect::PromptDialect;
+use std::collections::HashMap;
+
+/// One per-command output block with the prompt context it was captured under.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranscriptBlock {
+    /// Command echoed after the prompt, if any.
+    pub command: Option<String>,
+    /// Hostname from the prompt that started the block.
+    pub hostname: Option<String>,
+    /// CLI mode from that prompt (`config-if`, `config>router`, ...), if any.
+    pub mode: Option<String>,
+    /// First line of `text` in the original transcript (1-based).
+    pub start_line: usize,
+    /// Last line of `text` in the original transcript (1-based, inclusive).
+    pub end_line: usize,
+    /// Transcript lines inside the range that noise cleanup removed from `text` (1-based).
+    pub removed_lines: Vec<usize>,
+    pub text: String,
+}
+
+impl TranscriptBlock {
+    /// The whole input as one block without prompt metadata.
+    pub fn whole(raw: &str) -> Self {
+        Self {
+            command: None,
+            hostname: None,
+            mode: None,
+            start_line: 1,
+            end_line: raw.lines().count().max(1),
+            removed_lines: Vec::new(),
+            text: raw.to_string(),
+        }
+    }
+
+    /// Map a 0-based line index within `text` to its 0-based line index in the transcript.
+    pub fn original_line_idx(&self, line_idx: usize) -> usize {
+        let mut line = self.start_line + line_idx;
+        for &removed in &self.removed_lines {
+            if removed <= line {
+                line += 1;
+            }
+        }
+        line - 1
+    }
+
+    /// Shift line numbers counted over cleaned text back to the transcript, given the
+    /// 0-based transcript lines cleanup removed (sorted).
+    pub(crate) fn restore_removed_lines(&mut self, removed: &[usize]) {
+        let original = |cleaned_idx: usize| {
+            removed
+                .iter()
+                .fold(cleaned_idx, |idx, &r| if r <= idx { idx + 1 } else { idx })
+        };
+        let start = original(self.start_line - 1);
+        let end = original(self.end_line - 1);
+        self.removed_lines = removed
+            .iter()
+            .filter(|&&r| r > start && r < end)
+            .map(|r| r + 1)
+            .collect();
+        self.start_line = start + 1;
+        self.end_line = end + 1;
+    }
+
+    /// Short label for messages, e.g. `'show version' at lines 2-14`.
+    pub fn describe(&self) -> String {
+        let lines = if self.start_line == self.end_line {
+            format!("line {}", self.start_line)
+        } else {
+            format!("lines {}-{}", self.start_line, self.end_line)
+        };
+        match &self.command {
+            Some(cmd) => format!("'{cmd}' at {lines}"),
+            None => lines,
+        }
+    }
+}
+
+/// Split `raw` into per-command blocks at the prompt lines of `dialect`.
+///
+/// Prompt lines, command echoes and the dialect's context lines are dropped; a context line
+/// inside a block splits it so each block's text stays contiguous in the transcript. When no
+/// prompt base repeats and the first line is not a prompt with a command echo, or no command
+/// echo is found at all, the whole input is returned as a single block without a command.
+pub fn segment_transcript(raw: &str, dialect: &dyn PromptDialect) -> Vec<TranscriptBlock> {
+    let whole = || vec![TranscriptBlock::whole(raw)];
+    let mut base_counts: HashMap<&str, usize> = HashMap::new();
+    let mut first_line_is_echo = false;
+
+    for (idx, line) in raw.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if let Some(p) = dialect.parse_prompt(line) {
+            *base_counts.entry(p.base).or_insert(0) += 1;
+            if idx == 0 && p.cmd.is_some() {
+                first_line_is_echo = true;
+            }
+        }
+    }
+
+    let has_stable_base = base_counts.values().any(|&n| n >= 2);
+    if !has_stable_base && !first_line_is_echo {
+        return whole();
+    }
+
+    let mut blocks: Vec<TranscriptBlock> = Vec::new();
+    let mut cur: Vec<&str> = Vec::new();
+    let mut cur_start = 0usize;
+    let mut cur_cmd: Option<String> = None;
+    let mut hostname: Option<String> = None;
+    let mut mode: Option<String> = None;
+    let mut started_any = false;
+    let mut after_context = false;
+
+    let mut flush = |cur: &mut Vec<&str>, start: usize, cmd: Option<String>, host, mode| {
+        if !cur.is_empty() {
+            blocks.push(TranscriptBlock {
+                command: cmd,
+                hostname: host,
+                mode,
+                start_line: start,
+                end_line: start + cur.len() - 1,
+                removed_lines: Vec::new(),
+                text: cur.join("\n"),
+            });
+            cur.clear();
+        }
+    };
+
+    for (idx, line) in raw.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if let Some(p) = dialect.parse_prompt(line) {
+            // A prompt ends the current block; a command echo starts the next one.
+            flush(
+                &mut cur,
+                cur_start,
+                cur_cmd.take(),
+                hostname.take(),
+                mode.take(),
+            );
+            cur_cmd = p.cmd.map(|cmd| cmd.trim().to_string());
+            hostname = Some(p.hostname.to_string());
+            mode = p.mode.map(str::to_string);
+            started_any |= p.cmd.is_some();
+            after_context = false;
+            continue;
+        }
+        if dialect.is_context_line(line) {
+            flush(
+                &mut cur,
+                cur_start,
+                cur_cmd.clone(),
+                hostname.clone(),
+                mode.clone(),
+            );
+            after_context = true;
+            continue;
+        }
+
+        // Normal line; output resuming after a context line continues the same command.
+        if started_any && !(after_context && cur.is_empty() && line.trim().is_empty()) {
+            if cur.is_empty() {
+                cur_start = idx + 1;
+            }
+            cur.push(line);
+        }
+    }
+    flush(&mut cur, cur_start, cur_cmd, hostname, mode);
+
+    // If we never saw a prompt+command echo, we can't safely segment; fall back.
+    if !started_any || blocks.is_empty() {
+        return whole();
+    }
+
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::dialect::{Ios, Junos, Sros};
+
+    fn texts(blocks: Vec<TranscriptBlock>) -> Vec<String> {
+        blocks.into_iter().map(|block| block.text).collect()
+    }
+
+    #[test]
+    fn segments_multi_command_transcript_and_strips_prompts_and_echoes() {
+        let raw = concat!(
+            "Router# show ip interface brief\n",
+            "Interface              IP-Address      OK? Method Status                Protocol\n",
+            "GigabitEthernet0/0     10.0.0.1        YES manual up                    up\n",
+            "Router# show version\n",
+            "Cisco IOS Software, ...\n",
+            "Router#\n",
+        );
+
+        let blocks = texts(segment_transcript(raw, &Ios));
+        assert_eq!(blocks.len(), 2);
+        assert!(!blocks[0].contains("Router#"));
+        assert!(!blocks[1].contains("Router#"));
+        assert!(blocks[0].contains("GigabitEthernet0/0"));
+        assert!(blocks[1].contains("Cisco IOS Software"));
+    }
+
+    #[test]
+    fn strips_single_prompt_command_echo_at_start_even_without_trailing_prompt() {
+        let raw = "Router# show version\nCisco IOS Software, ...\nROM: Bootstrap\n";
+        let blocks = texts(segment_transcript(raw, &Ios));
+
+        assert_eq!(blocks.len(), 1);
+        assert!(!blocks[0].contains("Router# show version"));
+        assert!(blocks[0].contains("Cisco IOS Software"));
+    }
+
+    #[test]
+    fn does_not_trigger_on_single_prompt_like_line_when_confidence_is_low() {
+        let raw = "Some output line\nRouter#\nMore output\n";
+        let blocks = texts(segment_transcript(raw, &Ios));
+
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0], raw);
+    }
+
+    #[test]
+    fn recognizes_config_mode_prompts_as_same_hostname_base() {
+        let raw = concat!(
+            "Router(config)# show running-config\n",
+            "Building configuration...\n",
+            "Current configuration : 1234 bytes\n",
+            "Router(config)#\n",
+        );
+        let blocks = texts(segment_transcript(raw, &Ios));
+
+        assert_eq!(blocks.len(), 1);
+        assert!(!blocks[0].contains("Router(config)#"));
+        assert!(blocks[0].contains("Building configuration"));
+        assert!(blocks[0].contains("Current configuration"));
+    }
+
+    #[test]
+    fn segments_keep_the_echoed_command() {
+        let raw = concat!(
+            "Router# show version\n",
+            "Cisco IOS Software, ...\n",
+            "Router#  sh ip int br \n",
+            "Gi0/0 10.0.0.1 YES manual up up\n",
+            "Router#\n",
+        );
+        let blocks = segment_transcript(raw, &Ios);
+
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].command.as_deref(), Some("show version"));
+        assert_eq!(blocks[1].command.as_deref(), Some("sh ip int br"));
+        assert!(blocks[1].text.contains("Gi0/0"));
+    }
+
+    #[test]
+    fn blocks_carry_prompt_context_and_transcript_line_range() {
+        let raw = concat!(
+            "r1# show version\n",
+            "Cisco IOS Software, ...\n",
+            "uptime is 3 days\n",
+            "r1(config-if)# do show ip int br\n",
+            "Gi0/0 10.0.0.1 YES manual up up\n",
+            "r1(config-if)#\n",
+        );
+        let blocks = segment_transcript(raw, &Ios);
+
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].hostname.as_deref(), Some("r1"));
+        assert_eq!(blocks[0].mode, None);
+        assert_eq!((blocks[0].start_line, blocks[0].end_line), (2, 3));
+        assert_eq!(blocks[0].describe(), "'show version' at lines 2-3");
+        assert_eq!(blocks[1].mode.as_deref(), Some("config-if"));
+        assert_eq!((blocks[1].start_line, blocks[1].end_line), (5, 5));
+        assert_eq!(blocks[1].original_line_idx(0), 4);
+    }
+
+    #[test]
+    fn context_lines_split_blocks_to_keep_line_ranges_contiguous() {
+        let raw = concat!(
+            "admin@mx1> show chassis alarms\n",
+            "No alarms currently active\n",
+            "{master:0}\n",
+            "late output line\n",
+            "admin@mx1> \n",
+        );
+        let blocks = segment_transcript(raw, &Junos);
+
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[1].command.as_deref(), Some("show chassis alarms"));
+        assert_eq!(
+            (blocks[1].start_line, blocks[1].text.as_str()),
+            (4, "late output line")
+        );
+    }
+
+    #[test]
+    fn drops_dialect_context_lines() {
+        let raw = concat!(
+            "admin@mx1> show system uptime\n",
+            "Current time: 2024-01-01 10:00:00 UTC\n",
+            "\n",
+            "{master:0}\n",
+            "admin@mx1> configure\n",
+            "Entering configuration mode\n",
+            "\n",
+            "[edit]\n",
+            "admin@mx1# show system host-name\n",
+            "host-name mx1;\n",
+            "\n",
+            "[edit]\n",
+            "admin@mx1# \n",
+        );
+        let blocks = segment_transcript(raw, &Junos);
+
+        let commands: Vec<_> = blocks.iter().map(|b| b.command.as_deref()).collect();
+        assert_eq!(
+            commands,
+            [
+                Some("show system uptime"),
+                Some("configure"),
+                Some("show system host-name")
+            ]
+        );
+        assert_eq!(blocks[2].text, "host-name mx1;\n");
+        assert!(!blocks[0].text.contains("master"));
+    }
+
+    #[test]
+    fn segments_sros_md_cli_sessions() {
+        let raw = "[/]\nA:admin@pe1# show version\nTiMOS-B-22.10.R1\n\n[/]\nA:admin@pe1#\n";
+        let blocks = segment_transcript(raw, &Sros);
+
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].command.as_deref(), Some("show version"));
+        assert_eq!(blocks[0].text, "TiMOS-B-22.10.R1\n");
+    }
+}
@@ -0,0 +1,327 @@
+//! Session log decoders that run ahead of transcript cleanup and segmentation.
+//!
+//! Terminal clients wrap what the device printed: SecureCRT and PuTTY can prefix every line
+//! with a timestamp, `script(1)` records the raw terminal stream between a start and an end
+//! banner, and asciinema stores output as timed JSON events. Each decoder returns the text the
+//! device printed; header and footer lines become blank lines so transcript line numbers still
+//! match the log file (casts excepted: their lines are the decoded output's).
+
+use super::{dialect, noise};
+use regex::Regex;
+use std::borrow::Cow;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// How a session log is wrapped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SessionFormat {
+    /// Detect from the file name, then the content.
+    #[default]
+    Auto,
+    /// Plain capture; nothing to decode.
+    Plain,
+    /// SecureCRT/PuTTY log with per-line timestamps and/or a PuTTY log banner.
+    Timestamped,
+    /// `script(1)` typescript.
+    Typescript,
+    /// asciinema v2/v3 `.cast` recording.
+    Asciicast,
+}
+
+impl SessionFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            SessionFormat::Auto => "auto",
+            SessionFormat::Plain => "plain",
+            SessionFormat::Timestamped => "timestamped",
+            SessionFormat::Typescript => "typescript",
+            SessionFormat::Asciicast => "asciicast",
+        }
+    }
+
+    /// Resolve `Auto` from the file name where it is conclusive (`*.cast`, `typescript`).
+    pub fn for_path(self, path: Option<&Path>) -> Self {
+        if self != SessionFormat::Auto {
+            return self;
+        }
+        let Some(path) = path else {
+            return self;
+        };
+        if path.extension().is_some_and(|ext| ext == "cast") {
+            SessionFormat::Asciicast
+        } else if path.file_name().is_some_and(|name| {
+            name == "typescript" || name.to_string_lossy().ends_with(".typescript")
+        }) {
+            SessionFormat::Typescript
+        } else {
+            self
+        }
+    }
+}
+
+/// PuTTY's log banner, e.g. `=~=~=~=~=~=~=~=~=~=~=~= PuTTY log 2024.01.15 10:23:45 =~=~=...`.
+const PUTTY_BANNER: &str = "=~=~=~=~=~=~=~=~=~=~=~= PuTTY log ";
+
+/// Per-line timestamp prefixes: bracketed (SecureCRT `[Mon Jan 15 10:23:45 2024]`,
+/// `[2024-01-15 10:23:45.123]`, `[10:23:45]`) or bare ISO-ish (`2024-01-15 10:23:45: `).
+fn timestamp_prefix() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r"^(?:\[[^\]\r\n]*\b\d{1,2}:\d{2}:\d{2}\b[^\]\r\n]*\]|\d{4}[-/.]\d{2}[-/.]\d{2}[ T]\d{2}:\d{2}:\d{2}(?:[.,]\d+)?(?:Z|[+-]\d{2}:?\d{2})?:?)[ \t]?",
+        )
+        .expect("timestamp regex must compile")
+    })
+}
+
+/// SecureCRT-style bracketed timestamp prefixes, the only per-line stamps auto-detection
+/// trusts.
+fn bracketed_timestamp() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"^\[[^\]\r\n]*\b\d{1,2}:\d{2}:\d{2}\b[^\]\r\n]*\][ \t]?")
+            .expect("bracketed timestamp regex must compile")
+    })
+}
+
+/// Identify the session format from the content.
+///
+/// Timestamped logs need positive evidence: the PuTTY banner, or bracketed stamps on most lines
+/// with device prompts after them. Plain output whose lines merely start with a date (syslog,
+/// `show logging`) stays plain; `--input-format timestamped` strips other stamp styles.
+pub fn detect(raw: &str) -> SessionFormat {
+    let first = raw
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .unwrap_or("");
+    if first.trim_start().starts_with('{') && cast_version(first.trim()).is_some() {
+        return SessionFormat::Asciicast;
+    }
+    if first.starts_with("Script started on ") {
+        return SessionFormat::Typescript;
+    }
+    if first.starts_with(PUTTY_BANNER) {
+        return SessionFormat::Timestamped;
+    }
+
+    let sample: Vec<&str> = raw
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .take(50)
+        .collect();
+    let unstamped: Vec<&str> = sample
+        .iter()
+        .filter_map(|line| bracketed_timestamp().find(line).map(|m| &line[m.end()..]))
+        .collect();
+    let stamped = unstamped.len();
+    if stamped >= 2
+        && stamped * 5 >= sample.len() * 4
+        && dialect::detect(&unstamped.join("\n")).is_some()
+    {
+        SessionFormat::Timestamped
+    } else {
+        SessionFormat::Plain
+    }
+}
+
+/// Decode `raw` as `format` (detecting `Auto` from the content).
+pub fn decode<'a>(raw: &'a str, format: SessionFormat, warnings: &mut Vec<String>) -> Cow<'a, str> {
+    let format = match format {
+        SessionFormat::Auto => detect(raw),
+        format => format,
+    };
+    tracing::debug!(
+        target: "cliscrape::transcript",
+        event = "session_format",
+        format = format.name()
+    );
+    match format {
+        SessionFormat::Auto | SessionFormat::Plain => Cow::Borrowed(raw),
+        SessionFormat::Timestamped => Cow::Owned(strip_timestamps(raw, warnings)),
+        SessionFormat::Typescript => Cow::Owned(replay_typescript(raw, warnings)),
+        SessionFormat::Asciicast => Cow::Owned(replay_cast(raw, warnings)),
+    }
+}
+
+fn strip_timestamps(raw: &str, warnings: &mut Vec<String>) -> String {
+    let mut stripped = 0usize;
+    let lines: Vec<&str> = raw
+        .split('\n')
+        .map(|line| {
+            if line.starts_with(PUTTY_BANNER) {
+                return "";
+            }
+            match timestamp_prefix().find(line) {
+                Some(m) => {
+                    stripped += 1;
+                    &line[m.end()..]
+                }
+                None => line,
+            }
+        })
+        .collect();
+    if stripped > 0 {
+        warnings.push(format!(
+            "Stripped session log timestamps from {} line(s)",
+            stripped
+        ));
+    }
+    lines.join("\n")
+}
+
+/// Blank the `Script started`/`Script done` banners and replay the terminal stream.
+fn replay_typescript(raw: &str, warnings: &mut Vec<String>) -> String {
+    let lines: Vec<&str> = raw
+        .split('\n')
+        .map(|line| {
+            if line.starts_with("Script started on ") || line.starts_with("Script done on ") {
+                ""
+            } else {
+                line
+            }
+        })
+        .collect();
+    noise::emulate_terminal(&lines.join("\n"), warnings)
+}
+
+/// Version of an asciicast header line, if it is one we read.
+fn cast_version(header: &str) -> Option<u64> {
+    let header: serde_json::Value = serde_json::from_str(header).ok()?;
+    header
+        .get("version")
+        .and_then(serde_json::Value::as_u64)
+        .filter(|version| matches!(version, 2 | 3))
+}
+
+/// Concatenate the output (`"o"`) events of a cast and replay them as a terminal stream.
+fn replay_cast(raw: &str, warnings: &mut Vec<String>) -> String {
+    let mut lines = raw.lines().filter(|line| !line.trim().is_empty());
+    if lines
+        .next()
+        .and_then(|header| cast_version(header.trim()))
+        .is_none()
+    {
+        warnings.push(
+            "asciicast header missing or not version 2/3; decoding events anyway".to_string(),
+        );
+    }
+
+    let mut stream = String::new();
+    let mut skipped = 0usize;
+    for line in lines {
+        let event: Option<(serde_json::Value, String, String)> = serde_json::from_str(line).ok();
+        match event {
+            Some((_, kind, data)) if kind == "o" => stream.push_str(&data),
+            Some(_) => {}
+            None => skipped += 1,
+        }
+    }
+    if skipped > 0 {
+        warnings.push(format!(
+            "Skipped {} malformed asciicast event line(s)",
+            skipped
+        ));
+    }
+    noise::emulate_terminal(&stream, warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_formats_by_content_and_file_name() {
+        let cast = "{\"version\": 2, \"width\": 80, \"height\": 24}\n[0.1, \"o\", \"r1#\"]\n";
+        assert_eq!(detect(cast), SessionFormat::Asciicast);
+        assert_eq!(
+            detect("Script started on 2024-01-15 10:23:45+00:00 [TERM=\"xterm\"]\nr1#\n"),
+            SessionFormat::Typescript
+        );
+        assert_eq!(
+            detect(concat!(
+                "[2024-01-15 10:23:45] r1# show clock\n",
+                "[2024-01-15 10:23:45] 10:23:45 UTC\n",
+                "[2024-01-15 10:23:46] r1#\n",
+            )),
+            SessionFormat::Timestamped
+        );
+        // Log output stamped like a session log, but with no prompts, is device output.
+        let syslog = concat!(
+            "2024-01-15 10:23:45 r1 kernel: eth0 link up\n",
+            "2024-01-15 10:23:46 r1 kernel: eth1 link up\n",
+            "2024-01-15 10:23:47 r1 sshd[42]: accepted publickey\n",
+        );
+        assert_eq!(detect(syslog), SessionFormat::Plain);
+        assert_eq!(
+            detect(&syslog.replace("2024-01-15 ", "[").replace(" r1", "] r1")),
+            SessionFormat::Plain
+        );
+        assert_eq!(
+            detect("r1#show clock\n10:23:45.123 UTC Mon Jan 15 2024\nr1#\n"),
+            SessionFormat::Plain
+        );
+
+        let auto = SessionFormat::Auto;
+        assert_eq!(
+            auto.for_path(Some(Path::new("logs/r1.cast"))),
+            SessionFormat::Asciicast
+        );
+        assert_eq!(
+            auto.for_path(Some(Path::new("typescript"))),
+            SessionFormat::Typescript
+        );
+        assert_eq!(
+            auto.for_path(Some(Path::new("r1.log"))),
+            SessionFormat::Auto
+        );
+        assert_eq!(
+            SessionFormat::Plain.for_path(Some(Path::new("r1.cast"))),
+            SessionFormat::Plain
+        );
+    }
+
+    #[test]
+    fn strips_securecrt_and_putty_timestamps_keeping_line_count() {
+        let raw = concat!(
+            "=~=~=~=~=~=~=~=~=~=~=~= PuTTY log 2024.01.15 10:23:45 =~=~=~=~=~=~=~=~=~=~=~=\n",
+            "[Mon Jan 15 10:23:45 2024] r1#show ip int br\n",
+            "[Mon Jan 15 10:23:45 2024] Gi0/0  10.0.0.1  up\n",
+            "2024-01-15 10:23:46: r1#\n",
+        );
+        let mut warnings = Vec::new();
+        let decoded = decode(raw, SessionFormat::Auto, &mut warnings);
+
+        assert_eq!(decoded, "\nr1#show ip int br\nGi0/0  10.0.0.1  up\nr1#\n");
+        assert_eq!(warnings, ["Stripped session log timestamps from 3 line(s)"]);
+    }
+
+    #[test]
+    fn replays_typescripts_and_casts() {
+        let typescript = concat!(
+            "Script started on 2024-01-15 10:23:45+00:00 [TERM=\"xterm\"]\n",
+            "r1#sh\x07\x08\x08show clock\r\n",
+            "10:23:45 UTC\r\n",
+            "r1#exit\r\n",
+            "\n",
+            "Script done on 2024-01-15 10:24:00+00:00 [COMMAND_EXIT_CODE=\"0\"]\n",
+        );
+        let mut warnings = Vec::new();
+        let decoded = decode(typescript, SessionFormat::Auto, &mut warnings);
+        assert_eq!(
+            decoded,
+            "\nr1#show clock\r\n10:23:45 UTC\r\nr1#exit\r\n\n\n"
+        );
+
+        let cast = concat!(
+            "{\"version\": 2, \"width\": 80, \"height\": 24}\n",
+            "[0.10, \"o\", \"r1#show \"]\n",
+            "[0.20, \"i\", \"q\"]\n",
+            "[0.30, \"o\", \"clock\\r\\n10:23:45 UTC\\r\\nr1#\"]\n",
+            "not json\n",
+        );
+        let mut warnings = Vec::new();
+        let decoded = decode(cast, SessionFormat::Auto, &mut warnings);
+        assert_eq!(decoded, "r1#show clock\r\n10:23:45 UTC\r\nr1#");
+        assert_eq!(warnings, ["Skipped 1 malformed asciicast event line(s)"]);
+    }
+}
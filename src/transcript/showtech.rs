@@ -0,0 +1,185 @@
+//! `show tech-support` section splitting.
+//!
+//! Show-tech output runs dozens of commands back to back under one prompt, separating them
+//! with banner lines instead of prompts, so the prompt segmenter sees a single block. Banners:
+//!
+//! - IOS, IOS-XE, IOS-XR and EOS: `------------------ show version ------------------`
+//! - NX-OS: `` `show version` ``
+//!
+//! Ordinary output has dashed headings too, so outside a `show tech*` block only banners that
+//! name a `show` command split it.
+
+use super::TranscriptBlock;
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// The command a show-tech section banner introduces.
+pub fn section_banner(line: &str) -> Option<&str> {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| {
+        Regex::new(r"^\s*(?:-{5,}\s+(?P<dashed>[A-Za-z].*?)\s+-{5,}|`(?P<quoted>[a-z][^`]*)`)\s*$")
+            .expect("show tech banner regex must compile")
+    });
+    let caps = re.captures(line)?;
+    caps.name("dashed")
+        .or_else(|| caps.name("quoted"))
+        .map(|m| m.as_str().trim())
+}
+
+/// Whether `command` runs show tech-support (`show tech`, `sh tech-support detail`, ...).
+fn is_show_tech(command: &str) -> bool {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"(?i)^\s*sh(?:o|ow)?\s+tech").expect("show tech regex must compile")
+    })
+    .is_match(command)
+}
+
+/// Whether a banner names a `show` command rather than an ordinary heading.
+fn is_show_command(name: &str) -> bool {
+    let mut words = name.split_whitespace();
+    words
+        .next()
+        .is_some_and(|word| word.eq_ignore_ascii_case("show"))
+        && words.next().is_some()
+}
+
+/// Split `block` at show-tech section banners into one block per section command.
+///
+/// Inside a `show tech*` block every banner counts; elsewhere only banners naming a `show`
+/// command do. Blocks with fewer than two banners are returned unchanged. Output before the first banner
+/// stays with the block's own command; banner lines and empty sections are dropped. Sections
+/// keep the prompt hostname and mode, and their line ranges refer to the transcript.
+pub fn split_sections(block: TranscriptBlock) -> Vec<TranscriptBlock> {
+    let in_show_tech = block.command.as_deref().is_some_and(is_show_tech);
+    let lines: Vec<&str> = block.text.split('\n').collect();
+    let banners: Vec<(usize, &str)> = lines
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, line)| {
+            section_banner(line)
+                .filter(|cmd| in_show_tech || is_show_command(cmd))
+                .map(|cmd| (idx, cmd))
+        })
+        .collect();
+    if banners.len() < 2 {
+        return vec![block];
+    }
+
+    let section = |command: Option<String>, first: usize, end: usize| {
+        let body = &lines[first..end];
+        if body.iter().all(|line| line.trim().is_empty()) {
+            return None;
+        }
+        let start_line = block.original_line_idx(first) + 1;
+        let end_line = block.original_line_idx(end - 1) + 1;
+        Some(TranscriptBlock {
+            command,
+            hostname: block.hostname.clone(),
+            mode: block.mode.clone(),
+            start_line,
+            end_line,
+            removed_lines: block
+                .removed_lines
+                .iter()
+                .copied()
+                .filter(|&line| line > start_line && line < end_line)
+                .collect(),
+            text: body.join("\n"),
+        })
+    };
+
+    let mut sections: Vec<TranscriptBlock> = Vec::new();
+    sections.extend(section(block.command.clone(), 0, banners[0].0));
+    for (i, &(idx, command)) in banners.iter().enumerate() {
+        let end = banners.get(i + 1).map_or(lines.len(), |&(next, _)| next);
+        sections.extend(section(Some(command.to_string()), idx + 1, end));
+    }
+    tracing::debug!(
+        target: "cliscrape::transcript",
+        event = "show_tech_sections",
+        command = ?block.command,
+        sections = sections.len()
+    );
+    sections
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognises_ios_eos_and_nxos_banners() {
+        assert_eq!(
+            section_banner("------------------ show version ------------------"),
+            Some("show version")
+        );
+        assert_eq!(
+            section_banner("------------- show interfaces status -------------"),
+            Some("show interfaces status")
+        );
+        assert_eq!(section_banner("`show module`"), Some("show module"));
+        assert_eq!(section_banner("-------------------- ----"), None);
+        assert_eq!(section_banner("---------- ---------- ----------"), None);
+        assert_eq!(section_banner("Port  Name  Status"), None);
+    }
+
+    #[test]
+    fn splits_sections_with_transcript_line_ranges() {
+        let block = TranscriptBlock {
+            command: Some("show tech-support".to_string()),
+            hostname: Some("sw1".to_string()),
+            mode: None,
+            start_line: 2,
+            end_line: 10,
+            removed_lines: Vec::new(),
+            text: concat!(
+                "\n",
+                "------------------ show clock ------------------\n",
+                "10:00:00 UTC\n",
+                "\n",
+                "------------------ show ip route summary ------------------\n",
+                "\n",
+                "------------------ show version ------------------\n",
+                "Cisco IOS Software\n",
+                "uptime is 3 days",
+            )
+            .to_string(),
+        };
+
+        let sections = split_sections(block);
+
+        let commands: Vec<_> = sections.iter().map(|s| s.command.as_deref()).collect();
+        assert_eq!(commands, [Some("show clock"), Some("show version")]);
+        assert_eq!(sections[0].text, "10:00:00 UTC\n");
+        assert_eq!((sections[0].start_line, sections[0].end_line), (4, 5));
+        assert_eq!((sections[1].start_line, sections[1].end_line), (9, 10));
+        assert_eq!(sections[1].hostname.as_deref(), Some("sw1"));
+    }
+
+    #[test]
+    fn splits_at_headings_only_inside_show_tech() {
+        let text = concat!(
+            "----- Port summary -----\n",
+            "Gi0/1 up\n",
+            "----- Totals -----\n",
+            "1 port up",
+        );
+        let block = |command: &str| TranscriptBlock {
+            command: Some(command.to_string()),
+            ..TranscriptBlock::whole(text)
+        };
+
+        assert_eq!(split_sections(block("show ports")), [block("show ports")]);
+
+        let sections = split_sections(block("sh tech-support detail"));
+        let commands: Vec<_> = sections.iter().map(|s| s.command.as_deref()).collect();
+        assert_eq!(commands, [Some("Port summary"), Some("Totals")]);
+    }
+
+    #[test]
+    fn leaves_blocks_with_a_single_banner_alone() {
+        let block = TranscriptBlock::whole("----- show clock -----\n10:00:00 UTC");
+        assert_eq!(split_sections(block.clone()), [block]);
+    }
+}
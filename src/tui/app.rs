@@ -1,3 +1,4 @@
+use crate::transcript::TranscriptBlock;
 use cliscrape::DebugReport;
 use std::collections::HashSet;
 use std::path::PathBuf;
@@ -45,6 +46,9 @@ pub struct AppState {
     pub stepping_mode: crate::tui::trace::SteppingMode,
     pub filter_state: crate::tui::trace::FilterState,
     pub watch_list: HashSet<String>,
+    /// Transcript blocks of the input; the debugger parses `blocks[block_idx]`.
+    pub blocks: Vec<TranscriptBlock>,
+    pub block_idx: usize,
 }
 
 impl AppState {
@@ -96,6 +100,8 @@ impl AppState {
             stepping_mode: crate::tui::trace::SteppingMode::LineByLine,
             filter_state: crate::tui::trace::FilterState::default(),
             watch_list: HashSet::new(),
+            blocks: Vec::new(),
+            block_idx: 0,
         }
     }
 
@@ -135,7 +141,9 @@ impl AppState {
         self.status = ParseStatus::Parsing;
     }
 
-    pub fn on_parse_done(&mut self, report: DebugReport) {
+    pub fn on_parse_done(&mut self, report: DebugReport, blocks: Vec<TranscriptBlock>) {
+        self.block_idx = self.block_idx.min(blocks.len().saturating_sub(1));
+        self.blocks = blocks;
         self.lines = report.lines.clone();
         self.last_good = Some(report);
         self.current_error = None;
@@ -147,6 +155,34 @@ impl AppState {
         self.sync_selections();
     }
 
+    /// Select the next transcript block, wrapping around. Returns false with fewer than two.
+    pub fn next_block(&mut self) -> bool {
+        if self.blocks.len() < 2 {
+            return false;
+        }
+        self.block_idx = (self.block_idx + 1) % self.blocks.len();
+        self.cursor_line_idx = 0;
+        true
+    }
+
+    /// Select the previous transcript block, wrapping around.
+    pub fn previous_block(&mut self) -> bool {
+        if self.blocks.len() < 2 {
+            return false;
+        }
+        self.block_idx = (self.block_idx + self.blocks.len() - 1) % self.blocks.len();
+        self.cursor_line_idx = 0;
+        true
+    }
+
+    /// Transcript line number (1-based) of line `idx` of the selected block.
+    pub fn transcript_line_number(&self, idx: usize) -> usize {
+        self.blocks
+            .get(self.block_idx)
+            .map_or(idx, |block| block.original_line_idx(idx))
+            + 1
+    }
+
     pub fn on_parse_error(&mut self, error: String) {
         self.current_error = Some(error);
         self.status = ParseStatus::Error;
@@ -29,8 +29,13 @@ pub enum FsWhich {
 #[derive(Debug)]
 pub enum Message {
     Key(crossterm::event::KeyEvent),
-    FsChanged { which: FsWhich },
-    ParseDone(cliscrape::DebugReport),
+    FsChanged {
+        which: FsWhich,
+    },
+    ParseDone(
+        cliscrape::DebugReport,
+        Vec<crate::transcript::TranscriptBlock>,
+    ),
     ParseError(String),
 }
 
@@ -56,7 +61,7 @@ pub fn run(mut app: AppState) -> anyhow::Result<()> {
         worker.request(worker::ParseRequest {
             template_path: tpl,
             input_path: inp,
-            block_idx: 0,
+            block_idx: app.block_idx,
         });
     }
 
@@ -127,12 +132,12 @@ fn handle_message(
             worker.request(worker::ParseRequest {
                 template_path: tpl,
                 input_path: inp,
-                block_idx: 0,
+                block_idx: app.block_idx,
             });
             false
         }
-        Message::ParseDone(report) => {
-            app.on_parse_done(report);
+        Message::ParseDone(report, blocks) => {
+            app.on_parse_done(report, blocks);
             false
         }
         Message::ParseError(err) => {
@@ -158,7 +163,7 @@ fn handle_key(
 
     match app.mode {
         Mode::Picker => handle_key_picker(app, key, worker, msg_tx, watcher),
-        Mode::Browse => handle_key_browse(app, key),
+        Mode::Browse => handle_key_browse(app, key, worker),
         Mode::EditTemplate => handle_key_editor(app, key, worker),
         Mode::TemplateBrowser => handle_key_template_browser(app, key, worker, msg_tx, watcher),
     }
@@ -213,6 +218,7 @@ fn handle_key_picker(
                 }
                 crate::tui::picker::PickTarget::Input => {
                     app.input_path = Some(path);
+                    app.block_idx = 0;
                 }
             }
 
@@ -243,7 +249,7 @@ fn handle_key_picker(
                 worker.request(worker::ParseRequest {
                     template_path: tpl,
                     input_path: inp,
-                    block_idx: 0,
+                    block_idx: app.block_idx,
                 });
             }
 
@@ -252,7 +258,11 @@ fn handle_key_picker(
     }
 }
 
-fn handle_key_browse(app: &mut AppState, key: crossterm::event::KeyEvent) -> bool {
+fn handle_key_browse(
+    app: &mut AppState,
+    key: crossterm::event::KeyEvent,
+    worker: &worker::ParseWorker,
+) -> bool {
     match key.code {
         KeyCode::Char('q') => true,
         KeyCode::Up | KeyCode::Char('k') => {
@@ -283,6 +293,25 @@ fn handle_key_browse(app: &mut AppState, key: crossterm::event::KeyEvent) -> boo
             app.enter_template_browser();
             false
         }
+        // Transcript block navigation
+        KeyCode::Char('b') | KeyCode::Char('B') => {
+            let moved = if key.code == KeyCode::Char('b') {
+                app.next_block()
+            } else {
+                app.previous_block()
+            };
+            if let (true, Some(tpl), Some(inp)) =
+                (moved, app.template_path.clone(), app.input_path.clone())
+            {
+                app.on_parse_started();
+                worker.request(worker::ParseRequest {
+                    template_path: tpl,
+                    input_path: inp,
+                    block_idx: app.block_idx,
+                });
+            }
+            false
+        }
         // State Tracer stepping controls
         KeyCode::PageDown => {
             app.step_forward();
@@ -392,7 +421,7 @@ fn handle_key_template_browser(
                     worker.request(worker::ParseRequest {
                         template_path: tpl,
                         input_path: inp,
-                        block_idx: 0,
+                        block_idx: app.block_idx,
                     });
                 }
                 app.exit_template_browser();
@@ -430,7 +459,7 @@ fn handle_key_editor(
                         worker.request(worker::ParseRequest {
                             template_path: tpl,
                             input_path: inp,
-                            block_idx: 0,
+                            block_idx: app.block_idx,
                         });
                     }
                 }
@@ -474,7 +474,11 @@ fn render_picker_status(frame: &mut Frame, area: Rect, app: &AppState) {
 }
 
 fn render_lines_pane(frame: &mut Frame, area: Rect, app: &AppState) {
-    let title = format!("Lines (cursor: {})", app.cursor_line_idx + 1);
+    // Number lines as in the transcript, not the selected block.
+    let title = format!(
+        "Lines (cursor: {})",
+        app.transcript_line_number(app.cursor_line_idx)
+    );
     let block = Block::default().borders(Borders::ALL).title(title);
     let inner = block.inner(area);
 
@@ -559,7 +563,7 @@ fn render_lines_pane(frame: &mut Frame, area: Rect, app: &AppState) {
             Color::Gray
         });
 
-        let prefix_num = format!("{:>5} ", idx + 1);
+        let prefix_num = format!("{:>5} ", app.transcript_line_number(idx));
 
         let (text_spans, skipped) =
             build_highlight_spans(line.as_str(), base, highlight_style, &selected_match_ranges);
@@ -1069,6 +1073,14 @@ fn render_status_pane(frame: &mut Frame, area: Rect, app: &AppState) {
         Mode::TemplateBrowser => "template-browser",
     };
     lines.push(Line::from(format!("mode:     {}", mode_str)));
+    if let Some(block) = app.blocks.get(app.block_idx) {
+        lines.push(Line::from(format!(
+            "block:    {}/{} {} (b/B: next/prev)",
+            app.block_idx + 1,
+            app.blocks.len(),
+            block.describe()
+        )));
+    }
 
     if let Some(report) = &app.last_good {
         let cursor = app
@@ -1,3 +1,4 @@
+use crate::transcript::{TranscriptBlock, TranscriptOptions};
 use crate::tui::Message;
 use anyhow::Context;
 use cliscrape::FsmParser;
@@ -29,8 +30,8 @@ impl ParseWorker {
                 }
 
                 match parse_once(&req) {
-                    Ok(report) => {
-                        let _ = sender.send(Message::ParseDone(report));
+                    Ok((report, blocks)) => {
+                        let _ = sender.send(Message::ParseDone(report, blocks));
                     }
                     Err(err) => {
                         let _ = sender.send(Message::ParseError(format!("{:#}", err)));
@@ -63,20 +64,26 @@ impl Drop for ParseWorker {
     }
 }
 
-fn parse_once(req: &ParseRequest) -> anyhow::Result<cliscrape::DebugReport> {
+fn parse_once(
+    req: &ParseRequest,
+) -> anyhow::Result<(cliscrape::DebugReport, Vec<TranscriptBlock>)> {
     let parser = FsmParser::from_file(&req.template_path)
         .with_context(|| format!("Failed to load template from {:?}", req.template_path))?;
 
     let input_content = std::fs::read_to_string(&req.input_path)
         .with_context(|| format!("Failed to read input from {:?}", req.input_path))?;
-    let blocks = crate::transcript::preprocess_ios_transcript(&input_content);
+    let options = TranscriptOptions::default().for_source(Some(&req.input_path));
+    let (blocks, _) =
+        crate::transcript::preprocess_transcript_with_warnings(&input_content, &options);
 
+    // The input may have lost blocks since the index was chosen; stay on the last one.
     let block = blocks
-        .get(req.block_idx)
-        .map(|s| s.as_str())
+        .get(req.block_idx.min(blocks.len().saturating_sub(1)))
+        .map(|block| block.text.as_str())
         .unwrap_or(&input_content);
 
-    parser
+    let report = parser
         .debug_parse(block)
-        .with_context(|| "Failed to debug-parse input")
+        .with_context(|| "Failed to debug-parse input")?;
+    Ok((report, blocks))
 }
@@ -1,4 +1,5 @@
 use assert_cmd::Command;
+use sha2::Digest;
 
 #[test]
 fn parse_file_input_emits_json_with_hostname() {
@@ -208,3 +209,1466 @@ fn parse_no_partial_stdout_on_later_failure() {
         }
     }
 }
+
+#[test]
+fn parse_var_binds_regex_and_set_adds_constant_columns() {
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .args([
+            "parse",
+            "-t",
+            "tests/fixtures/templates/prompt_var.toml",
+            "--var",
+            "hostname=core-01",
+            "--set",
+            "site=nyc",
+            "--format",
+            "json",
+            "tests/fixtures/inputs/uptime_two_hosts.txt",
+        ])
+        .output()
+        .expect("run cliscrape parse with --var/--set");
+
+    assert!(output.status.success(), "parse with --var should succeed");
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout is valid UTF-8");
+    let json: serde_json::Value =
+        serde_json::from_str(stdout.trim()).expect("stdout is valid JSON");
+
+    let records = json.as_array().expect("JSON is array");
+    assert_eq!(records.len(), 1, "only the bound hostname should match");
+    assert_eq!(records[0]["uptime"], serde_json::json!("3 weeks"));
+    assert_eq!(records[0]["site"], serde_json::json!("nyc"));
+}
+
+#[test]
+fn parse_missing_var_fails_with_hint() {
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .args([
+            "parse",
+            "-t",
+            "tests/fixtures/templates/prompt_var.toml",
+            "tests/fixtures/inputs/uptime_two_hosts.txt",
+        ])
+        .output()
+        .expect("run cliscrape parse without --var");
+
+    assert!(!output.status.success(), "unbound variable should fail");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--var hostname=VALUE"), "{stderr}");
+}
+
+#[test]
+fn parse_path_capture_adds_columns_from_file_path() {
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .args([
+            "parse",
+            "-t",
+            "templates/modern/simple_hostname.toml",
+            "--path-capture",
+            r"captures/(?P<device>[^/]+)/(?P<command>[^/.]+)\.txt$",
+            "--format",
+            "json",
+            "tests/fixtures/captures/nyc-core-01/show_hostname.txt",
+        ])
+        .output()
+        .expect("run cliscrape parse with --path-capture");
+
+    assert!(
+        output.status.success(),
+        "parse with --path-capture should succeed"
+    );
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout is valid UTF-8");
+    let json: serde_json::Value =
+        serde_json::from_str(stdout.trim()).expect("stdout is valid JSON");
+
+    let records = json.as_array().expect("JSON is array");
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0]["hostname"], serde_json::json!("FileHost"));
+    assert_eq!(records[0]["device"], serde_json::json!("nyc-core-01"));
+    assert_eq!(records[0]["command"], serde_json::json!("show_hostname"));
+}
+
+#[test]
+fn parse_path_capture_keeps_template_fields_of_the_same_name() {
+    let lines = ndjson_lines(&[
+        "parse",
+        "-t",
+        "templates/modern/simple_hostname.toml",
+        "-f",
+        "ndjson",
+        "--ndjson-warnings",
+        "--path-capture",
+        r"captures/(?P<hostname>[^/]+)/(?P<command>[^/.]+)\.txt$",
+        "tests/fixtures/captures/nyc-core-01/show_hostname.txt",
+    ]);
+
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["hostname"], serde_json::json!("FileHost"));
+    assert_eq!(lines[0]["command"], serde_json::json!("show_hostname"));
+    assert_eq!(
+        lines[1]["warning"]["kind"],
+        serde_json::json!("constant_shadowed")
+    );
+}
+
+#[test]
+fn parse_named_record_tables_emit_json_object() {
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .args([
+            "parse",
+            "-t",
+            "tests/fixtures/textfsm/chassis_ports.textfsm",
+            "--format",
+            "json",
+            "tests/fixtures/inputs/chassis_ports.txt",
+        ])
+        .output()
+        .expect("run cliscrape parse with a multi-table template");
+
+    assert!(output.status.success(), "multi-table parse should succeed");
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout is valid UTF-8");
+    let json: serde_json::Value =
+        serde_json::from_str(stdout.trim()).expect("stdout is valid JSON");
+
+    let chassis = json["chassis"].as_array().expect("chassis table");
+    assert_eq!(chassis.len(), 1);
+    assert_eq!(chassis[0]["SERIAL"], serde_json::json!("FOC123"));
+
+    let ports = json["ports"].as_array().expect("ports table");
+    assert_eq!(ports.len(), 2);
+    assert_eq!(ports[0]["CHASSIS"], serde_json::json!("sw1"));
+    assert_eq!(ports[1]["STATUS"], serde_json::json!("down"));
+}
+
+#[test]
+fn parse_named_record_tables_reject_csv() {
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .args([
+            "parse",
+            "-t",
+            "tests/fixtures/textfsm/chassis_ports.textfsm",
+            "--format",
+            "csv",
+            "tests/fixtures/inputs/chassis_ports.txt",
+        ])
+        .output()
+        .expect("run cliscrape parse with a multi-table template");
+
+    assert!(!output.status.success(), "multi-table CSV is not valid CSV");
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("2 tables (chassis, ports); use --format json or yaml"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn pipeline_joins_each_capture_separately() {
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .args([
+            "pipeline",
+            "-s",
+            "tests/fixtures/pipeline/interfaces.yaml",
+            "--format",
+            "json",
+            "tests/fixtures/pipeline/snapshot.txt",
+            "tests/fixtures/pipeline/snapshot_sw2.txt",
+        ])
+        .output()
+        .expect("run cliscrape pipeline over two captures");
+    assert!(output.status.success(), "pipeline should succeed");
+
+    let records: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("stdout is valid JSON");
+    let rows: Vec<(&str, &str, &str)> = records
+        .as_array()
+        .expect("JSON is array")
+        .iter()
+        .map(|r| {
+            (
+                r["source"].as_str().unwrap(),
+                r["INTERFACE"].as_str().unwrap(),
+                r["ip_ADDRESS"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    let (sw1, sw2) = (
+        "tests/fixtures/pipeline/snapshot.txt",
+        "tests/fixtures/pipeline/snapshot_sw2.txt",
+    );
+    assert_eq!(
+        rows,
+        [
+            (sw1, "Gi0/1", "10.0.0.1"),
+            (sw1, "Gi0/2", ""),
+            (sw2, "Gi0/1", "10.0.0.2")
+        ]
+    );
+}
+
+#[test]
+fn pipeline_joins_results_across_commands() {
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .args([
+            "pipeline",
+            "-s",
+            "tests/fixtures/pipeline/interfaces.yaml",
+            "--format",
+            "json",
+            "tests/fixtures/pipeline/snapshot.txt",
+        ])
+        .output()
+        .expect("run cliscrape pipeline");
+
+    assert!(output.status.success(), "pipeline should succeed");
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout is valid UTF-8");
+    let json: serde_json::Value =
+        serde_json::from_str(stdout.trim()).expect("stdout is valid JSON");
+
+    let records = json.as_array().expect("JSON is array");
+    assert_eq!(records.len(), 2, "left join keeps every interface");
+    assert_eq!(records[0]["INTERFACE"], serde_json::json!("Gi0/1"));
+    assert_eq!(records[0]["ip_ADDRESS"], serde_json::json!("10.0.0.1"));
+    assert_eq!(records[1]["ip_ADDRESS"], serde_json::json!(""));
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("show clock"), "unrouted block is reported");
+}
+
+#[test]
+fn parse_where_sort_and_fields_shape_csv_output() {
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .args([
+            "parse",
+            "-t",
+            "tests/fixtures/pipeline/interfaces.textfsm",
+            "--where",
+            "STATUS != admin",
+            "--sort",
+            "STATUS:desc",
+            "--fields",
+            "INTERFACE:name,STATUS",
+            "--limit",
+            "1",
+            "--format",
+            "csv",
+            "tests/fixtures/pipeline/snapshot.txt",
+        ])
+        .output()
+        .expect("run cliscrape parse with query options");
+
+    assert!(
+        output.status.success(),
+        "parse with query options should succeed"
+    );
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout is valid UTF-8");
+    assert_eq!(stdout.trim_end(), "name,STATUS\nGi0/1,up");
+}
+
+fn ndjson_lines(args: &[&str]) -> Vec<serde_json::Value> {
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .args(args)
+        .output()
+        .expect("run cliscrape parse --format ndjson");
+    assert!(output.status.success(), "ndjson parse should succeed");
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout is valid UTF-8");
+    stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("each line is a JSON value"))
+        .collect()
+}
+
+#[test]
+fn parse_ndjson_is_deterministic_across_input_order() {
+    let template = "tests/fixtures/pipeline/interfaces.textfsm";
+    let a = "tests/fixtures/pipeline/snapshot.txt";
+    let b = "tests/fixtures/inputs/chassis_ports.txt";
+
+    let forward = ndjson_lines(&["parse", "-t", template, "-f", "ndjson", a, b]);
+    let reverse = ndjson_lines(&["parse", "-t", template, "-f", "ndjson", b, a]);
+
+    assert_eq!(forward.len(), 4, "one line per record");
+    assert_eq!(forward, reverse);
+    assert!(
+        forward
+            .iter()
+            .all(|r| r.is_object() && r.get("INTERFACE").is_some())
+    );
+}
+
+#[test]
+fn parse_ndjson_interleaves_tagged_warnings() {
+    let lines = ndjson_lines(&[
+        "parse",
+        "-t",
+        "tests/fixtures/pipeline/interfaces.textfsm",
+        "-f",
+        "ndjson",
+        "--ndjson-warnings",
+        "--path-capture",
+        r"nomatch/(?P<device>[^/]+)",
+        "tests/fixtures/pipeline/snapshot.txt",
+    ]);
+
+    assert_eq!(lines.len(), 3);
+    assert_eq!(
+        lines[0]["warning"]["kind"],
+        serde_json::json!("path_capture")
+    );
+    assert_eq!(
+        lines[0]["warning"]["source"],
+        serde_json::json!("tests/fixtures/pipeline/snapshot.txt")
+    );
+    assert_eq!(lines[1]["INTERFACE"], serde_json::json!("Gi0/1"));
+}
+
+fn parse_typed_ports(format: &str) -> String {
+    parse_typed_ports_with(&["--format", format])
+}
+
+fn parse_typed_ports_with(extra: &[&str]) -> String {
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .args([
+            "parse",
+            "-t",
+            "tests/fixtures/templates/typed_ports.toml",
+            "tests/fixtures/inputs/typed_ports.txt",
+        ])
+        .args(extra)
+        .output()
+        .expect("run cliscrape parse");
+    assert!(output.status.success(), "parse {extra:?} should succeed");
+    String::from_utf8(output.stdout).expect("stdout is valid UTF-8")
+}
+
+#[test]
+fn parse_fields_order_structured_output() {
+    let fields = ["--fields", "vlans,port:name"];
+    let json = parse_typed_ports_with(&[&fields[..], &["--format", "json"]].concat());
+    assert!(
+        json.find("\"vlans\"") < json.find("\"name\""),
+        "JSON keys follow --fields: {json}"
+    );
+
+    let ndjson = parse_typed_ports_with(&[&fields[..], &["--format", "ndjson"]].concat());
+    assert_eq!(
+        ndjson.lines().next(),
+        Some(r#"{"vlans":["10","20"],"name":"Gi0/1"}"#)
+    );
+
+    let yaml = parse_typed_ports_with(&[&fields[..], &["--format", "yaml"]].concat());
+    assert!(
+        yaml.starts_with("- vlans:\n  - '10'\n  - '20'\n  name: Gi0/1\n"),
+        "YAML keys follow --fields: {yaml}"
+    );
+
+    // Without --fields, keys follow the template's column order.
+    let plain = parse_typed_ports_with(&["--format", "ndjson"]);
+    assert_eq!(
+        plain.lines().next(),
+        Some(r#"{"port":"Gi0/1","mtu":1500,"vlans":["10","20"]}"#)
+    );
+}
+
+fn typed_ports_expected() -> serde_json::Value {
+    serde_json::json!([
+        { "port": "Gi0/1", "mtu": 1500, "vlans": ["10", "20"] },
+        { "port": "Gi0/2", "mtu": 9000, "vlans": ["30"] },
+    ])
+}
+
+#[test]
+fn parse_yaml_round_trips_with_typed_values() {
+    let stdout = parse_typed_ports("yaml");
+    let records: serde_json::Value =
+        serde_yaml_ng::from_str(&stdout).expect("stdout is valid YAML");
+    assert_eq!(records, typed_ports_expected());
+}
+
+#[test]
+fn parse_toml_wraps_records_in_array_of_tables() {
+    let stdout = parse_typed_ports("toml");
+    assert!(stdout.starts_with("[[records]]"), "{stdout}");
+
+    let document: toml::Table = toml::from_str(&stdout).expect("stdout is valid TOML");
+    let records = serde_json::to_value(&document["records"]).expect("records convert to JSON");
+    assert_eq!(records, typed_ports_expected());
+}
+
+#[test]
+fn parse_json_envelope_reports_template_warnings_and_stats() {
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .args([
+            "parse",
+            "-t",
+            "tests/fixtures/templates/typed_ports.toml",
+            "--path-capture",
+            r"captures/(?P<device>[^/]+)/",
+            "--format",
+            "json-envelope",
+            "tests/fixtures/inputs/typed_ports.txt",
+        ])
+        .output()
+        .expect("run cliscrape parse --format json-envelope");
+    assert!(output.status.success(), "parse should succeed");
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout is valid UTF-8");
+    let envelope: serde_json::Value =
+        serde_json::from_str(stdout.trim()).expect("stdout is valid JSON");
+
+    assert_eq!(envelope["ok"], serde_json::json!(true));
+    assert_eq!(envelope["template"]["name"], "typed_ports.toml");
+    assert_eq!(envelope["template"]["source"], "file");
+    let template = std::fs::read("tests/fixtures/templates/typed_ports.toml").unwrap();
+    let digest: String = sha2::Sha256::digest(&template)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    assert_eq!(envelope["template"]["sha256"], serde_json::json!(digest));
+
+    assert_eq!(envelope["records"], typed_ports_expected());
+    assert_eq!(
+        envelope["sources"],
+        serde_json::json!([
+            { "name": "tests/fixtures/inputs/typed_ports.txt", "lines": 7, "records": 2 }
+        ])
+    );
+
+    let warnings = envelope["warnings"].as_array().expect("warnings array");
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0]["kind"], "path_capture");
+    assert_eq!(
+        warnings[0]["source"],
+        "tests/fixtures/inputs/typed_ports.txt"
+    );
+
+    assert_eq!(envelope["stats"]["lines"], 7);
+    assert_eq!(envelope["stats"]["records"], 2);
+    assert_eq!(envelope["stats"]["coverage"], 100.0);
+}
+
+#[test]
+fn parse_json_envelope_reports_strict_failures() {
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .args([
+            "parse",
+            "-t",
+            "tests/fixtures/templates/typed_ports.toml",
+            "--strict",
+            "--threshold",
+            "101",
+            "--format",
+            "json-envelope",
+            "tests/fixtures/inputs/typed_ports.txt",
+        ])
+        .output()
+        .expect("run cliscrape parse --strict --format json-envelope");
+    assert_eq!(output.status.code(), Some(1));
+
+    let envelope: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("stdout is a JSON envelope");
+    assert_eq!(envelope["ok"], serde_json::json!(false));
+    let error = envelope["error"].as_str().expect("error message");
+    assert!(error.contains("Failed to parse block 1"), "{error}");
+    assert!(error.contains("100.0% < 101.0%"), "{error}");
+    assert_eq!(envelope["warnings"], serde_json::json!([]));
+    assert_eq!(envelope["stats"]["lines"], 7);
+    assert_eq!(envelope["stats"]["records"], 0);
+    assert!(envelope.get("records").is_none());
+}
+
+#[cfg(not(feature = "sqlite"))]
+#[test]
+fn parse_sqlite_needs_the_sqlite_feature() {
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .args([
+            "parse",
+            "-t",
+            "tests/fixtures/templates/typed_ports.toml",
+            "--format",
+            "sqlite",
+            "--output",
+            "unused.sqlite",
+            "tests/fixtures/inputs/typed_ports.txt",
+        ])
+        .output()
+        .expect("run cliscrape parse --format sqlite");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("`sqlite` feature"));
+}
+
+#[test]
+fn parse_csv_columns_follow_template_declaration_order() {
+    let stdout = parse_typed_ports("csv");
+    assert_eq!(stdout.lines().next(), Some("port,mtu,vlans"));
+}
+
+#[test]
+fn parse_csv_list_delimiter_and_exploded_lists() {
+    let joined = parse_typed_ports_with(&["--format", "csv", "--list-delimiter", ";"]);
+    assert_eq!(
+        joined.trim_end(),
+        "port,mtu,vlans\nGi0/1,1500,10;20\nGi0/2,9000,30"
+    );
+
+    let exploded = parse_typed_ports_with(&[
+        "--format",
+        "csv",
+        "--explode-lists",
+        "--fields",
+        "port,vlans,speed",
+        "--null-text",
+        "-",
+    ]);
+    assert_eq!(
+        exploded.trim_end(),
+        "port,vlans,speed\nGi0/1,10,-\nGi0/1,20,-\nGi0/2,30,-"
+    );
+}
+
+#[test]
+fn parse_table_respects_width_and_vertical_layout() {
+    let narrow = parse_typed_ports_with(&["--format", "table", "--table-width", "20"]);
+    for line in narrow.lines() {
+        assert!(line.chars().count() <= 20, "line too wide: {line:?}");
+    }
+
+    let vertical = parse_typed_ports_with(&["--format", "table", "--table-layout", "vertical"]);
+    assert_eq!(
+        vertical.trim_end(),
+        "-[ RECORD 1 ]-\nport  | Gi0/1\nmtu   | 1500\nvlans | 10\n      | 20\n\
+         -[ RECORD 2 ]-\nport  | Gi0/2\nmtu   | 9000\nvlans | 30"
+    );
+}
+
+fn parse_typed_ports_report(format: &str) -> String {
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .args([
+            "parse",
+            "-t",
+            "tests/fixtures/templates/typed_ports.toml",
+            "--format",
+            format,
+            "tests/fixtures/inputs/typed_ports.txt",
+            "tests/fixtures/inputs/typed_ports_markup.txt",
+        ])
+        .output()
+        .expect("run cliscrape parse");
+    assert!(
+        output.status.success(),
+        "parse --format {format} should succeed"
+    );
+    String::from_utf8(output.stdout).expect("stdout is valid UTF-8")
+}
+
+#[test]
+fn parse_markdown_groups_tables_by_source() {
+    let stdout = parse_typed_ports_report("markdown");
+    assert_eq!(
+        stdout.trim_end(),
+        "## tests/fixtures/inputs/typed_ports.txt\n\n\
+         | port | mtu | vlans |\n\
+         | --- | --- | --- |\n\
+         | Gi0/1 | 1500 | 10<br>20 |\n\
+         | Gi0/2 | 9000 | 30 |\n\n\
+         ## tests/fixtures/inputs/typed_ports_markup.txt\n\n\
+         | port | mtu | vlans |\n\
+         | --- | --- | --- |\n\
+         | <Te1\\|1> | 1500 | 5 |"
+    );
+}
+
+#[test]
+fn parse_html_is_standalone_and_escaped() {
+    let stdout = parse_typed_ports_report("html");
+    assert!(stdout.starts_with("<!DOCTYPE html>"), "{stdout}");
+    assert!(stdout.trim_end().ends_with("</html>"), "{stdout}");
+    assert!(stdout.contains("<h2>tests/fixtures/inputs/typed_ports_markup.txt</h2>"));
+    assert!(stdout.contains("<td>&lt;Te1|1&gt;</td>"), "{stdout}");
+    assert!(!stdout.contains("<Te1"), "{stdout}");
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn parse_sqlite_appends_records_with_provenance() {
+    let dir = std::env::temp_dir().join(format!("cliscrape-e2e-sqlite-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let db = dir.join("facts.sqlite");
+    let _ = std::fs::remove_file(&db);
+
+    for _ in 0..2 {
+        let output = Command::cargo_bin("cliscrape")
+            .expect("cliscrape binary builds")
+            .args([
+                "parse",
+                "-t",
+                "tests/fixtures/templates/typed_ports.toml",
+                "--format",
+                "sqlite",
+                "--output",
+            ])
+            .arg(&db)
+            .arg("tests/fixtures/inputs/typed_ports.txt")
+            .output()
+            .expect("run cliscrape parse --format sqlite");
+        assert!(output.status.success(), "sqlite export should succeed");
+        assert!(output.stdout.is_empty(), "sqlite export writes no stdout");
+    }
+
+    let conn = rusqlite::Connection::open(&db).unwrap();
+    let (count, source, mtu): (i64, String, i64) = conn
+        .query_row(
+            "SELECT COUNT(*), MIN(_source), MAX(mtu) FROM typed_ports",
+            [],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+        )
+        .unwrap();
+    assert_eq!(count, 4, "second run appends");
+    assert_eq!(source, "tests/fixtures/inputs/typed_ports.txt");
+    assert_eq!(mtu, 9000);
+
+    let vlans: i64 = conn
+        .query_row("SELECT COUNT(*) FROM typed_ports_vlans", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(vlans, 6);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn parse_output_dir_writes_one_file_per_source() {
+    let dir = std::env::temp_dir().join(format!("cliscrape-e2e-outdir-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .args([
+            "parse",
+            "-t",
+            "tests/fixtures/templates/typed_ports.toml",
+            "--output-name",
+            "{parent}/{stem}.{format}",
+            "--output-dir",
+        ])
+        .arg(&dir)
+        .args([
+            "tests/fixtures/inputs/typed_ports.txt",
+            "tests/fixtures/inputs/typed_ports_transcript.txt",
+        ])
+        .output()
+        .expect("run cliscrape parse --output-dir");
+    assert!(output.status.success(), "--output-dir should succeed");
+    assert!(output.stdout.is_empty(), "--output-dir writes no stdout");
+
+    let read = |name: &str| -> serde_json::Value {
+        let text = std::fs::read_to_string(dir.join("inputs").join(name)).unwrap();
+        serde_json::from_str(&text).expect("file is valid JSON")
+    };
+    assert_eq!(read("typed_ports.json"), typed_ports_expected());
+    assert_eq!(read("typed_ports_transcript.json")[1]["port"], "Gi0/9");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn parse_output_dir_rejects_colliding_file_names() {
+    let dir = std::env::temp_dir().join(format!("cliscrape-e2e-collide-{}", std::process::id()));
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .args([
+            "parse",
+            "-t",
+            "tests/fixtures/templates/typed_ports.toml",
+            "--output-name",
+            "ports.{format}",
+            "--output-dir",
+        ])
+        .arg(&dir)
+        .args([
+            "tests/fixtures/inputs/typed_ports.txt",
+            "tests/fixtures/inputs/typed_ports_markup.txt",
+        ])
+        .output()
+        .expect("run cliscrape parse --output-dir");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("gives 'ports.json' for both"), "{stderr}");
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn parse_group_by_source_keys_records_by_source_and_block() {
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .args([
+            "parse",
+            "-t",
+            "tests/fixtures/templates/typed_ports.toml",
+            "--group-by-source",
+            "tests/fixtures/inputs/typed_ports.txt",
+            "tests/fixtures/inputs/typed_ports_transcript.txt",
+        ])
+        .output()
+        .expect("run cliscrape parse --group-by-source");
+    assert!(output.status.success(), "--group-by-source should succeed");
+
+    let document: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("stdout is valid JSON");
+    let keys: Vec<&str> = document
+        .as_object()
+        .unwrap()
+        .keys()
+        .map(String::as_str)
+        .collect();
+    assert_eq!(
+        keys,
+        [
+            "tests/fixtures/inputs/typed_ports.txt",
+            "tests/fixtures/inputs/typed_ports_transcript.txt#1",
+            "tests/fixtures/inputs/typed_ports_transcript.txt#2",
+        ]
+    );
+    assert_eq!(
+        document["tests/fixtures/inputs/typed_ports.txt"],
+        typed_ports_expected()
+    );
+    assert_eq!(
+        document["tests/fixtures/inputs/typed_ports_transcript.txt#2"][0]["port"],
+        "Gi0/9"
+    );
+}
+
+#[test]
+fn parse_openmetrics_exposes_mapped_fields_with_escaped_labels() {
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .args([
+            "parse",
+            "-t",
+            "tests/fixtures/templates/typed_ports_metrics.toml",
+            "--format",
+            "openmetrics",
+            "--set",
+            r#"site=a"b\c"#,
+            "tests/fixtures/inputs/port_counters.txt",
+        ])
+        .output()
+        .expect("run cliscrape parse --format openmetrics");
+    assert!(output.status.success(), "openmetrics should succeed");
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout is valid UTF-8");
+    assert_eq!(
+        stdout,
+        concat!(
+            "# TYPE port_mtu_bytes gauge\n",
+            "# HELP port_mtu_bytes Configured \\\"MTU\\\"\n",
+            "port_mtu_bytes{port=\"Gi0/1\",site=\"a\\\"b\\\\c\"} 1500\n",
+            "port_mtu_bytes{port=\"Gi0/2\",site=\"a\\\"b\\\\c\"} 9000\n",
+            "# TYPE port_errors counter\n",
+            "port_errors_total{port=\"Gi0/1\",site=\"a\\\"b\\\\c\"} 12345678901234\n",
+            "# EOF\n",
+        )
+    );
+}
+
+#[test]
+fn parse_openmetrics_labels_series_by_source_and_reports_duplicates() {
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .args([
+            "parse",
+            "-t",
+            "tests/fixtures/templates/typed_ports_metrics.toml",
+            "--format",
+            "openmetrics",
+            "tests/fixtures/inputs/port_counters.txt",
+            "tests/fixtures/inputs/port_counters_sw2.txt",
+        ])
+        .output()
+        .expect("run cliscrape parse --format openmetrics over two captures");
+    assert!(output.status.success(), "openmetrics should succeed");
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout is valid UTF-8");
+    let sw1 = r#"source="tests/fixtures/inputs/port_counters.txt""#;
+    let sw2 = r#"source="tests/fixtures/inputs/port_counters_sw2.txt""#;
+    let samples: Vec<&str> = stdout.lines().filter(|l| !l.starts_with('#')).collect();
+    assert_eq!(
+        samples,
+        [
+            format!(r#"port_mtu_bytes{{port="Gi0/1",{sw1}}} 1500"#),
+            format!(r#"port_mtu_bytes{{port="Gi0/2",{sw1}}} 9000"#),
+            format!(r#"port_mtu_bytes{{port="Gi0/1",{sw2}}} 1400"#),
+            format!(r#"port_errors_total{{port="Gi0/1",{sw1}}} 12345678901234"#),
+        ]
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Dropped 1 port_mtu_bytes sample(s) repeating an exposed label set"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn parse_openmetrics_requires_metrics_mapping() {
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .args([
+            "parse",
+            "-t",
+            "tests/fixtures/templates/typed_ports.toml",
+            "--format",
+            "openmetrics",
+            "tests/fixtures/inputs/typed_ports.txt",
+        ])
+        .output()
+        .expect("run cliscrape parse --format openmetrics");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("'metadata.metrics'"), "{stderr}");
+}
+
+fn diff_typed_ports(extra: &[&str]) -> std::process::Output {
+    Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .args([
+            "diff",
+            "-t",
+            "tests/fixtures/templates/typed_ports.toml",
+            "tests/fixtures/inputs/typed_ports.txt",
+            "tests/fixtures/inputs/typed_ports_after.txt",
+        ])
+        .args(extra)
+        .output()
+        .expect("run cliscrape diff")
+}
+
+#[test]
+fn diff_reports_added_and_changed_records_by_key() {
+    let output = diff_typed_ports(&["--key", "port", "--format", "json"]);
+    assert!(output.status.success(), "diff without --exit-code succeeds");
+
+    let diff: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("stdout is valid JSON");
+    assert_eq!(diff["key"], serde_json::json!(["port"]));
+    assert_eq!(diff["added"][0]["port"], "Gi0/3");
+    assert_eq!(diff["removed"], serde_json::json!([]));
+    assert_eq!(
+        diff["changed"],
+        serde_json::json!([{
+            "key": { "port": "Gi0/2" },
+            "changes": [{ "field": "mtu", "old": 9000, "new": 9216 }],
+        }])
+    );
+    assert_eq!(diff["unchanged"], 1);
+}
+
+#[test]
+fn diff_exit_code_gates_on_differences() {
+    let output = diff_typed_ports(&["--key", "port", "--format", "table", "--exit-code"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).expect("stdout is valid UTF-8");
+    assert!(
+        stdout.ends_with("1 added, 0 removed, 1 changed, 1 unchanged\n"),
+        "{stdout}"
+    );
+
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .args([
+            "diff",
+            "-t",
+            "tests/fixtures/templates/typed_ports.toml",
+            "--exit-code",
+            "tests/fixtures/inputs/typed_ports.txt",
+            "tests/fixtures/inputs/typed_ports.txt",
+        ])
+        .output()
+        .expect("run cliscrape diff");
+    assert!(output.status.success(), "identical captures exit 0");
+
+    // Errors exit 2 so a gate can tell them from differences.
+    for (template, after) in [
+        (
+            "tests/fixtures/templates/typed_ports.toml",
+            "tests/fixtures/inputs/missing.txt",
+        ),
+        (
+            "tests/fixtures/templates/missing.toml",
+            "tests/fixtures/inputs/typed_ports_after.txt",
+        ),
+    ] {
+        let output = Command::cargo_bin("cliscrape")
+            .expect("cliscrape binary builds")
+            .args([
+                "diff",
+                "-t",
+                template,
+                "--exit-code",
+                "tests/fixtures/inputs/typed_ports.txt",
+                after,
+            ])
+            .output()
+            .expect("run cliscrape diff");
+        assert_eq!(output.status.code(), Some(2), "{template} vs {after}");
+    }
+}
+
+#[test]
+fn diff_honours_transcript_options() {
+    let diff = |extra: &[&str]| {
+        let output = Command::cargo_bin("cliscrape")
+            .expect("cliscrape binary builds")
+            .args([
+                "diff",
+                "-t",
+                "tests/fixtures/templates/typed_ports.toml",
+                "--key",
+                "port",
+                "--format",
+                "json",
+            ])
+            .args(extra)
+            .args([
+                "tests/fixtures/inputs/typed_ports_session.cast",
+                "tests/fixtures/inputs/typed_ports.txt",
+            ])
+            .output()
+            .expect("run cliscrape diff");
+        assert!(output.status.success());
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).expect("stdout is valid JSON")
+    };
+
+    // Decoded, the cast holds the same two ports (with other VLANs).
+    let decoded = diff(&[]);
+    assert_eq!(decoded["added"], serde_json::json!([]));
+    assert_eq!(decoded["changed"].as_array().unwrap().len(), 2);
+
+    // Read as plain text it holds none.
+    let plain = diff(&["--input-format", "plain"]);
+    assert_eq!(plain["added"].as_array().unwrap().len(), 2);
+    assert_eq!(plain["changed"], serde_json::json!([]));
+}
+
+#[test]
+fn parse_selects_template_from_user_command_index() {
+    let data_home =
+        std::env::temp_dir().join(format!("cliscrape-e2e-index-{}", std::process::id()));
+    let templates = data_home.join("cliscrape/templates");
+    std::fs::create_dir_all(&templates).unwrap();
+    std::fs::copy(
+        "tests/fixtures/templates/typed_ports.toml",
+        templates.join("lab_show_ports.toml"),
+    )
+    .unwrap();
+    std::fs::write(
+        templates.join("index"),
+        "Template, Hostname, Platform, Command\n\nlab_show_ports.toml, .*, lab_os, sh[[ow]] po[[rts]]\n",
+    )
+    .unwrap();
+
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .env("XDG_DATA_HOME", &data_home)
+        .args([
+            "parse",
+            "--platform",
+            "lab_os",
+            "--command",
+            "sh po",
+            "--format",
+            "json",
+            "tests/fixtures/inputs/typed_ports.txt",
+        ])
+        .output()
+        .expect("run cliscrape parse --platform --command");
+    assert!(output.status.success(), "index lookup should succeed");
+    let records: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("stdout is valid JSON");
+    assert_eq!(records, typed_ports_expected());
+
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .env("XDG_DATA_HOME", &data_home)
+        .args([
+            "parse",
+            "--platform",
+            "lab_os",
+            "--command",
+            "show portable",
+            "tests/fixtures/inputs/typed_ports.txt",
+        ])
+        .output()
+        .expect("run cliscrape parse --platform --command");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("No template index entry matches"),
+        "{stderr}"
+    );
+
+    std::fs::remove_dir_all(&data_home).unwrap();
+}
+
+#[test]
+fn parse_auto_template_routes_blocks_by_echoed_command() {
+    let data_home = std::env::temp_dir().join(format!("cliscrape-e2e-auto-{}", std::process::id()));
+    let templates = data_home.join("cliscrape/templates");
+    std::fs::create_dir_all(&templates).unwrap();
+    std::fs::copy(
+        "tests/fixtures/templates/typed_ports.toml",
+        templates.join("lab_show_ports.toml"),
+    )
+    .unwrap();
+    std::fs::write(
+        templates.join("index"),
+        "Template, Hostname, Platform, Command\n\nlab_show_ports.toml, r1, lab_os, sh[[ow]] po[[rts]]\n",
+    )
+    .unwrap();
+
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .env("XDG_DATA_HOME", &data_home)
+        .args([
+            "parse",
+            "--auto-template",
+            "--format",
+            "json",
+            "tests/fixtures/inputs/auto_template_transcript.txt",
+        ])
+        .output()
+        .expect("run cliscrape parse --auto-template");
+    assert!(
+        output.status.success(),
+        "auto-template parse should succeed"
+    );
+    let tables: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("stdout is valid JSON");
+    // The prompt hostname satisfies the index Hostname filter, and `sh po` joins `show ports`.
+    let ports: Vec<&str> = tables["lab_show_ports"]
+        .as_array()
+        .expect("records keyed by the template")
+        .iter()
+        .map(|r| r["port"].as_str().unwrap())
+        .collect();
+    assert_eq!(ports, ["Gi0/1", "Gi0/9"]);
+    assert_eq!(tables.as_object().unwrap().len(), 1);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("auto_template_unrouted") && stderr.contains("show clock"),
+        "{stderr}"
+    );
+
+    // --platform narrows the index; no entry matches another platform.
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .env("XDG_DATA_HOME", &data_home)
+        .args([
+            "parse",
+            "--auto-template",
+            "--platform",
+            "cisco_ios",
+            "--format",
+            "json",
+            "tests/fixtures/inputs/auto_template_transcript.txt",
+        ])
+        .output()
+        .expect("run cliscrape parse --auto-template --platform");
+    assert!(output.status.success());
+    let tables: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("stdout is valid JSON");
+    assert_eq!(tables, serde_json::json!({}));
+
+    // The prompt hostname r1 does not pass a Hostname filter for sw switches.
+    std::fs::write(
+        templates.join("index"),
+        "Template, Hostname, Platform, Command\n\nlab_show_ports.toml, sw.*, lab_os, sh[[ow]] po[[rts]]\n",
+    )
+    .unwrap();
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .env("XDG_DATA_HOME", &data_home)
+        .args([
+            "parse",
+            "--auto-template",
+            "--format",
+            "json",
+            "tests/fixtures/inputs/auto_template_transcript.txt",
+        ])
+        .output()
+        .expect("run cliscrape parse --auto-template with a Hostname filter");
+    assert!(output.status.success());
+    let tables: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("stdout is valid JSON");
+    assert_eq!(tables, serde_json::json!({}));
+
+    std::fs::remove_dir_all(&data_home).unwrap();
+}
+
+#[test]
+fn identify_ranks_matching_template_first_and_powers_template_auto() {
+    let sample = "tests/fixtures/cisco/ios_show_version/ios_15_standard.txt";
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .args(["identify", "--format", "json", sample])
+        .output()
+        .expect("run cliscrape identify");
+    assert!(output.status.success(), "identify should succeed");
+    let ranked: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("stdout is valid JSON");
+    let ranked = ranked.as_array().expect("ranked list");
+    assert_eq!(ranked[0]["name"], "cisco_ios_show_version.yaml");
+    assert_eq!(ranked[0]["field_coverage"], 100.0);
+    assert!(
+        ranked.iter().all(|c| c["records"].as_u64().unwrap() > 0),
+        "templates without records are hidden unless --all"
+    );
+
+    let parse = |template: &str| {
+        let output = Command::cargo_bin("cliscrape")
+            .expect("cliscrape binary builds")
+            .args(["parse", "-t", template, "--format", "json", sample])
+            .output()
+            .expect("run cliscrape parse");
+        assert!(
+            output.status.success(),
+            "parse -t {template} should succeed"
+        );
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).expect("stdout is valid JSON")
+    };
+    assert_eq!(parse("auto"), parse("cisco_ios_show_version"));
+}
+
+#[test]
+fn parse_detects_junos_prompts_unless_dialect_is_overridden() {
+    let parse = |dialect: &str| {
+        let output = Command::cargo_bin("cliscrape")
+            .expect("cliscrape binary builds")
+            .args([
+                "parse",
+                "-t",
+                "tests/fixtures/templates/typed_ports.toml",
+                "--group-by-source",
+                "--transcript-dialect",
+                dialect,
+                "tests/fixtures/inputs/typed_ports_junos.txt",
+            ])
+            .output()
+            .expect("run cliscrape parse --transcript-dialect");
+        assert!(output.status.success(), "--transcript-dialect {dialect}");
+        let document: serde_json::Value =
+            serde_json::from_slice(&output.stdout).expect("stdout is valid JSON");
+        document
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        parse("auto"),
+        [
+            "tests/fixtures/inputs/typed_ports_junos.txt#1",
+            "tests/fixtures/inputs/typed_ports_junos.txt#2",
+        ]
+    );
+    // IOS prompts never match, so the capture stays one block.
+    assert_eq!(
+        parse("ios"),
+        ["tests/fixtures/inputs/typed_ports_junos.txt"]
+    );
+}
+
+#[test]
+fn parse_command_column_and_errors_use_transcript_blocks() {
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .args([
+            "parse",
+            "-t",
+            "tests/fixtures/templates/typed_ports.toml",
+            "--command-column",
+            "command",
+            "--fields",
+            "port,command",
+            "tests/fixtures/inputs/typed_ports_junos.txt",
+        ])
+        .output()
+        .expect("run cliscrape parse --command-column");
+    assert!(output.status.success());
+    let records: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("stdout is valid JSON");
+    assert_eq!(
+        records,
+        serde_json::json!([
+            {"port": "Gi0/1", "command": "show ports"},
+            {"port": "Gi0/9", "command": "show ports"},
+        ])
+    );
+
+    // Error lines count from the top of the transcript, not the second block.
+    let dir = std::env::temp_dir().join(format!("cliscrape-e2e-blocks-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let template = dir.join("reject_small_mtu.textfsm");
+    std::fs::write(
+        &template,
+        "Value PORT (\\S+)\n\nStart\n  ^Port \\S+ mtu 100$$ -> Error\n  ^Port ${PORT} -> Record\n",
+    )
+    .unwrap();
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .args(["parse", "-t"])
+        .arg(&template)
+        .arg("tests/fixtures/inputs/typed_ports_junos.txt")
+        .output()
+        .expect("run cliscrape parse with an Error rule");
+    std::fs::remove_dir_all(&dir).ok();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("block 2 ('show ports' at lines 8-11)"),
+        "stderr: {stderr}"
+    );
+    assert!(
+        stderr.contains("Parsing error at line 8:"),
+        "stderr: {stderr}"
+    );
+}
+
+#[test]
+fn parse_cleans_pager_redraws_and_optionally_syslog_lines() {
+    let parse = |extra: &[&str]| {
+        let output = Command::cargo_bin("cliscrape")
+            .expect("cliscrape binary builds")
+            .args([
+                "parse",
+                "-t",
+                "tests/fixtures/templates/typed_ports.toml",
+                "--fields",
+                "port",
+            ])
+            .args(extra)
+            .arg("tests/fixtures/inputs/typed_ports_pager.txt")
+            .output()
+            .expect("run cliscrape parse on a paged capture");
+        assert!(output.status.success());
+        let records: serde_json::Value =
+            serde_json::from_slice(&output.stdout).expect("stdout is valid JSON");
+        (
+            records,
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        )
+    };
+
+    let (records, stderr) = parse(&[]);
+    assert_eq!(
+        records,
+        serde_json::json!([{"port": "Gi0/1"}, {"port": "Gi0/2"}])
+    );
+    assert!(stderr.contains("Replayed backspace/carriage-return overwrites on 1 line(s)"));
+    assert!(stderr.contains("Found 1 syslog-like line(s)"), "{stderr}");
+
+    let (_, stderr) = parse(&["--strip-syslog"]);
+    assert!(stderr.contains("Removed 1 syslog line(s)"), "{stderr}");
+
+    // Without cleanup the pager prompt hides the second port line.
+    let (records, _) = parse(&["--keep-terminal-noise"]);
+    assert_eq!(records[1], serde_json::json!({"port": ""}));
+}
+
+#[test]
+fn parse_decodes_asciicast_session_logs_unless_input_format_overrides() {
+    let parse = |extra: &[&str]| {
+        let output = Command::cargo_bin("cliscrape")
+            .expect("cliscrape binary builds")
+            .args([
+                "parse",
+                "-t",
+                "tests/fixtures/templates/typed_ports.toml",
+                "--fields",
+                "port",
+            ])
+            .args(extra)
+            .arg("tests/fixtures/inputs/typed_ports_session.cast")
+            .output()
+            .expect("run cliscrape parse on an asciinema cast");
+        assert!(output.status.success());
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).expect("stdout is valid JSON")
+    };
+
+    assert_eq!(
+        parse(&[]),
+        serde_json::json!([{"port": "Gi0/1"}, {"port": "Gi0/2"}])
+    );
+    assert_eq!(parse(&["--input-format", "plain"]), serde_json::json!([]));
+}
+
+#[test]
+fn parse_keeps_iso_dates_of_plain_log_output() {
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .args([
+            "parse",
+            "-t",
+            "tests/fixtures/templates/log_dates.toml",
+            "--fields",
+            "date",
+            "tests/fixtures/inputs/iso_dated_log.txt",
+        ])
+        .output()
+        .expect("run cliscrape parse on ISO-dated log lines");
+    assert!(output.status.success());
+    let records: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("stdout is valid JSON");
+    assert_eq!(records.as_array().map(Vec::len), Some(3), "{records}");
+    assert_eq!(records[0]["date"], "2024-01-15");
+}
+
+#[test]
+fn parse_splits_show_tech_sections_into_command_blocks() {
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .args([
+            "parse",
+            "-t",
+            "tests/fixtures/templates/typed_ports.toml",
+            "--group-by-source",
+            "--command-column",
+            "command",
+            "--fields",
+            "port,command",
+            "tests/fixtures/inputs/typed_ports_show_tech.txt",
+        ])
+        .output()
+        .expect("run cliscrape parse on show tech-support output");
+    assert!(output.status.success());
+    let document: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("stdout is valid JSON");
+
+    let key = |n: usize| format!("tests/fixtures/inputs/typed_ports_show_tech.txt#{n}");
+    assert_eq!(document.as_object().unwrap().len(), 3);
+    assert_eq!(document[key(1)], serde_json::json!([]));
+    assert_eq!(
+        document[key(2)],
+        serde_json::json!([
+            {"port": "Gi0/1", "command": "show ports"},
+            {"port": "Gi0/2", "command": "show ports"},
+        ])
+    );
+}
+
+#[test]
+fn parse_bundle_routes_host_command_outputs_through_template_map() {
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .args([
+            "parse",
+            "--bundle",
+            "tests/fixtures/inputs/typed_ports_bundle.json",
+            "--template-map",
+            "show ports=tests/fixtures/templates/typed_ports.toml",
+            "--fields",
+            "host,command,port",
+            "--format",
+            "json",
+        ])
+        .output()
+        .expect("run cliscrape parse --bundle");
+    assert!(output.status.success(), "bundle parse should succeed");
+    let tables: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("stdout is valid JSON");
+    assert_eq!(
+        tables,
+        serde_json::json!({
+            "typed_ports": [
+                {"host": "sw1", "command": "show ports", "port": "Gi0/1"},
+                {"host": "sw2", "command": "show ports", "port": "Gi0/2"},
+                {"host": "sw2", "command": "show ports", "port": "Gi0/3"},
+            ]
+        })
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("'show vlan' on sw2 is null"),
+        "non-text outputs are reported: {stderr}"
+    );
+    assert!(
+        stderr.contains("auto_template_unrouted") && stderr.contains("[sw1] (show clock)"),
+        "{stderr}"
+    );
+
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .args([
+            "parse",
+            "-t",
+            "tests/fixtures/templates/typed_ports.toml",
+            "--template-map",
+            "show ports=tests/fixtures/templates/typed_ports.toml",
+            "tests/fixtures/inputs/typed_ports.txt",
+        ])
+        .output()
+        .expect("run cliscrape parse --template-map without routing");
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("--template-map needs --auto-template or --bundle")
+    );
+}
+
+#[test]
+fn parse_bundle_keeps_template_fields_named_like_bundle_columns() {
+    let output = Command::cargo_bin("cliscrape")
+        .expect("cliscrape binary builds")
+        .args([
+            "parse",
+            "--bundle",
+            "tests/fixtures/inputs/neighbor_bundle.json",
+            "--template-map",
+            "show neighbors=tests/fixtures/templates/neighbor_hosts.toml",
+            "--format",
+            "json",
+        ])
+        .output()
+        .expect("run cliscrape parse --bundle with a host field");
+    assert!(
+        output.status.success(),
+        "a template field named host should not collide: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let tables: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("stdout is valid JSON");
+    assert_eq!(
+        tables,
+        serde_json::json!({
+            "neighbor_hosts": [
+                {"host": "core1", "command": "show neighbors", "port": "Gi0/1"},
+                {"host": "core2", "command": "show neighbors", "port": "Gi0/2"},
+            ]
+        })
+    );
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("constant_shadowed"),
+        "the dropped bundle host is reported"
+    );
+}
@@ -15,6 +15,8 @@ fn test_template_from_ir_expands_macros_in_rule_regex() {
             required: false,
             list: false,
             type_hint: None,
+            optional: false,
+            tables: Vec::new(),
         },
     );
 
@@ -28,6 +30,7 @@ fn test_template_from_ir_expands_macros_in_rule_regex() {
                 line_action: Action::Next,
                 record_action: Action::Record,
                 next_state: None,
+                table: None,
             }],
         },
     );
@@ -36,6 +39,10 @@ fn test_template_from_ir_expands_macros_in_rule_regex() {
         values,
         states,
         macros: HashMap::new(),
+        coverage_threshold: None,
+        field_order: Vec::new(),
+        columns: Vec::new(),
+        metrics: None,
     };
 
     let template = Template::from_ir(ir).unwrap();